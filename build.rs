@@ -1,3 +1,68 @@
+use std::{env, fs, path::Path};
+
 fn main() {
     println!("cargo:rerun-if-changed=examples/");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    write_grammar_versions();
+}
+
+/// Generates the table of the tree-sitter grammar crates that Mergiraf depends on, along with
+/// their versions as resolved in the lock file, for `mergiraf languages --grammar-versions`.
+///
+/// If the lock file isn't available (for instance because Mergiraf is built as a dependency
+/// of another crate), the version requirements of the manifest are used instead.
+fn write_grammar_versions() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set by Cargo");
+    let manifest_dir = Path::new(&manifest_dir);
+    let manifest = fs::read_to_string(manifest_dir.join("Cargo.toml")).unwrap_or_default();
+    let lock_file = fs::read_to_string(manifest_dir.join("Cargo.lock")).unwrap_or_default();
+
+    let mut table = String::from("&[\n");
+    for (name, requirement) in grammar_dependencies(&manifest) {
+        let version = locked_version(&lock_file, name).unwrap_or(requirement);
+        table.push_str(&format!("    ({name:?}, {version:?}),\n"));
+    }
+    table.push(']');
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by Cargo");
+    fs::write(Path::new(&out_dir).join("grammar_versions.rs"), table)
+        .expect("could not write the table of grammar versions");
+}
+
+/// The names and version requirements of the grammar crates among the dependencies
+fn grammar_dependencies(manifest: &str) -> Vec<(&str, &str)> {
+    let mut in_dependencies = false;
+    let mut dependencies = Vec::new();
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_dependencies = line == "[dependencies]";
+        } else if in_dependencies
+            && let Some((name, requirement)) = line.split_once('=')
+            && let name = name.trim()
+            && name.starts_with("tree-sitter-")
+        {
+            // only the simple `name = "version"` form is used for grammars
+            dependencies.push((name, requirement.trim().trim_matches('"')));
+        }
+    }
+    dependencies.sort_unstable();
+    dependencies
+}
+
+/// The version of a package in the lock file
+fn locked_version<'a>(lock_file: &'a str, package: &str) -> Option<&'a str> {
+    let mut lines = lock_file.lines();
+    while let Some(line) = lines.next() {
+        if line
+            .strip_prefix("name = ")
+            .map(|name| name.trim_matches('"'))
+            == Some(package)
+        {
+            let version = lines.next()?.strip_prefix("version = ")?;
+            return Some(version.trim_matches('"'));
+        }
+    }
+    None
 }
@@ -0,0 +1,8 @@
+#!/bin/sh
+set -e
+
+build() {
+    cargo build --release
+}
+
+build
@@ -0,0 +1,16 @@
+#!/bin/sh
+set -e
+
+build() {
+    cargo build --release
+}
+
+lint() {
+    cargo clippy -- -D warnings
+}
+
+test() {
+    cargo test
+}
+
+build
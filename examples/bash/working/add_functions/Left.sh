@@ -0,0 +1,12 @@
+#!/bin/sh
+set -e
+
+build() {
+    cargo build --release
+}
+
+lint() {
+    cargo clippy -- -D warnings
+}
+
+build
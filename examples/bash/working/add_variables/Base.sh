@@ -0,0 +1,5 @@
+#!/bin/sh
+IMAGE=registry.example.com/app
+TAG=latest
+
+docker build -t "$IMAGE:$TAG" .
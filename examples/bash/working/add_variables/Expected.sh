@@ -0,0 +1,7 @@
+#!/bin/sh
+IMAGE=registry.example.com/app
+TAG=v1.2
+PLATFORM=linux/amd64
+CONTEXT=.
+
+docker build -t "$IMAGE:$TAG" .
@@ -0,0 +1,6 @@
+#!/bin/sh
+IMAGE=registry.example.com/app
+TAG=latest
+PLATFORM=linux/amd64
+
+docker build -t "$IMAGE:$TAG" .
@@ -0,0 +1,6 @@
+#!/bin/sh
+IMAGE=registry.example.com/app
+TAG=v1.2
+CONTEXT=.
+
+docker build -t "$IMAGE:$TAG" .
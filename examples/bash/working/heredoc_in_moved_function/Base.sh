@@ -0,0 +1,11 @@
+#!/bin/bash
+
+greet() {
+  cat <<EOF
+Hello
+  world
+EOF
+  echo done
+}
+
+greet
@@ -0,0 +1,13 @@
+#!/bin/bash
+
+if [ -n "$GREET" ]; then
+  greet() {
+    cat <<EOF
+Hi
+  world
+EOF
+    echo finished
+  }
+
+  greet
+fi
@@ -0,0 +1,13 @@
+#!/bin/bash
+
+if [ -n "$GREET" ]; then
+  greet() {
+    cat <<EOF
+Hello
+  world
+EOF
+    echo done
+  }
+
+  greet
+fi
@@ -0,0 +1,11 @@
+#!/bin/bash
+
+greet() {
+  cat <<EOF
+Hi
+  world
+EOF
+  echo finished
+}
+
+greet
@@ -0,0 +1,9 @@
+class Foo
+{
+    [Obsolete]
+    public void Run() { }
+
+    enum Color { Red = 1, Green = 2 }
+
+    enum Size { Small, Large }
+}
@@ -0,0 +1,17 @@
+class Foo
+{
+    [Obsolete]
+    [Serializable]
+    [Conditional("DEBUG")]
+    public void Run() { }
+
+    enum Color { Red = 1, Green = 2, Blue = 3, Yellow = 4 }
+
+<<<<<<< LEFT
+    enum Size { Small, Large, Huge }
+||||||| BASE
+    enum Size { Small, Large }
+=======
+    enum Size { Small, Large, Tiny }
+>>>>>>> RIGHT
+}
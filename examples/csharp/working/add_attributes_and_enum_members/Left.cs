@@ -0,0 +1,10 @@
+class Foo
+{
+    [Obsolete]
+    [Serializable]
+    public void Run() { }
+
+    enum Color { Red = 1, Green = 2, Blue = 3 }
+
+    enum Size { Small, Large, Huge }
+}
@@ -0,0 +1,10 @@
+class Foo
+{
+    [Obsolete]
+    [Conditional("DEBUG")]
+    public void Run() { }
+
+    enum Color { Red = 1, Green = 2, Yellow = 4 }
+
+    enum Size { Small, Large, Tiny }
+}
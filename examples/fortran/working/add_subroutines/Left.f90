@@ -0,0 +1,26 @@
+module geometry
+  use iso_fortran_env, only: real64
+  use constants
+  implicit none
+contains
+  subroutine area(r, a)
+    real(real64), intent(in) :: r
+    real(real64), intent(out) :: a
+    a = pi * r * r
+  end subroutine area
+
+  function perimeter(r) result(p)
+    real(real64), intent(in) :: r
+    real(real64) :: p
+    p = 2 * pi * r
+  end function perimeter
+
+  subroutine volume(r, v)
+    real(real64), intent(in) :: r
+    real(real64), intent(out) :: v
+    v = 4.0 / 3.0 * pi * r**3
+  end subroutine volume
+end module geometry
+
+subroutine standalone()
+end subroutine standalone
@@ -0,0 +1,13 @@
+module MyApp.Main
+
+open System
+open System.IO
+open System.Text
+open System.Threading
+
+let greet name = printfn "Hello %s" name
+
+[<EntryPoint>]
+let main argv =
+    greet "world"
+    0
@@ -0,0 +1,12 @@
+module MyApp.Main
+
+open System
+open System.IO
+open System.Text
+
+let greet name = printfn "Hello %s" name
+
+[<EntryPoint>]
+let main argv =
+    greet "world"
+    0
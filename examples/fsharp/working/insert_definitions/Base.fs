@@ -0,0 +1,11 @@
+module MyApp.Main
+
+open System
+open System.IO
+
+let greet name = printfn "Hello %s" name
+
+[<EntryPoint>]
+let main argv =
+    greet "world"
+    0
@@ -0,0 +1,18 @@
+module MyApp.Main
+
+open System
+open System.IO
+
+let greet name = printfn "Hello %s" name
+
+<<<<<<< LEFT
+let farewell name = printfn "Bye %s" name
+||||||| BASE
+=======
+let shout name = printfn "HELLO %s" name
+>>>>>>> RIGHT
+
+[<EntryPoint>]
+let main argv =
+    greet "world"
+    0
@@ -0,0 +1,13 @@
+module MyApp.Main
+
+open System
+open System.IO
+
+let greet name = printfn "Hello %s" name
+
+let farewell name = printfn "Bye %s" name
+
+[<EntryPoint>]
+let main argv =
+    greet "world"
+    0
@@ -0,0 +1,13 @@
+module MyApp.Main
+
+open System
+open System.IO
+
+let greet name = printfn "Hello %s" name
+
+let shout name = printfn "HELLO %s" name
+
+[<EntryPoint>]
+let main argv =
+    greet "world"
+    0
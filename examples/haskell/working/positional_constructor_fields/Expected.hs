@@ -0,0 +1,9 @@
+module Shape where
+
+<<<<<<< LEFT
+data Shape = Rect Double Double Bool
+||||||| BASE
+data Shape = Rect Double Double 
+=======
+data Shape = Rect Double Double Int
+>>>>>>> RIGHT
@@ -0,0 +1,3 @@
+module Shape where
+
+data Shape = Rect Double Double Bool
@@ -0,0 +1,6 @@
+module Person where
+
+data Person = Person
+  { name :: String
+  , age :: Int
+  }
@@ -0,0 +1,8 @@
+module Person where
+
+data Person = Person
+  { name :: String
+  , age :: Int
+  , email :: String
+  , phone :: Maybe String
+  }
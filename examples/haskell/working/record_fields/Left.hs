@@ -0,0 +1,7 @@
+module Person where
+
+data Person = Person
+  { name :: String
+  , age :: Int
+  , email :: String
+  }
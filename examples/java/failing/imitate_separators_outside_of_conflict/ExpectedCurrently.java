@@ -1,7 +0,0 @@
-
-public interface Jsonizable {
-        String getId();
-        String serialize();
-
-        void prettyPrint();
-}
@@ -0,0 +1,16 @@
+class A {
+    void a() {
+    }
+
+
+    void b() {
+    }
+
+
+    void c() {
+    }
+
+
+    void d() {
+    }
+}
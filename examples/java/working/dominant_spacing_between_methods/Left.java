@@ -0,0 +1,12 @@
+class A {
+    void a() {
+    }
+
+
+    void b() {
+    }
+
+
+    void c() {
+    }
+}
@@ -0,0 +1,11 @@
+class A {
+    void a() {
+    }
+
+
+    void b() {
+    }
+
+    void d() {
+    }
+}
@@ -0,0 +1,14 @@
+enum Color {
+    RED,
+<<<<<<< LEFT
+    GREEN,
+    BLUE;
+||||||| BASE
+    GREEN;
+=======
+    GREEN,
+    YELLOW;
+>>>>>>> RIGHT
+
+    int x;
+}
@@ -0,0 +1,7 @@
+enum Color {
+    RED,
+    GREEN,
+    YELLOW;
+
+    int x;
+}
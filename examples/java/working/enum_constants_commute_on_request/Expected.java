@@ -0,0 +1,8 @@
+enum Color {
+    RED,
+    GREEN,
+    BLUE,
+    YELLOW;
+
+    int x;
+}
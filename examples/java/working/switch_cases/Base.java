@@ -0,0 +1,8 @@
+class A {
+    void f(int x) {
+        switch (x) {
+            case 1:
+                a();
+        }
+    }
+}
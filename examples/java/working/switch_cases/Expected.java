@@ -0,0 +1,17 @@
+class A {
+    void f(int x) {
+        switch (x) {
+            case 1:
+                a();
+                break;
+<<<<<<< LEFT
+            case 2:
+                b();
+||||||| BASE
+=======
+            default:
+                c();
+>>>>>>> RIGHT
+        }
+    }
+}
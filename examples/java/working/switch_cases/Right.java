@@ -0,0 +1,11 @@
+class A {
+    void f(int x) {
+        switch (x) {
+            case 1:
+                a();
+                break;
+            default:
+                c();
+        }
+    }
+}
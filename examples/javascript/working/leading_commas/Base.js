@@ -0,0 +1,4 @@
+const config = {
+  a: 1
+  , b: 2
+};
@@ -0,0 +1,6 @@
+const config = {
+  a: 1
+  , b: 2
+  , c: 3
+  , d: 4
+};
@@ -0,0 +1,5 @@
+const config = {
+  a: 1
+  , b: 2
+  , d: 4
+};
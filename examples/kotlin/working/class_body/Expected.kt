@@ -1,6 +1,5 @@
 object MergeAlgorithm {
     var diff3 = false
     var verbose = true
-
     var timeout = 1000
 }
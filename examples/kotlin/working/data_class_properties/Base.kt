@@ -0,0 +1 @@
+data class Point(val x: Int)
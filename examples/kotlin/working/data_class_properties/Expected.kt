@@ -0,0 +1,7 @@
+<<<<<<< LEFT
+data class Point(val x: Int, val y: Int)
+||||||| BASE
+data class Point(val x: Int)
+=======
+data class Point(val x: Int, val z: Int)
+>>>>>>> RIGHT
@@ -0,0 +1,12 @@
+enum class Color {
+    RED,
+<<<<<<< LEFT
+    GREEN,
+    BLUE
+||||||| BASE
+    GREEN
+=======
+    GREEN,
+    YELLOW
+>>>>>>> RIGHT
+}
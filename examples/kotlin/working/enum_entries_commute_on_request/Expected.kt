@@ -0,0 +1,6 @@
+enum class Color {
+    RED,
+    GREEN,
+    BLUE,
+    YELLOW
+}
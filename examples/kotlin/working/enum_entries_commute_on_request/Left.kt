@@ -0,0 +1,5 @@
+enum class Color {
+    RED,
+    GREEN,
+    BLUE
+}
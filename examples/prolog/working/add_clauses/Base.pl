@@ -0,0 +1,6 @@
+parent(tom, bob).
+parent(bob, ann).
+
+grandparent(X, Z) :-
+    parent(X, Y),
+    parent(Y, Z).
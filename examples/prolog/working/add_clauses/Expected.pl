@@ -0,0 +1,7 @@
+parent(tom, bob).
+parent(bob, liz).
+parent(ann, jim).
+
+grandparent(X, Z) :-
+    parent(X, Y),
+    parent(Y, Z).
@@ -0,0 +1,7 @@
+parent(tom, bob).
+parent(bob, ann).
+parent(ann, jim).
+
+grandparent(X, Z) :-
+    parent(X, Y),
+    parent(Y, Z).
@@ -0,0 +1,4 @@
+:- module(family, [parent/2]).
+:- use_module(library(lists), [member/2]).
+
+parent(tom, bob).
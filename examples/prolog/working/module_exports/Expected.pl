@@ -0,0 +1,13 @@
+:- module(family, [parent/2, grandparent/2, sibling/2]).
+:- use_module(library(lists), [member/2, append/3, last/2]).
+
+sibling(X, Y) :-
+    parent(P, X),
+    parent(P, Y),
+    X \== Y.
+
+parent(tom, bob).
+
+grandparent(X, Z) :-
+    parent(X, Y),
+    parent(Y, Z).
@@ -0,0 +1,8 @@
+:- module(family, [parent/2, grandparent/2]).
+:- use_module(library(lists), [member/2, append/3]).
+
+parent(tom, bob).
+
+grandparent(X, Z) :-
+    parent(X, Y),
+    parent(Y, Z).
@@ -0,0 +1,9 @@
+:- module(family, [parent/2, sibling/2]).
+:- use_module(library(lists), [member/2, last/2]).
+
+sibling(X, Y) :-
+    parent(P, X),
+    parent(P, Y),
+    X \== Y.
+
+parent(tom, bob).
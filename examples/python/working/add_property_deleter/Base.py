@@ -0,0 +1,11 @@
+class A:
+    @property
+    def x(self):
+        return self._x
+
+    @x.setter
+    def x(self, value):
+        self._x = value
+
+    def f(self):
+        pass
@@ -0,0 +1,15 @@
+class A:
+    @property
+    def x(self):
+        return self._x
+
+    @x.setter
+    def x(self, value):
+        self._x = value
+
+    def f(self):
+        pass
+
+    @x.deleter
+    def x(self):
+        del self._x
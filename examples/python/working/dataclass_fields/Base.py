@@ -0,0 +1,7 @@
+@dataclass
+class Point:
+    x: int
+    y: int
+
+    def norm(self):
+        return abs(self.x) + abs(self.y)
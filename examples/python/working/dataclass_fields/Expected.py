@@ -0,0 +1,19 @@
+@dataclass
+class Point:
+    x: int
+    y: int
+<<<<<<< LEFT
+    z: int = 0
+||||||| BASE
+=======
+    label: str = ""
+>>>>>>> RIGHT
+
+    def norm(self):
+        return abs(self.x) + abs(self.y)
+
+    def flip(self):
+        return Point(self.y, self.x)
+
+    def double(self):
+        return Point(2 * self.x, 2 * self.y)
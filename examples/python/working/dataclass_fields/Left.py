@@ -0,0 +1,11 @@
+@dataclass
+class Point:
+    x: int
+    y: int
+    z: int = 0
+
+    def norm(self):
+        return abs(self.x) + abs(self.y)
+
+    def flip(self):
+        return Point(self.y, self.x)
@@ -0,0 +1,11 @@
+@dataclass
+class Point:
+    x: int
+    y: int
+    label: str = ""
+
+    def norm(self):
+        return abs(self.x) + abs(self.y)
+
+    def double(self):
+        return Point(2 * self.x, 2 * self.y)
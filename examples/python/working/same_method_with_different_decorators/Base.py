@@ -0,0 +1,6 @@
+from functools import cache, lru_cache
+
+
+class Repository:
+    def __init__(self, path):
+        self.path = path
@@ -0,0 +1,21 @@
+from functools import cache, lru_cache
+
+
+class Repository:
+    def __init__(self, path):
+        self.path = path
+<<<<<<< LEFT
+
+    @cache
+    def head(self):
+        return self.resolve("HEAD")
+
+||||||| BASE
+
+=======
+
+    @lru_cache
+    def head(self):
+        return self.resolve("HEAD")
+
+>>>>>>> RIGHT
\ No newline at end of file
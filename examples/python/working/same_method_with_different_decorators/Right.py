@@ -0,0 +1,10 @@
+from functools import cache, lru_cache
+
+
+class Repository:
+    def __init__(self, path):
+        self.path = path
+
+    @lru_cache
+    def head(self):
+        return self.resolve("HEAD")
@@ -1,5 +1,6 @@
 mod tests {
     pub fn foo() {}
+
     pub fn bar() {}
 
     #[cfg(feature = "tracing")]
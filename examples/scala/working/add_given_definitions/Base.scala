@@ -0,0 +1,7 @@
+package shop
+
+import scala.math.Ordering
+
+given priceOrdering: Ordering[Price] = Ordering.by(_.amount)
+
+case class Price(amount: Int)
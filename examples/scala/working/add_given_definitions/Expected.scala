@@ -0,0 +1,9 @@
+package shop
+
+import scala.math.Ordering
+
+given priceOrdering: Ordering[Price] = Ordering.by(_.amount)
+given itemOrdering: Ordering[Item] = Ordering.by(_.name)
+given Ordering[Stock] = Ordering.by(_.count)
+
+case class Price(amount: Int)
@@ -0,0 +1,8 @@
+package shop
+
+import scala.math.Ordering
+
+given priceOrdering: Ordering[Price] = Ordering.by(_.amount)
+given itemOrdering: Ordering[Item] = Ordering.by(_.name)
+
+case class Price(amount: Int)
@@ -0,0 +1,8 @@
+package shop
+
+import scala.math.Ordering
+
+given priceOrdering: Ordering[Price] = Ordering.by(_.amount)
+given Ordering[Stock] = Ordering.by(_.count)
+
+case class Price(amount: Int)
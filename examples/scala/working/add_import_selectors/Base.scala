@@ -0,0 +1,7 @@
+package shop
+
+import scala.collection.mutable.{ArrayBuffer, Map => MutableMap}
+
+object Inventory:
+  val items = ArrayBuffer.empty[String]
+  val counts = MutableMap.empty[String, Int]
@@ -0,0 +1,9 @@
+package shop
+
+import scala.collection.mutable.{ArrayBuffer, Map => MutableMap, Set => MutableSet, ListBuffer}
+
+object Inventory:
+  val log = ListBuffer.empty[String]
+  val items = ArrayBuffer.empty[String]
+  val counts = MutableMap.empty[String, Int]
+  val seen = MutableSet.empty[String]
@@ -0,0 +1,8 @@
+package shop
+
+import scala.collection.mutable.{ArrayBuffer, Map => MutableMap, Set => MutableSet}
+
+object Inventory:
+  val items = ArrayBuffer.empty[String]
+  val counts = MutableMap.empty[String, Int]
+  val seen = MutableSet.empty[String]
@@ -0,0 +1,8 @@
+package shop
+
+import scala.collection.mutable.{ArrayBuffer, Map => MutableMap, ListBuffer}
+
+object Inventory:
+  val log = ListBuffer.empty[String]
+  val items = ArrayBuffer.empty[String]
+  val counts = MutableMap.empty[String, Int]
@@ -12,6 +12,7 @@ py_library(
     name = "python_lib",
     srcs = ["python_lib.py"],
 )
+
 rust_library(
     name = "rust_lib",
     srcs = ["src/lib.rs"],
@@ -0,0 +1,11 @@
+class Circle {
+    let radius: Double
+
+    init(radius: Double) {
+        self.radius = radius
+    }
+
+    func area() -> Double {
+        return 3.14 * radius * radius
+    }
+}
@@ -0,0 +1,21 @@
+class Circle {
+    let radius: Double
+    var name: String = "circle"
+    var color: String?
+
+    init(radius: Double) {
+        self.radius = radius
+    }
+
+    func area() -> Double {
+        return 3.14 * radius * radius
+    }
+
+    func scale(by factor: Double) -> Circle {
+        return Circle(radius: radius * factor)
+    }
+
+    func scale(by factor: Int) -> Circle {
+        return Circle(radius: radius * Double(factor))
+    }
+}
@@ -0,0 +1,16 @@
+class Circle {
+    let radius: Double
+    var name: String = "circle"
+
+    init(radius: Double) {
+        self.radius = radius
+    }
+
+    func area() -> Double {
+        return 3.14 * radius * radius
+    }
+
+    func scale(by factor: Double) -> Circle {
+        return Circle(radius: radius * factor)
+    }
+}
@@ -0,0 +1,16 @@
+class Circle {
+    let radius: Double
+    var color: String?
+
+    init(radius: Double) {
+        self.radius = radius
+    }
+
+    func area() -> Double {
+        return 3.14 * radius * radius
+    }
+
+    func scale(by factor: Int) -> Circle {
+        return Circle(radius: radius * Double(factor))
+    }
+}
@@ -0,0 +1,5 @@
+import Foundation
+
+func greet(name: String) -> String {
+    return "Hello, \(name)"
+}
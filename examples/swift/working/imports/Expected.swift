@@ -0,0 +1,7 @@
+import Foundation
+import UIKit
+import Combine
+
+func greet(name: String) -> String {
+    return "Hello, \(name)"
+}
@@ -0,0 +1,6 @@
+import Foundation
+import Combine
+
+func greet(name: String) -> String {
+    return "Hello, \(name)"
+}
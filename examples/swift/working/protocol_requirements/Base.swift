@@ -0,0 +1,4 @@
+protocol Shape {
+    var name: String { get }
+    func area() -> Double
+}
@@ -0,0 +1,6 @@
+protocol Shape {
+    var name: String { get }
+    var color: String? { get set }
+    func area() -> Double
+    func perimeter() -> Double
+}
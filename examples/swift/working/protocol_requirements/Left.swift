@@ -0,0 +1,5 @@
+protocol Shape {
+    var name: String { get }
+    func area() -> Double
+    func perimeter() -> Double
+}
@@ -0,0 +1,5 @@
+protocol Shape {
+    var name: String { get }
+    var color: String? { get set }
+    func area() -> Double
+}
@@ -0,0 +1,4 @@
+struct Point {
+    var x: Double
+    var y: Double
+}
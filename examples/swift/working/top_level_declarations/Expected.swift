@@ -0,0 +1,13 @@
+struct Point {
+    var x: Double
+    var y: Double
+}
+
+extension Point: Equatable {
+}
+
+extension Point: CustomStringConvertible {
+    var description: String {
+        return "(\(x), \(y))"
+    }
+}
@@ -0,0 +1,7 @@
+struct Point {
+    var x: Double
+    var y: Double
+}
+
+extension Point: Equatable {
+}
@@ -0,0 +1,10 @@
+struct Point {
+    var x: Double
+    var y: Double
+}
+
+extension Point: CustomStringConvertible {
+    var description: String {
+        return "(\(x), \(y))"
+    }
+}
@@ -0,0 +1,15 @@
+---- MODULE Counter ----
+EXTENDS Naturals, Sequences, TLC
+
+VARIABLE x
+
+Init == x = 0
+
+Reset == x' = 0
+
+Next == x' = x + 1
+
+TypeOK == x \in Nat
+
+Spec == Init /\ [][Next]_x
+====
@@ -0,0 +1,13 @@
+---- MODULE Counter ----
+EXTENDS Naturals, Sequences
+
+VARIABLE x
+
+Init == x = 0
+
+Next == x' = x + 1
+
+TypeOK == x \in Nat
+
+Spec == Init /\ [][Next]_x
+====
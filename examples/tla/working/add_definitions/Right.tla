@@ -0,0 +1,13 @@
+---- MODULE Counter ----
+EXTENDS Naturals, TLC
+
+VARIABLE x
+
+Init == x = 0
+
+Reset == x' = 0
+
+Next == x' = x + 1
+
+Spec == Init /\ [][Next]_x
+====
@@ -1,11 +1,23 @@
 enum MyEnum {
   baseA,
+<<<<<<< LEFT
   leftA,
+||||||| BASE
+=======
   rightA,
+>>>>>>> RIGHT
   baseB,
+<<<<<<< LEFT
   leftB,
+||||||| BASE
+=======
   rightB,
+>>>>>>> RIGHT
   baseC,
+<<<<<<< LEFT
   leftC,
+||||||| BASE
+=======
   rightC,
+>>>>>>> RIGHT
 }
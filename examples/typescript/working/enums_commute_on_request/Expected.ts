@@ -0,0 +1,6 @@
+enum Color {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+}
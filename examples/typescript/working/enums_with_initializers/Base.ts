@@ -0,0 +1,4 @@
+enum Status {
+  Active = 'active',
+  Closed = 'closed',
+}
@@ -0,0 +1,6 @@
+enum Status {
+  Active = 'active',
+  Closed = 'closed',
+  Pending = 'pending',
+  Archived = 'archived',
+}
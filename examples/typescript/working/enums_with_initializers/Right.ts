@@ -0,0 +1,5 @@
+enum Status {
+  Active = 'active',
+  Closed = 'closed',
+  Archived = 'archived',
+}
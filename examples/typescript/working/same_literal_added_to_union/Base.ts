@@ -0,0 +1,3 @@
+type Level = 'info' | 'warn';
+
+function log(level: Level) {}
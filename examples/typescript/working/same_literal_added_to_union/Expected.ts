@@ -0,0 +1,3 @@
+type Level = 'info' | 'warn' | 'error' | 'debug';
+
+function log(level: Level, message: string) {}
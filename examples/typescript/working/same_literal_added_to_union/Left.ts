@@ -0,0 +1,3 @@
+type Level = 'info' | 'warn' | 'error';
+
+function log(level: Level, message: string) {}
@@ -12,6 +12,7 @@ use mergiraf::{
     minimize::minimize,
     // XXX: move the uses to lib to avoid making these public?
     newline::normalize_to_lf,
+    write_two_way_matching_to_dotty_file,
 };
 use typed_arena::Arena;
 
@@ -48,6 +49,17 @@ enum Command {
         #[arg(short, long)]
         commutative: bool,
     },
+    /// Match the trees of two files and write the matching as a GraphViz graph,
+    /// which can be rendered with `dot -Tsvg`
+    Viz {
+        /// Path to the first file
+        first: PathBuf,
+        /// Path to the second file
+        second: PathBuf,
+        /// Output path of the `.dot` file
+        #[arg(short, long, default_value = "matching.dot")]
+        output: PathBuf,
+    },
     /// Minimize a test case while maintaining a specific behaviour from a supplied script
     Minimize {
         /// Path to a directory containing Base, Left and Right files (with some extension)
@@ -91,8 +103,13 @@ fn real_main(args: &CliArgs) -> Result<i32, String> {
     let arena = Arena::new();
     let ref_arena = Arena::new();
 
-    let lang_profile = |language_determining_path| {
-        LangProfile::find(language_determining_path, args.language.as_deref(), None)
+    let lang_profile = |language_determining_path, contents| {
+        LangProfile::find(
+            language_determining_path,
+            contents,
+            args.language.as_deref(),
+            None,
+        )
     };
 
     let contents = |path: &Path| -> Result<Cow<str>, String> {
@@ -110,11 +127,11 @@ fn real_main(args: &CliArgs) -> Result<i32, String> {
 
     let exit_code = match &args.command {
         Command::Parse { path, max_depth } => {
-            let lang_profile = lang_profile(path)?;
+            let contents = contents(path)?;
+            let lang_profile = lang_profile(path, &contents)?;
             let mut lang_profile = lang_profile.clone();
             lang_profile.allow_parse_errors = true;
 
-            let contents = contents(path)?;
             let tree = parse(&contents, &lang_profile)?;
 
             print!("{}", tree.ascii_tree(*max_depth, true));
@@ -125,11 +142,11 @@ fn real_main(args: &CliArgs) -> Result<i32, String> {
             second,
             commutative,
         } => {
-            let lang_profile = lang_profile(first)?;
+            let contents_first = contents(first)?;
+            let lang_profile = lang_profile(first, &contents_first)?;
             let mut lang_profile = lang_profile.clone();
             lang_profile.allow_parse_errors = true;
 
-            let contents_first = contents(first)?;
             let tree_first = parse(&contents_first, &lang_profile)?;
 
             let contents_second = contents(second)?;
@@ -146,6 +163,25 @@ fn real_main(args: &CliArgs) -> Result<i32, String> {
                 1
             }
         }
+        Command::Viz {
+            first,
+            second,
+            output,
+        } => {
+            let contents_first = contents(first)?;
+            let lang_profile = lang_profile(first, &contents_first)?;
+            let mut lang_profile = lang_profile.clone();
+            lang_profile.allow_parse_errors = true;
+
+            let tree_first = parse(&contents_first, &lang_profile)?;
+
+            let contents_second = contents(second)?;
+            let tree_second = parse(&contents_second, &lang_profile)?;
+
+            write_two_way_matching_to_dotty_file(output, tree_first, tree_second)
+                .map_err(|err| format!("Could not write '{}': {err}", output.display()))?;
+            0
+        }
         Command::Minimize {
             test_case,
             script,
@@ -265,4 +301,26 @@ mod tests {
             Ok(0)
         );
     }
+
+    #[test]
+    fn viz_writes_matching() {
+        let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+        let output = repo_dir.path().join("matching.dot");
+        assert_eq!(
+            real_main(&CliArgs::parse_from([
+                "mgf_dev",
+                "viz",
+                "../examples/java/working/demo/Base.java",
+                "../examples/java/working/demo/Left.java",
+                "--output",
+                output.to_str().unwrap(),
+            ])),
+            Ok(0)
+        );
+        let graph = fs::read_to_string(&output).expect("could not read the generated graph");
+        assert!(graph.starts_with("graph matching {\n"));
+        assert!(graph.contains("  subgraph l {\n"));
+        assert!(graph.contains("  subgraph r {\n"));
+        assert!(graph.contains("[color=red,constraint=false]"));
+    }
 }
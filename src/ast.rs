@@ -84,6 +84,11 @@ pub struct AstNode<'a> {
     /// The range of bytes in the original source code that the source of this node spans
     pub byte_range: Range<usize>,
     /// An internal node id, guaranteed to be unique within the tree.
+    ///
+    /// Ids are allocated from 1 in post-order (children before their parent, including the
+    /// nodes of injected languages), so they only depend on the source and the language:
+    /// parsing the same source twice gives the same ids, which lets them be used as keys
+    /// of data stored across runs.
     pub id: usize,
     /// Whether this node is one that can appear anywhere in the source (such as a code comment)
     pub is_extra: bool,
@@ -167,9 +172,12 @@ impl<'a> AstNode<'a> {
         lang_profile: &'b LangProfile,
     ) -> FxHashMap<usize, &'b CommutativeParent> {
         let mut node_id_to_commutative_parent = FxHashMap::default();
-        // For each commutative parent that is defined by a tree-sitter query
+        // For each commutative parent that is defined by a tree-sitter query.
+        // Later definitions override earlier ones matching the same nodes, including
+        // disabled ones, so that they can exclude some nodes from commutative merging.
         for commutative_parent in &lang_profile.commutative_parents {
             if let ParentType::ByQuery(query_str) = commutative_parent.parent_type() {
+                let enabled = lang_profile.is_enabled(commutative_parent);
                 // Execute this query over the tree
                 let query = Query::new(&lang_profile.language, query_str)
                     .expect("Invalid commutative parent query");
@@ -180,10 +188,13 @@ impl<'a> AstNode<'a> {
                 let matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
                 // For each match, mark the captured node(s) as commutative
                 matches.for_each(|m| {
-                    node_id_to_commutative_parent.extend(
-                        m.nodes_for_capture_index(commutative_capture_index)
-                            .map(|node| (node.id(), commutative_parent)),
-                    );
+                    for node in m.nodes_for_capture_index(commutative_capture_index) {
+                        if enabled {
+                            node_id_to_commutative_parent.insert(node.id(), commutative_parent);
+                        } else {
+                            node_id_to_commutative_parent.remove(&node.id());
+                        }
+                    }
                 });
             }
         }
@@ -260,6 +271,8 @@ impl<'a> AstNode<'a> {
         // for nodes that we flatten, track the number of children this will add so that
         // we are able to allocate the new children vector efficiently.
         let mut children_added_by_flattening = 0;
+        // whether the first children were skipped because they only consist of whitespace
+        let mut skipped_leading_whitespace = false;
 
         // check if the current node is an injection
         let injection_lang = node_id_to_injection_lang.get(&node.id());
@@ -276,8 +289,23 @@ impl<'a> AstNode<'a> {
                 last_child_end = injected_root.byte_range.end;
             } // if the parsing of the injection fails, keep the injection node as a leaf but don't abort the entire parsing
         } else if !atomic && !node.is_error() && !node.is_missing() && cursor.goto_first_child() {
+            // (`ERROR` nodes are kept as atomic leaves, so that when parse errors are allowed,
+            // the rest of the tree can still be merged structurally)
             let mut child_available = true;
             while child_available {
+                // Whitespace nodes (such as text between XML elements) are skipped when they are blank:
+                // this whitespace is kept track of between nodes, like in any other language.
+                let child_range = cursor.node().byte_range();
+                if lang_profile.is_whitespace_node_type(cursor.node().kind())
+                    && cursor.node().child_count() == 0
+                    && !child_range.is_empty()
+                    && !node_id_to_injection_lang.contains_key(&cursor.node().id())
+                    && global_source[child_range].trim().is_empty()
+                {
+                    skipped_leading_whitespace |= children.is_empty();
+                    child_available = cursor.goto_next_sibling();
+                    continue;
+                }
                 let child = Self::internal_new(
                     cursor,
                     global_source,
@@ -341,6 +369,14 @@ impl<'a> AstNode<'a> {
         } else {
             range
         };
+        // Similarly, if leading whitespace was skipped, start the node at its first child,
+        // so that this whitespace is treated as whitespace between nodes too
+        let range = match children.first() {
+            Some(first_child) if skipped_leading_whitespace && node.parent().is_some() => {
+                first_child.byte_range.start..range.end
+            }
+            _ => range,
+        };
         let local_source = &global_source[range.start..range.end];
         if !lang_profile.allow_parse_errors {
             if node.is_error() {
@@ -368,9 +404,49 @@ impl<'a> AstNode<'a> {
             }
         }
 
+        let kind = node.kind();
+
+        // pre-compute the commutative parent, either by node type or via a query.
+        let commutative_parent = lang_profile
+            .get_commutative_parent_by_kind(kind)
+            .or_else(|| node_id_to_commutative_parent.get(&node.id()).copied());
+
+        // if this is a single-line leaf whose words are separated by whitespace in a commutative
+        // parent (typically, lists of files in build files), create one child per word,
+        // so that they can be merged commutatively
+        if children.is_empty()
+            && !local_source.contains('\n')
+            && lang_profile.is_word_list_node_type(kind)
+            && commutative_parent.is_some_and(|parent| parent.trimmed_separator().is_empty())
+        {
+            let mut offset = range.start;
+            for word in local_source.split_whitespace() {
+                let start_position = offset
+                    + local_source[offset - range.start..]
+                        .find(word)
+                        .unwrap_or_default();
+                children.push(Self::internal_finalize(
+                    lang_profile,
+                    arena,
+                    next_node_id,
+                    None,
+                    false,
+                    Vec::new(),
+                    word,
+                    start_position..start_position + word.len(),
+                    "@virtual_word@",
+                    None,
+                ));
+                offset = start_position + word.len();
+            }
+        }
+
         // if this is a leaf that spans multiple lines, create one child per line,
         // to ease matching and diffing (typically, for multi-line comments)
-        if children.is_empty() && local_source.contains('\n') {
+        if children.is_empty()
+            && local_source.contains('\n')
+            && !lang_profile.is_unsplit_node_type(node.kind())
+        {
             let mut offset = range.start;
             for line in local_source.lines() {
                 let trimmed = line.trim_start();
@@ -400,18 +476,11 @@ impl<'a> AstNode<'a> {
             }
         }
 
-        let kind = node.kind();
-
         // check if this node needs flattening.
         if children_added_by_flattening > 0 && lang_profile.flattened_nodes.contains(&kind) {
             children = Self::flatten_children(children, children_added_by_flattening, kind);
         }
 
-        // pre-compute the commutative parent, either by node type or via a query.
-        let commutative_parent = lang_profile
-            .get_commutative_parent_by_kind(kind)
-            .or_else(|| node_id_to_commutative_parent.get(&node.id()).copied());
-
         if let Some(commutative_parent) = commutative_parent {
             children = Self::bundle_comments(
                 children,
@@ -797,29 +866,33 @@ impl<'a> AstNode<'a> {
         self.parent.get()
     }
 
+    /// The position of this node in the list of children of its parent (if any).
+    ///
+    /// Siblings are sorted by their start in the source, so only those starting at the
+    /// same byte as this node need to be compared, which keeps this logarithmic in the
+    /// number of siblings (think of a JSON object with thousands of keys).
+    fn position_in_parent(&'a self) -> Option<(&'a Self, usize)> {
+        let parent = self.parent()?;
+        let first_candidate = (parent.children)
+            .partition_point(|sibling| sibling.byte_range.start < self.byte_range.start);
+        let offset = parent.children[first_candidate..]
+            .iter()
+            .position(|sibling| sibling.id == self.id)?;
+        Some((parent, first_candidate + offset))
+    }
+
     /// The node that comes just before this node in the list of children
     /// of its parent (if any).
     pub fn predecessor(&'a self) -> Option<&'a Self> {
-        self.parent()?
-            .children
-            .iter()
-            .rev()
-            .skip_while(|sibling| sibling.id != self.id)
-            .skip(1)
-            .copied()
-            .next()
+        let (parent, position) = self.position_in_parent()?;
+        parent.children.get(position.checked_sub(1)?).copied()
     }
 
     /// The node that comes just after this node in the list of children
     /// of its parent (if any).
     pub fn successor(&'a self) -> Option<&'a Self> {
-        self.parent()?
-            .children
-            .iter()
-            .skip_while(|sibling| sibling.id != self.id)
-            .skip(1)
-            .copied()
-            .next()
+        let (parent, position) = self.position_in_parent()?;
+        parent.children.get(position + 1).copied()
     }
 
     /// Truncate a tree so that all nodes selected by the predicate are treated as leaves
@@ -999,10 +1072,7 @@ impl<'a> AstNode<'a> {
     /// The source of this node, stripped from any indentation inherited by the node or its ancestors
     pub fn unindented_source(&'a self) -> Cow<'a, str> {
         match (self.preceding_indentation()).or_else(|| self.ancestor_indentation()) {
-            Some(indentation) => {
-                // TODO FIXME this is invalid for multiline string literals!
-                Cow::from(self.source.replace(&format!("\n{indentation}"), "\n"))
-            }
+            Some(indentation) => self.replace_indentation(indentation, ""),
             None => Cow::from(self.source),
         }
     }
@@ -1016,10 +1086,43 @@ impl<'a> AstNode<'a> {
         if indentation == new_indentation {
             return Cow::from(self.source);
         }
-        let newlines = format!("\n{indentation}");
+        self.replace_indentation(indentation, new_indentation)
+    }
 
+    /// Replaces the given indentation at the start of each line of the source by another one,
+    /// except for the lines inside nodes whose contents must be kept verbatim (such as here-documents).
+    fn replace_indentation(&'a self, indentation: &str, new_indentation: &str) -> Cow<'a, str> {
+        let newlines = format!("\n{indentation}");
         let new_newlines = format!("\n{new_indentation}");
-        Cow::from(self.source.replace(&newlines, &new_newlines)) // TODO FIXME this is invalid for multiline string literals!
+        let verbatim_ranges = self
+            .dfs()
+            .filter(|node| node.lang_profile.is_verbatim_node_type(node.kind))
+            .map(|node| {
+                (node.byte_range.start - self.byte_range.start)
+                    ..(node.byte_range.end - self.byte_range.start)
+            })
+            .collect_vec();
+        if verbatim_ranges.is_empty() {
+            // TODO FIXME this is invalid for multiline string literals!
+            return Cow::from(self.source.replace(&newlines, &new_newlines));
+        }
+
+        let mut result = String::with_capacity(self.source.len());
+        let mut last_end = 0;
+        for (start, _) in self.source.match_indices(&newlines) {
+            // the line starting after this newline belongs to a verbatim node
+            if verbatim_ranges
+                .iter()
+                .any(|range| range.contains(&(start + 1)))
+            {
+                continue;
+            }
+            result.push_str(&self.source[last_end..start]);
+            result.push_str(&new_newlines);
+            last_end = start + newlines.len();
+        }
+        result.push_str(&self.source[last_end..]);
+        Cow::from(result)
     }
 
     /// Source of the node, including any whitespace before and after,
@@ -1143,6 +1246,35 @@ impl<'a> AstNode<'a> {
         )
     }
 
+    /// Represents the node and its sub-structure as an S-expression, in the style of tree-sitter:
+    /// `(document (object (pair key: (string (string_content)) value: (number))))`.
+    /// Anonymous nodes (such as punctuation) are omitted.
+    pub fn to_sexp(&self) -> String {
+        fn write_sexp(node: &AstNode, output: &mut String) {
+            if let Some(field_name) = node.field_name {
+                output.push_str(field_name);
+                output.push_str(": ");
+            }
+            output.push('(');
+            output.push_str(node.kind);
+            let is_named = |child: &AstNode| {
+                child
+                    .lang_profile
+                    .language
+                    .id_for_node_kind(child.kind, true)
+                    != 0
+            };
+            for child in node.children.iter().filter(|child| is_named(child)) {
+                output.push(' ');
+                write_sexp(child, output);
+            }
+            output.push(')');
+        }
+        let mut output = String::new();
+        write_sexp(self, &mut output);
+        output
+    }
+
     /// Checks if a tree has any signature conflicts in it
     pub(crate) fn has_signature_conflicts(&self) -> bool {
         let conflict_in_children = || {
@@ -1166,10 +1298,10 @@ impl<'a> AstNode<'a> {
     }
 
     /// Extracts a signature for this node if we have a signature definition
-    /// for this type of nodes in the language profile.
+    /// for this type of nodes in the language profile, and it could be extracted.
     pub(crate) fn signature(&'a self) -> Option<Signature<'a, 'a>> {
         let definition = self.signature_definition()?;
-        Some(definition.extract_signature_from_original_node(self))
+        definition.extract_signature_from_original_node(self)
     }
 }
 
@@ -1799,6 +1931,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reindent_keeps_heredocs_verbatim() {
+        let ctx = ctx();
+        let source = "\
+if true; then
+  greet() {
+    cat <<EOF
+Hello
+  world
+EOF
+    echo done
+  }
+fi
+";
+        let tree = ctx.parse("a.sh", source);
+        let function = tree[0][4];
+        assert_eq!(function.kind, "function_definition");
+
+        assert_eq!(
+            function.unindented_source(),
+            "\
+greet() {
+  cat <<EOF
+Hello
+  world
+EOF
+  echo done
+}"
+        );
+        assert_eq!(
+            function.reindented_source("    "),
+            "\
+greet() {
+      cat <<EOF
+Hello
+  world
+EOF
+      echo done
+    }"
+        );
+    }
+
     #[test]
     fn multiline_comments_are_isomorphic() {
         let ctx = ctx();
@@ -1821,6 +1995,28 @@ mod tests {
         assert_eq!(comment_2[1].preceding_whitespace(), Some("\n   "));
     }
 
+    #[test]
+    fn unsplit_multiline_leaves() {
+        let ctx = ctx();
+        let blob = "AAAA\n".repeat(1000);
+        let source = format!("const BLOB: &str = r\"\n{blob}\";\n");
+
+        let string_content = ctx.parse("a.rs", &source)[0][5][0];
+        assert_eq!(string_content.kind, "string_content");
+        assert_eq!(string_content.children.len(), 1000);
+
+        let lang_profile = LangProfile {
+            unsplit_nodes: &["string_content"],
+            ..LangProfile::rust().clone()
+        };
+        let root = AstNode::parse(&source, &lang_profile, &ctx.arena, &ctx.ref_arena)
+            .expect("syntax error in source");
+        let string_content = root[0][5][0];
+        assert_eq!(string_content.kind, "string_content");
+        assert!(string_content.children.is_empty());
+        assert_eq!(string_content.source.trim(), blob.trim());
+    }
+
     #[test]
     fn print_as_ascii_art() {
         let ctx = ctx();
@@ -1901,6 +2097,19 @@ mod tests {
         assert_eq!(tree.ascii_tree(None, false), expected);
     }
 
+    #[test]
+    fn to_sexp() {
+        let ctx = ctx();
+        let tree = ctx.parse("a.json", "{\"foo\": 3, \"bar\": [true]}");
+
+        assert_eq!(
+            tree.to_sexp(),
+            "(document (object \
+                (pair key: (string (string_content)) value: (number)) \
+                (pair key: (string (string_content)) value: (array (true)))))"
+        );
+    }
+
     #[test]
     #[cfg(feature = "dev")]
     fn commutative_isomorphism() {
@@ -2007,6 +2216,32 @@ line 3";"#;
         assert_eq!(*ids.iter().max().unwrap(), ids.len());
     }
 
+    #[test]
+    fn node_ids_are_deterministic() {
+        // injections and bundled comments allocate ids too
+        let sources = [
+            (
+                "a.html",
+                "<html><head><script>let a = 1;</script><style>p { color: red; }</style></head>\n<body><script>b();</script></body></html>",
+            ),
+            (
+                "A.java",
+                "import a.B;\n// a comment\nimport c.D;\nclass A {\n  /* doc */\n  void f() {}\n}\n",
+            ),
+        ];
+
+        for (file_name, source) in sources {
+            let ids = || {
+                let ctx = ctx();
+                ctx.parse(file_name, source)
+                    .dfs()
+                    .map(|node| (node.id, node.kind, node.byte_range.clone(), node.hash))
+                    .collect_vec()
+            };
+            assert_eq!(ids(), ids(), "node ids differ across parses of {file_name}");
+        }
+    }
+
     #[test]
     fn parse_html_with_js() {
         let ctx = ctx();
@@ -2040,6 +2275,69 @@ line 3";"#;
         assert_eq!(script_element[1].children.len(), 0);
     }
 
+    #[test]
+    fn parse_svelte_script_injections() {
+        let ctx = ctx();
+        let source = "<script lang=\"ts\">let count: number = 0;</script>\n<script>invalid(][)</script>\n{#if count}<p>{count}</p>{/if}";
+        let svelte = ctx.parse("a.svelte", source);
+
+        assert_eq!(svelte.kind, "document");
+        assert_eq!(svelte.lang_profile.name, "Svelte");
+        let typescript = svelte[0][1];
+        assert_eq!(typescript.kind, "raw_text");
+        assert_eq!(typescript[0].kind, "program");
+        assert_eq!(typescript[0].lang_profile.name, "Typescript");
+
+        // the broken script is kept as a leaf, without failing the whole parse
+        let broken = svelte[1][1];
+        assert_eq!(broken.kind, "raw_text");
+        assert_eq!(broken.lang_profile.name, "Svelte");
+        assert_eq!(broken.children.len(), 0);
+    }
+
+    #[test]
+    fn handlebars_element_modifiers_are_not_commutative() {
+        let ctx = ctx();
+        let source = "\
+<p class=\"a\" id=\"b\">{{#if @x}}x{{/if}}</p>
+<button class=\"a\" {{on \"click\" this.go}}>Go</button>
+<span {{did-insert this.setup}} class=\"a\"></span>
+";
+        let handlebars = ctx.parse("a.hbs", source);
+        assert_eq!(handlebars.lang_profile.name, "Handlebars");
+
+        let [p, button, span] = [0, 1, 2].map(|i| handlebars[i][0]);
+        assert!(p.commutative_parent_definition().is_some());
+        assert!(button.commutative_parent_definition().is_none());
+        assert!(span.commutative_parent_definition().is_none());
+    }
+
+    #[test]
+    fn kotlin_parameters_are_not_commutative() {
+        let ctx = ctx();
+        let source = "\
+data class Point(val x: Int, val y: Int) {
+    fun moved(dx: Int, dy: Int) = copy(x = x + dx, y = y + dy)
+}
+";
+        let kotlin = ctx.parse("a.kt", source);
+
+        // the order of the properties of data classes is observable (`componentN()`, `copy`),
+        // just like the order of the parameters of functions
+        let positional_nodes: Vec<_> = kotlin
+            .dfs()
+            .filter(|node| ["class_parameters", "function_value_parameters"].contains(&node.kind))
+            .collect();
+        assert_eq!(positional_nodes.len(), 2);
+        for node in positional_nodes {
+            assert!(
+                node.commutative_parent_definition().is_none(),
+                "{} should not be commutative",
+                node.kind
+            );
+        }
+    }
+
     #[test]
     fn parse_empty_child_out_of_trimmed_parent() {
         let ctx = ctx();
@@ -2122,6 +2420,26 @@ other = [ 1, 2 ]
         assert!(second_list.commutative_parent_definition().is_none());
     }
 
+    #[test]
+    fn disabled_commutative_parent_via_query_overrides_earlier_ones() {
+        let ctx = ctx();
+        let source = "\
+enum Color { Red = 1, Green = 2 }
+enum Size { Small, Large }
+";
+        let csharp = ctx.parse("a.cs", source);
+
+        let explicit_values = csharp[0][2];
+        let implicit_values = csharp[1][2];
+        assert_eq!(explicit_values.kind, "enum_member_declaration_list");
+        assert_eq!(implicit_values.kind, "enum_member_declaration_list");
+
+        assert!(explicit_values.commutative_parent_definition().is_some());
+        // the order-sensitive definition matching this list is disabled, but still
+        // takes precedence over the earlier one
+        assert!(implicit_values.commutative_parent_definition().is_none());
+    }
+
     #[test]
     fn flatten_binary_operators() {
         let ctx = ctx();
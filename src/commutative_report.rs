@@ -0,0 +1,25 @@
+//! Reporting the places where a commutative merge combined elements added by both sides,
+//! so that those automatic resolutions can be reviewed.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static COMMUTATIVE_REPORT: AtomicBool = AtomicBool::new(false);
+
+/// Log each commutative merge combining additions from both sides.
+pub fn set_commutative_report(enabled: bool) {
+    COMMUTATIVE_REPORT.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether commutative merges should be reported
+pub(crate) fn commutative_report_enabled() -> bool {
+    COMMUTATIVE_REPORT.load(Ordering::Relaxed)
+}
+
+/// Logs a message about a commutative merge
+macro_rules! report_commutation {
+    ($($arg:tt)+) => {
+        log::info!("[commutative-report] {}", format_args!($($arg)+))
+    };
+}
+
+pub(crate) use report_commutation;
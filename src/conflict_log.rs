@@ -0,0 +1,362 @@
+//! Listing the conflicts which remain in a merged file, along with the element they are in,
+//! so that the outcome of many merges can be triaged without opening each file, or so that
+//! they can be presented by external tools.
+
+use std::{fmt::Write as _, fs::OpenOptions, io::Write as _, ops::Range, path::Path};
+
+use serde_json::json;
+use typed_arena::Arena;
+
+use crate::{
+    ast::AstNode,
+    git::attr::GitAttrsForMerge,
+    lang_profile::LangProfile,
+    parsed_merge::{MergedChunk, ParsedMerge},
+    pcs::Revision,
+    settings::DisplaySettings,
+};
+
+/// A conflict in a merged file
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ConflictInfo<'a> {
+    /// The byte range of the conflict in the file, including its markers
+    byte_range: Range<usize>,
+    /// The (1-based) line at which the conflict starts
+    line: usize,
+    left: Option<&'a str>,
+    base: Option<&'a str>,
+    right: Option<&'a str>,
+    /// A description of the closest element of the left revision which encloses the conflict
+    /// and has a signature, such as `method_declaration Signature [[foo], []]`
+    element: Option<String>,
+}
+
+impl ConflictInfo<'_> {
+    /// What the sides did to the conflicting region of the base revision
+    fn reason(&self) -> &'static str {
+        // empty sides are parsed as missing ones
+        let is_empty = |side: Option<&str>| side.is_none_or(|side| side.trim().is_empty());
+        if is_empty(self.base) {
+            "added on both sides"
+        } else if is_empty(self.left) {
+            "deleted on the left side, modified on the right side"
+        } else if is_empty(self.right) {
+            "modified on the left side, deleted on the right side"
+        } else {
+            "modified on both sides"
+        }
+    }
+}
+
+/// Appends a line for each conflict in the merged contents to the log file, giving the
+/// location of the conflict and the signature of the closest element enclosing it
+/// (such as a method), if any.
+///
+/// The language is determined like for merging the file (using the language supplied
+/// on the CLI if any, and the Git attributes of the file if a repository is supplied).
+pub fn append_to_conflict_log(
+    log_path: &Path,
+    file_path: &Path,
+    contents: &str,
+    settings: &DisplaySettings,
+    language: Option<&str>,
+    repo_dir: Option<&Path>,
+) -> Result<(), String> {
+    let lang_profile = find_lang_profile(file_path, contents, language, repo_dir);
+
+    let mut log = String::new();
+    for conflict in conflicts(contents, settings, lang_profile)? {
+        let element =
+            (conflict.element.as_deref()).unwrap_or("no enclosing element with a signature");
+        writeln!(log, "{}:{}: {element}", file_path.display(), conflict.line).unwrap();
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_path)
+        .and_then(|mut file| file.write_all(log.as_bytes()))
+        .map_err(|err| format!("Could not write '{}': {err}", log_path.display()))
+}
+
+/// Describes the conflicts in the merged contents as JSON: for each of them, the contents
+/// of its sides, its byte offsets in the file and the signature of the closest element
+/// enclosing it, if any.
+///
+/// The language is determined like in [`append_to_conflict_log`].
+pub fn conflicts_to_json(
+    file_path: &Path,
+    contents: &str,
+    settings: &DisplaySettings,
+    language: Option<&str>,
+    repo_dir: Option<&Path>,
+) -> Result<String, String> {
+    let lang_profile = find_lang_profile(file_path, contents, language, repo_dir);
+    let conflicts: Vec<_> = conflicts(contents, settings, lang_profile)?
+        .into_iter()
+        .map(|conflict| {
+            json!({
+                "start": conflict.byte_range.start,
+                "end": conflict.byte_range.end,
+                "line": conflict.line,
+                "left": conflict.left,
+                "base": conflict.base,
+                "right": conflict.right,
+                "element": conflict.element,
+            })
+        })
+        .collect();
+    let dump = json!({
+        "file": file_path.to_string_lossy(),
+        "conflicts": conflicts,
+    });
+    Ok(serde_json::to_string_pretty(&dump).expect("JSON values can always be serialized"))
+}
+
+/// Explains each conflict in the merged contents on a line, giving its location, what
+/// both sides did to cause it and the signature of the closest element enclosing it, if any.
+///
+/// The language is determined like in [`append_to_conflict_log`].
+pub fn explain_conflicts(
+    file_path: &Path,
+    contents: &str,
+    settings: &DisplaySettings,
+    language: Option<&str>,
+    repo_dir: Option<&Path>,
+) -> Result<String, String> {
+    let lang_profile = find_lang_profile(file_path, contents, language, repo_dir);
+
+    let mut explanation = String::new();
+    for conflict in conflicts(contents, settings, lang_profile)? {
+        let element =
+            (conflict.element.as_deref()).unwrap_or("no enclosing element with a signature");
+        writeln!(
+            explanation,
+            "{}:{}: {}, in {element}",
+            file_path.display(),
+            conflict.line,
+            conflict.reason(),
+        )
+        .unwrap();
+    }
+    Ok(explanation)
+}
+
+fn find_lang_profile(
+    file_path: &Path,
+    contents: &str,
+    language: Option<&str>,
+    repo_dir: Option<&Path>,
+) -> Option<&'static LangProfile> {
+    let language_git = repo_dir
+        .and_then(|repo_dir| GitAttrsForMerge::new(repo_dir, file_path))
+        .and_then(|git_attrs| git_attrs.language);
+    LangProfile::find(file_path, contents, language, language_git.as_deref()).ok()
+}
+
+/// Locates the conflicts in the merged contents
+fn conflicts<'a>(
+    contents: &'a str,
+    settings: &DisplaySettings,
+    lang_profile: Option<&LangProfile>,
+) -> Result<Vec<ConflictInfo<'a>>, String> {
+    let parsed = ParsedMerge::parse(contents, settings)?;
+    let left_revision = parsed.reconstruct_revision(Revision::Left);
+    let arena = Arena::new();
+    let ref_arena = Arena::new();
+    let tree = lang_profile.and_then(|lang_profile| {
+        AstNode::parse(&left_revision, lang_profile, &arena, &ref_arena).ok()
+    });
+    let end_marker = ">".repeat(settings.conflict_marker_size_or_default());
+
+    let mut conflicts = Vec::new();
+    // the end of the last chunk, in the merged contents and in the left revision
+    let mut merged_offset = 0;
+    let mut left_offset = 0;
+    for chunk in &parsed.chunks {
+        match *chunk {
+            MergedChunk::Resolved { offset, contents } => {
+                merged_offset = offset + contents.len();
+                left_offset += contents.len();
+            }
+            MergedChunk::Conflict {
+                left, base, right, ..
+            } => {
+                // the conflict ends with the first line starting with the right marker
+                let conflict_end = contents[merged_offset..]
+                    .split_inclusive('\n')
+                    .scan(merged_offset, |line_start, line| {
+                        let line_end = *line_start + line.len();
+                        let is_end_marker = line.strip_prefix(&end_marker).is_some_and(|rest| {
+                            rest.trim_end_matches(['\r', '\n']).is_empty() || rest.starts_with(' ')
+                        });
+                        *line_start = line_end;
+                        Some((line_end, is_end_marker))
+                    })
+                    .find_map(|(line_end, is_end_marker)| is_end_marker.then_some(line_end))
+                    .unwrap_or(contents.len());
+                let left_range = left_offset..left_offset + left.map_or(0, str::len);
+                conflicts.push(ConflictInfo {
+                    byte_range: merged_offset..conflict_end,
+                    line: contents[..merged_offset].matches('\n').count() + 1,
+                    left,
+                    base,
+                    right,
+                    element: tree.and_then(|tree| enclosing_element(tree, &left_range)),
+                });
+                merged_offset = conflict_end;
+                left_offset = left_range.end;
+            }
+        }
+    }
+    Ok(conflicts)
+}
+
+/// Describes the smallest node with a signature which contains the byte range
+fn enclosing_element<'a>(tree: &'a AstNode<'a>, range: &Range<usize>) -> Option<String> {
+    // the nodes containing the range form a path from the root, in DFS order
+    let node = tree
+        .dfs()
+        .filter(|node| node.byte_range.start <= range.start && range.end <= node.byte_range.end)
+        .filter(|node| node.signature_definition().is_some())
+        .last()?;
+    let signature = node.signature()?;
+    Some(format!("{} {signature}", node.kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFLICTS_IN_METHODS: &str = "\
+class A {
+    void first() {
+<<<<<<< LEFT
+        a = 1;
+||||||| BASE
+        a = 0;
+=======
+        a = 2;
+>>>>>>> RIGHT
+    }
+
+    void second() {
+<<<<<<< LEFT
+        b = 1;
+||||||| BASE
+        b = 0;
+=======
+        b = 2;
+>>>>>>> RIGHT
+    }
+}
+";
+
+    #[test]
+    fn conflicts_in_distinct_methods() {
+        let lang_profile = LangProfile::find_by_name("java").unwrap();
+
+        let conflicts = conflicts(
+            CONFLICTS_IN_METHODS,
+            &DisplaySettings::default(),
+            Some(lang_profile),
+        )
+        .expect("could not locate the conflicts");
+
+        assert_eq!(conflicts.len(), 2);
+        let [first, second] = &conflicts[..] else {
+            unreachable!()
+        };
+        assert_eq!(first.line, 3);
+        assert_eq!(second.line, 13);
+        assert!(CONFLICTS_IN_METHODS[first.byte_range.clone()].starts_with("<<<<<<< LEFT\n"));
+        assert!(CONFLICTS_IN_METHODS[first.byte_range.clone()].ends_with(">>>>>>> RIGHT\n"));
+        assert!(CONFLICTS_IN_METHODS[second.byte_range.clone()].contains("b = 0;"));
+        let first = first
+            .element
+            .as_deref()
+            .expect("no element found for the first conflict");
+        let second = (second.element.as_deref()).expect("no element found for the second conflict");
+        assert!(first.starts_with("method_declaration") && first.contains("first"));
+        assert!(second.starts_with("method_declaration") && second.contains("second"));
+    }
+
+    #[test]
+    fn conflicts_without_language() {
+        let contents = "a\n<<<<<<< LEFT\nb\n||||||| BASE\n=======\nc\n>>>>>>> RIGHT\n";
+
+        let conflicts = conflicts(contents, &DisplaySettings::default(), None)
+            .expect("could not locate the conflicts");
+
+        assert_eq!(
+            conflicts,
+            vec![ConflictInfo {
+                byte_range: 2..contents.len(),
+                line: 2,
+                left: Some("b\n"),
+                base: None,
+                right: Some("c\n"),
+                element: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn explain_conflicts_with_reasons() {
+        let explanation = explain_conflicts(
+            Path::new("A.java"),
+            CONFLICTS_IN_METHODS,
+            &DisplaySettings::default(),
+            None,
+            None,
+        )
+        .expect("could not explain the conflicts");
+
+        let lines: Vec<_> = explanation.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("A.java:3: modified on both sides, in method_declaration"));
+        assert!(lines[1].starts_with("A.java:13: modified on both sides, in method_declaration"));
+
+        let added = "a\n<<<<<<< LEFT\nb\n||||||| BASE\n=======\nc\n>>>>>>> RIGHT\n";
+        let deleted = "a\n<<<<<<< LEFT\n||||||| BASE\nb\n=======\nc\n>>>>>>> RIGHT\n";
+        let reasons: Vec<_> = [added, deleted]
+            .into_iter()
+            .map(|contents| {
+                conflicts(contents, &DisplaySettings::default(), None).unwrap()[0].reason()
+            })
+            .collect();
+        assert_eq!(
+            reasons,
+            [
+                "added on both sides",
+                "deleted on the left side, modified on the right side"
+            ]
+        );
+    }
+
+    #[test]
+    fn dump_conflicts_as_json() {
+        let dump = conflicts_to_json(
+            Path::new("A.java"),
+            CONFLICTS_IN_METHODS,
+            &DisplaySettings::default(),
+            None,
+            None,
+        )
+        .expect("could not dump the conflicts");
+
+        let dump: serde_json::Value = serde_json::from_str(&dump).expect("invalid JSON");
+        assert_eq!(dump["file"], "A.java");
+        let conflicts = dump["conflicts"].as_array().unwrap();
+        assert_eq!(conflicts.len(), 2);
+        for (conflict, name) in conflicts.iter().zip(["a", "b"]) {
+            assert_eq!(conflict["left"], format!("        {name} = 1;\n"));
+            assert_eq!(conflict["base"], format!("        {name} = 0;\n"));
+            assert_eq!(conflict["right"], format!("        {name} = 2;\n"));
+            let (start, end) = (conflict["start"].as_u64(), conflict["end"].as_u64());
+            let range = start.unwrap() as usize..end.unwrap() as usize;
+            assert!(CONFLICTS_IN_METHODS[range].starts_with("<<<<<<<"));
+        }
+        assert!(conflicts[0]["element"].as_str().unwrap().contains("first"));
+    }
+}
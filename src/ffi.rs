@@ -0,0 +1,189 @@
+//! A C interface to merge three revisions of a file held in memory.
+//!
+//! This module is only compiled with the `ffi` feature. To obtain a shared library exposing
+//! these functions, build the crate with:
+//! ```sh
+//! cargo rustc --lib --release --features ffi --crate-type cdylib
+//! ```
+//!
+//! # Memory ownership
+//!
+//! The strings passed to [`mergiraf_merge`] remain owned by the caller: they are copied
+//! before merging and can be freed as soon as the function returns.
+//!
+//! The [`MergeResultC`] returned by [`mergiraf_merge`] (including the string it points to)
+//! is owned by the caller, who must release it by passing it to [`mergiraf_merge_result_free`]
+//! exactly once. It must not be released with the C `free` function, and its `contents` must
+//! not be freed separately.
+
+use std::{
+    ffi::{CStr, CString, c_char},
+    path::Path,
+    ptr,
+};
+
+use crate::{
+    merge::{DEFAULT_TIMEOUT, merge_files},
+    settings::DisplaySettings,
+};
+
+/// The outcome of a merge performed via [`mergiraf_merge`]
+#[repr(C)]
+pub struct MergeResultC {
+    /// The merged contents (possibly with conflict markers), as a NUL-terminated UTF-8 string
+    pub contents: *mut c_char,
+    /// The number of conflicts left in the merged contents
+    pub conflict_count: usize,
+}
+
+/// Merges three revisions of a file, given as NUL-terminated UTF-8 strings.
+///
+/// The language to parse the revisions with is detected from `file_name`, unless `lang_name`
+/// is non-null: it is then the name of the language, which can also be a file extension or
+/// a file name (see `mergiraf languages`). Both can be null, and if no supported language is
+/// found, the revisions are merged line-based.
+///
+/// Returns null if any of the revisions is null or not valid UTF-8, or if the merged
+/// contents contain a NUL byte. Otherwise, the returned result must be released with
+/// [`mergiraf_merge_result_free`].
+///
+/// # Safety
+///
+/// Each non-null pointer must point to a NUL-terminated string which stays valid and unmodified
+/// for the duration of the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mergiraf_merge(
+    base: *const c_char,
+    left: *const c_char,
+    right: *const c_char,
+    file_name: *const c_char,
+    lang_name: *const c_char,
+) -> *mut MergeResultC {
+    // SAFETY: the pointers are valid according to the contract of this function
+    let (Some(base), Some(left), Some(right)) = (
+        unsafe { to_owned_str(base) },
+        unsafe { to_owned_str(left) },
+        unsafe { to_owned_str(right) },
+    ) else {
+        return ptr::null_mut();
+    };
+    // SAFETY: same as above
+    let (file_name, lang_name) = unsafe { (to_owned_str(file_name), to_owned_str(lang_name)) };
+
+    let merged = merge_files(
+        &base,
+        &left,
+        &right,
+        Path::new(file_name.as_deref().unwrap_or_default()),
+        lang_name.as_deref(),
+        &DisplaySettings::default(),
+        DEFAULT_TIMEOUT,
+    );
+
+    let Ok(contents) = CString::new(merged.contents) else {
+        return ptr::null_mut();
+    };
+    Box::into_raw(Box::new(MergeResultC {
+        contents: contents.into_raw(),
+        conflict_count: merged.conflict_count,
+    }))
+}
+
+/// Releases a result returned by [`mergiraf_merge`]. Does nothing if `result` is null.
+///
+/// # Safety
+///
+/// `result` must either be null or have been returned by [`mergiraf_merge`] and not been
+/// released before. Its fields must not have been modified.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mergiraf_merge_result_free(result: *mut MergeResultC) {
+    if result.is_null() {
+        return;
+    }
+    // SAFETY: `result` and its contents were allocated by `mergiraf_merge`
+    // via `Box::into_raw` and `CString::into_raw` respectively
+    unsafe {
+        let result = Box::from_raw(result);
+        drop(CString::from_raw(result.contents));
+    }
+}
+
+/// Copies a C string into an owned Rust string, if it is non-null and valid UTF-8
+///
+/// # Safety
+///
+/// `s` must either be null or point to a valid NUL-terminated string
+unsafe fn to_owned_str(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    // SAFETY: guaranteed by the caller
+    let s = unsafe { CStr::from_ptr(s) };
+    s.to_str().ok().map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe extern "C" {
+        fn mergiraf_merge(
+            base: *const c_char,
+            left: *const c_char,
+            right: *const c_char,
+            file_name: *const c_char,
+            lang_name: *const c_char,
+        ) -> *mut MergeResultC;
+        fn mergiraf_merge_result_free(result: *mut MergeResultC);
+    }
+
+    #[test]
+    fn merge_through_c_abi() {
+        let base = c"fn main() {}\n";
+        let left = c"fn left() {}\nfn main() {}\n";
+        let right = c"fn main() {}\nfn right() {}\n";
+
+        let result = unsafe {
+            mergiraf_merge(
+                base.as_ptr(),
+                left.as_ptr(),
+                right.as_ptr(),
+                c"main.rs".as_ptr(),
+                ptr::null(),
+            )
+        };
+        assert!(!result.is_null());
+        let (contents, conflict_count) = unsafe {
+            (
+                CStr::from_ptr((*result).contents)
+                    .to_str()
+                    .unwrap()
+                    .to_owned(),
+                (*result).conflict_count,
+            )
+        };
+        unsafe { mergiraf_merge_result_free(result) };
+
+        assert_eq!(contents, "fn left() {}\nfn main() {}\nfn right() {}\n");
+        assert_eq!(conflict_count, 0);
+    }
+
+    #[test]
+    fn invalid_input_through_c_abi() {
+        let valid = c"hello\n";
+        let invalid = c"\xff\n";
+
+        let result = unsafe {
+            mergiraf_merge(
+                valid.as_ptr(),
+                invalid.as_ptr(),
+                valid.as_ptr(),
+                ptr::null(),
+                ptr::null(),
+            )
+        };
+        assert!(result.is_null());
+        // releasing a null result is a no-op
+        unsafe { mergiraf_merge_result_free(result) };
+    }
+}
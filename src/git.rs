@@ -102,6 +102,83 @@ pub(crate) fn read_content_from_commits(
     ))
 }
 
+/// Reads the contents of a blob, given by an object name such as `main:src/lib.rs`
+pub fn read_blob(repo_dir: &Path, object_name: &str) -> Result<String, String> {
+    run_git_command(repo_dir, &["cat-file", "blob", object_name])
+}
+
+/// Whether a revision name is the full object name of a commit, such as those Git
+/// puts in conflict markers
+pub fn is_oid(name: &str) -> bool {
+    name.len() == 40 && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Reads the subject (first line of the message) of a commit
+pub fn commit_subject(repo_dir: &Path, oid: &str) -> Option<String> {
+    run_git_command(repo_dir, &["show", "--no-patch", "--format=%s", oid])
+        .ok()
+        .map(|output| output.trim_end().to_owned())
+        .filter(|subject| !subject.is_empty())
+}
+
+/// Runs a git command in the repository, returning its standard output
+fn run_git_command(repo_dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo_dir)
+        .output()
+        .map_err(|err| err.to_string())?;
+    if !output.status.success() {
+        return Err(format!(
+            "error while running `git {}`:\n{}",
+            args.iter().format(" "),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    String::from_utf8(output.stdout).map_err(|err| err.to_string())
+}
+
+/// Lists the merge commits with two parents reachable from a revision, most recent first,
+/// returning the oids of each merge commit and its parents.
+pub(crate) fn list_merge_commits(
+    repo_dir: &Path,
+    revision: &str,
+    max_count: Option<usize>,
+) -> Result<Vec<(String, String, String)>, String> {
+    let mut args = vec![
+        "rev-list",
+        "--parents",
+        "--min-parents=2",
+        "--max-parents=2",
+    ];
+    let max_count = max_count.map(|max_count| format!("--max-count={max_count}"));
+    args.extend(max_count.as_deref());
+    args.push(revision);
+    let output = run_git_command(repo_dir, &args)?;
+    output
+        .lines()
+        .map(|line| {
+            line.split_whitespace()
+                .map(str::to_owned)
+                .collect_tuple()
+                .ok_or_else(|| format!("invalid rev-list output: {line}"))
+        })
+        .collect()
+}
+
+/// Finds the best common ancestor of two commits, if any
+pub(crate) fn merge_base(repo_dir: &Path, left: &str, right: &str) -> Option<String> {
+    run_git_command(repo_dir, &["merge-base", left, right])
+        .ok()
+        .map(|output| output.trim().to_owned())
+}
+
+/// Lists the paths of the files modified between two commits
+pub(crate) fn changed_files(repo_dir: &Path, from: &str, to: &str) -> Result<Vec<PathBuf>, String> {
+    let output = run_git_command(repo_dir, &["diff", "--name-only", "-z", from, to])?;
+    Ok(output.split_terminator('\0').map(PathBuf::from).collect())
+}
+
 // FIXME: this should've been `#[cfg(test)]`, but for some reason, if I add that,
 // `solve_respects_conflict_marker_size_attr` stops compiling
 pub fn init(path: impl AsRef<Path>) {
@@ -121,6 +198,7 @@ pub mod attr {
     pub(super) const GIT_CONFLICT_MARKER_SIZE: &str = "conflict-marker-size";
     pub(super) const LINGUIST_LANGUAGE: &str = "linguist-language";
     pub(super) const MERGIRAF_ALLOW_PARSE_ERRORS: &str = "mergiraf.allow-parse-errors";
+    pub(super) const MERGIRAF_COMMUTE_ORDER_SENSITIVE: &str = "mergiraf.commute-order-sensitive";
     pub(super) const MERGIRAF_LANGUAGE: &str = "mergiraf.language";
 
     /// A value for a Git attribute.
@@ -192,6 +270,7 @@ pub mod attr {
     pub struct GitAttrsForMerge {
         pub language: Option<String>,
         pub allow_parse_errors: Option<bool>,
+        pub commute_order_sensitive: Option<bool>,
     }
 
     impl GitAttrsForMerge {
@@ -202,12 +281,15 @@ pub mod attr {
                 LINGUIST_LANGUAGE,
                 // allow_parse_errors
                 MERGIRAF_ALLOW_PARSE_ERRORS,
+                // commute_order_sensitive
+                MERGIRAF_COMMUTE_ORDER_SENSITIVE,
             ];
             let mut attrs = read_attributes_for_file(repo_dir, file_name, attr_names)?;
 
             Some(Self {
                 language: read_lang_attribute(&mut attrs),
                 allow_parse_errors: read_allow_parse_errors_attribute(&mut attrs),
+                commute_order_sensitive: read_commute_order_sensitive_attribute(&mut attrs),
             })
         }
     }
@@ -217,6 +299,7 @@ pub mod attr {
         pub conflict_marker_size: Option<usize>,
         pub language: Option<String>,
         pub allow_parse_errors: Option<bool>,
+        pub commute_order_sensitive: Option<bool>,
     }
 
     impl GitAttrsForSolve {
@@ -229,6 +312,8 @@ pub mod attr {
                 LINGUIST_LANGUAGE,
                 // allow_parse_errors
                 MERGIRAF_ALLOW_PARSE_ERRORS,
+                // commute_order_sensitive
+                MERGIRAF_COMMUTE_ORDER_SENSITIVE,
             ];
             let mut attrs = read_attributes_for_file(repo_dir, file_name, attr_names)?;
 
@@ -236,6 +321,7 @@ pub mod attr {
                 conflict_marker_size: read_conflict_marker_size_attribute(&mut attrs),
                 language: read_lang_attribute(&mut attrs),
                 allow_parse_errors: read_allow_parse_errors_attribute(&mut attrs),
+                commute_order_sensitive: read_commute_order_sensitive_attribute(&mut attrs),
             })
         }
     }
@@ -345,23 +431,35 @@ pub mod attr {
     pub(super) fn read_allow_parse_errors_attribute(
         attrs: &mut HashMap<&'static str, AttrValue>,
     ) -> Option<bool> {
-        let attr_name = MERGIRAF_ALLOW_PARSE_ERRORS;
+        read_bool_attribute(attrs, MERGIRAF_ALLOW_PARSE_ERRORS)
+    }
+
+    pub(super) fn read_commute_order_sensitive_attribute(
+        attrs: &mut HashMap<&'static str, AttrValue>,
+    ) -> Option<bool> {
+        read_bool_attribute(attrs, MERGIRAF_COMMUTE_ORDER_SENSITIVE)
+    }
 
+    /// Reads an attribute which can only be set (`attr`) or unset (`-attr`)
+    fn read_bool_attribute(
+        attrs: &mut HashMap<&'static str, AttrValue>,
+        attr_name: &'static str,
+    ) -> Option<bool> {
         let attr_value = attrs.remove(attr_name);
         debug_assert!(
             attr_value.is_some(),
             "`{attr_name}` wasn't present in `attrs={attrs:?}`"
         );
-        attr_value.and_then(|allow| match allow {
+        attr_value.and_then(|value| match value {
             AttrValue::Unspecified => None,
             AttrValue::Set => Some(true),
             AttrValue::Unset => Some(false),
             AttrValue::Specified(other) => {
                 warn!(
-                    "invalid value for `{MERGIRAF_ALLOW_PARSE_ERRORS}` attribute: \
-                    expected \"{MERGIRAF_ALLOW_PARSE_ERRORS}\" \
-                    or \"-{MERGIRAF_ALLOW_PARSE_ERRORS}\", \
-                    found \"{MERGIRAF_ALLOW_PARSE_ERRORS}={other}\""
+                    "invalid value for `{attr_name}` attribute: \
+                    expected \"{attr_name}\" \
+                    or \"-{attr_name}\", \
+                    found \"{attr_name}={other}\""
                 );
                 None
             }
@@ -378,7 +476,7 @@ mod test {
             git,
             git::attr::{
                 AttrValue, GIT_CONFLICT_MARKER_SIZE, LINGUIST_LANGUAGE,
-                MERGIRAF_ALLOW_PARSE_ERRORS, MERGIRAF_LANGUAGE,
+                MERGIRAF_ALLOW_PARSE_ERRORS, MERGIRAF_COMMUTE_ORDER_SENSITIVE, MERGIRAF_LANGUAGE,
             },
             utils::write_string_to_file,
         };
@@ -492,5 +590,20 @@ mod test {
             let attrs_deny = HashMap::from([(MERGIRAF_ALLOW_PARSE_ERRORS, AttrValue::Unset)]);
             assert_eq!(allow(attrs_deny), Some(false));
         }
+
+        #[test]
+        fn read_commute_order_sensitive_attribute() {
+            let commute = |mut attrs| git::attr::read_commute_order_sensitive_attribute(&mut attrs);
+
+            let attrs_empty =
+                HashMap::from([(MERGIRAF_COMMUTE_ORDER_SENSITIVE, AttrValue::Unspecified)]);
+            assert_eq!(commute(attrs_empty), None);
+
+            let attrs_set = HashMap::from([(MERGIRAF_COMMUTE_ORDER_SENSITIVE, AttrValue::Set)]);
+            assert_eq!(commute(attrs_set), Some(true));
+
+            let attrs_unset = HashMap::from([(MERGIRAF_COMMUTE_ORDER_SENSITIVE, AttrValue::Unset)]);
+            assert_eq!(commute(attrs_unset), Some(false));
+        }
     }
 }
@@ -18,10 +18,27 @@ pub struct LangProfile {
     pub extensions: &'static [&'static str],
     /// the full file names that this language should be used for
     pub file_names: &'static [&'static str],
+    /// patterns of paths that this language should be used for, such as `.github/workflows/*.yml`.
+    /// They are matched against the last components of the path, and `*` matches any part of a component.
+    pub path_patterns: &'static [&'static str],
     /// `tree_sitter` parser
     pub language: Language,
     /// list of node types which should be treated as leaves (atomic parts of the syntax tree)
     pub atomic_nodes: &'static [&'static str],
+    /// list of node types which should be kept as single leaves even when they span multiple lines,
+    /// instead of being split into one child per line (for instance, for large base64 blobs)
+    pub unsplit_nodes: &'static [&'static str],
+    /// list of node types whose contents must be kept as they are when reindenting their
+    /// ancestors (for instance, here-documents)
+    pub verbatim_nodes: &'static [&'static str],
+    /// list of node types which are left out of the syntax tree when they only consist of whitespace,
+    /// so that this whitespace is handled like the whitespace between any other nodes
+    /// (for instance, the text between XML elements)
+    pub whitespace_nodes: &'static [&'static str],
+    /// list of node types which are split into one child per word when they are single-line leaves
+    /// whose children are separated by whitespace in a commutative parent
+    /// (for instance, lists of files in build files)
+    pub word_list_nodes: &'static [&'static str],
     /// list of node types whose child order does not matter
     pub commutative_parents: Vec<CommutativeParent>,
     /// how to extract the signatures of nodes, uniquely identifying children of a commutative parent
@@ -40,6 +57,67 @@ pub struct LangProfile {
     /// When the tree-sitter parser routinely returns errors for valid files,
     /// this parameter should be set to true.
     pub allow_parse_errors: bool,
+    /// Whether commutative parents marked as order-sensitive (see
+    /// [`CommutativeParent::order_sensitive`]) should be merged commutatively.
+    /// Since reordering their children can change the semantics of the program,
+    /// this should be left disabled by default, letting users opt in.
+    pub commute_order_sensitive: bool,
+    /// The delimiters to write a comment in this language, such as `("/*", "*/")`, used to
+    /// annotate conflicts when requested. The closing delimiter is empty for line comments.
+    pub comment_delimiters: Option<(&'static str, &'static str)>,
+}
+
+/// Checks whether the last components of a path match a pattern such as `.github/workflows/*.yml`,
+/// where `*` matches any part of a single component. Backslashes are accepted as separators
+/// in the path, as Git drivers on Windows can use them.
+fn path_matches_pattern(path: &str, pattern: &str) -> bool {
+    let mut components = path.rsplit(['/', '\\']);
+    pattern.rsplit('/').all(|pattern_component| {
+        components
+            .next()
+            .is_some_and(|component| match pattern_component.split_once('*') {
+                None => component == pattern_component,
+                Some((prefix, suffix)) => {
+                    component.len() >= prefix.len() + suffix.len()
+                        && component.starts_with(prefix)
+                        && component.ends_with(suffix)
+                }
+            })
+    })
+}
+
+/// How the language of a file was determined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LanguageDetection {
+    /// Specified with the `--language` option
+    CliOption,
+    /// Specified with the `mergiraf.language` or `linguist-language` Git attributes
+    GitAttribute,
+    /// Detected from the extension of the file
+    Extension,
+    /// Detected from the full name of the file
+    FileName,
+    /// Detected from a pattern matching the path of the file
+    PathPattern,
+    /// Detected from an Emacs or Vim modeline in the file
+    Modeline,
+    /// Guessed from the contents of the file
+    Content,
+}
+
+impl Display for LanguageDetection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            Self::CliOption => "the --language option",
+            Self::GitAttribute => "Git attributes",
+            Self::Extension => "the file extension",
+            Self::FileName => "the file name",
+            Self::PathPattern => "the file path",
+            Self::Modeline => "a modeline in the file",
+            Self::Content => "the contents of the file",
+        };
+        write!(f, "{description}")
+    }
 }
 
 impl PartialEq for LangProfile {
@@ -80,57 +158,193 @@ impl LangProfile {
     where
         P: AsRef<Path>,
     {
-        fn inner(filename: &Path) -> Option<&'static LangProfile> {
-            // TODO make something more advanced like in difftastic
-            // https://github.com/Wilfred/difftastic/blob/master/src/parse/tree_sitter_parser.rs
-            let extension = filename.extension();
-            let name = filename.file_name()?;
-            SUPPORTED_LANGUAGES.iter().find(|lang_profile| {
-                lang_profile.extensions.iter().copied().any(|ext| {
-                    extension.is_some_and(|extension| {
-                        // NOTE: the comparison should be case-insensitive, see
-                        // https://rust-lang.github.io/rust-clippy/master/index.html#case_sensitive_file_extension_comparisons
-                        extension.eq_ignore_ascii_case(OsStr::new(ext))
-                    })
-                }) || lang_profile
-                    .file_names
-                    .iter()
-                    .copied()
-                    .any(|ref_name| name == ref_name)
-            })
+        Self::detect_from_path(filename.as_ref()).map(|(lang_profile, _)| lang_profile)
+    }
+
+    /// Detects the language of a file based on its filename, also returning which part
+    /// of the path it was detected from
+    fn detect_from_path(filename: &Path) -> Option<(&'static Self, LanguageDetection)> {
+        // TODO make something more advanced like in difftastic
+        // https://github.com/Wilfred/difftastic/blob/master/src/parse/tree_sitter_parser.rs
+
+        let path = filename.to_str();
+        // Git drivers on Windows can pass paths with backslashes, which are not
+        // understood as separators on other platforms: only keep the last component.
+        let filename = path
+            .and_then(|filename| filename.rsplit('\\').next())
+            .map_or(filename, Path::new);
+        let extension = filename.extension();
+        let name = filename.file_name()?;
+        SUPPORTED_LANGUAGES.iter().find_map(|lang_profile| {
+            let detection = if lang_profile.extensions.iter().copied().any(|ext| {
+                extension.is_some_and(|extension| {
+                    // NOTE: the comparison should be case-insensitive, see
+                    // https://rust-lang.github.io/rust-clippy/master/index.html#case_sensitive_file_extension_comparisons
+                    extension.eq_ignore_ascii_case(OsStr::new(ext))
+                })
+            }) {
+                LanguageDetection::Extension
+            } else if lang_profile
+                .file_names
+                .iter()
+                .copied()
+                .any(|ref_name| name == ref_name)
+            {
+                LanguageDetection::FileName
+            } else if path.is_some_and(|path| {
+                (lang_profile.path_patterns.iter())
+                    .any(|pattern| path_matches_pattern(path, pattern))
+            }) {
+                LanguageDetection::PathPattern
+            } else {
+                return None;
+            };
+            Some((lang_profile, detection))
+        })
+    }
+
+    /// Detects the language of a file from an Emacs or Vim modeline, such as
+    /// `-*- mode: python -*-` or `vim: set ft=python:`, in its first or last lines
+    pub fn detect_from_modeline(contents: &str) -> Option<&'static Self> {
+        /// How many lines to scan at the start and end of the file, like Vim does by default
+        const MODELINE_LINES: usize = 5;
+
+        let lines: Vec<&str> = contents.lines().collect();
+        let last_lines = lines
+            .len()
+            .saturating_sub(MODELINE_LINES)
+            .max(MODELINE_LINES);
+        (lines.iter().take(MODELINE_LINES))
+            .chain(lines.iter().skip(last_lines))
+            .filter_map(|line| Self::emacs_mode(line).or_else(|| Self::vim_filetype(line)))
+            .find_map(Self::find_by_name)
+    }
+
+    /// Guesses the language of a file from the shape of its contents, as a last resort.
+    ///
+    /// Only a few languages are recognized, based on simple heuristics (such as a leading
+    /// brace for JSON, or lines ending with colons after `def` for Python), and a guess
+    /// is only accepted if the file parses without errors in that language.
+    pub fn detect_from_content(contents: &str) -> Option<&'static Self> {
+        let trimmed = contents.trim_start();
+        let lines = || contents.lines().map(str::trim);
+        let candidate = if trimmed.starts_with(['{', '[']) {
+            "JSON"
+        } else if trimmed.starts_with('<') {
+            "XML"
+        } else if lines().any(|line| line.starts_with("def ") || line.starts_with("class "))
+            && lines().any(|line| line.ends_with(':'))
+        {
+            "Python"
+        } else if contents.contains(';')
+            && contents.contains('{')
+            && contents.matches('{').count() == contents.matches('}').count()
+        {
+            "C"
+        } else {
+            return None;
+        };
+        let lang_profile = Self::find_by_name(candidate)?;
+
+        // the guess is fuzzy, so we check that it is consistent with the contents,
+        // including for languages whose parse errors are normally tolerated
+        let strict_profile = Self {
+            allow_parse_errors: false,
+            ..lang_profile.clone()
+        };
+        let arena = typed_arena::Arena::new();
+        let ref_arena = typed_arena::Arena::new();
+        AstNode::parse(contents, &strict_profile, &arena, &ref_arena)
+            .is_ok()
+            .then_some(lang_profile)
+    }
+
+    /// Extracts the mode from an Emacs modeline, such as `-*- mode: python; coding: utf-8 -*-`
+    /// or `-*- python -*-`
+    fn emacs_mode(line: &str) -> Option<&str> {
+        let (_, rest) = line.split_once("-*-")?;
+        let (variables, _) = rest.split_once("-*-")?;
+        if !variables.contains(':') {
+            return Some(variables.trim());
         }
-        inner(filename.as_ref())
+        variables.split(';').find_map(|variable| {
+            let (name, value) = variable.split_once(':')?;
+            name.trim()
+                .eq_ignore_ascii_case("mode")
+                .then(|| value.trim())
+        })
+    }
+
+    /// Extracts the file type from a Vim modeline, such as `vim: set ft=python:`
+    /// or `vi: filetype=python`
+    fn vim_filetype(line: &str) -> Option<&str> {
+        let options = ["vim:", "vi:", "ex:"].into_iter().find_map(|marker| {
+            let (before, options) = line.split_once(marker)?;
+            // the marker must be preceded by whitespace (or start the line)
+            (before.is_empty() || before.ends_with(char::is_whitespace)).then_some(options)
+        })?;
+        options
+            .split(|c: char| c == ':' || c.is_whitespace())
+            .find_map(|option| {
+                option
+                    .strip_prefix("ft=")
+                    .or_else(|| option.strip_prefix("filetype="))
+            })
     }
 
     /// Loads a language, by:
     /// - first, looking up the language using the name provided on CLI (if present)
     /// - failing that, looking up the language using the name from gitattributes file (if present)
     /// - failing that, by detecting it from a filename
+    /// - failing that, by looking for an Emacs or Vim modeline in the contents of the file
     pub fn find<P>(
         filename: P,
+        contents: &str,
         lang_name_from_cli: Option<&str>,
         lang_name_from_git: Option<&str>,
     ) -> Result<&'static Self, String>
+    where
+        P: AsRef<Path>,
+    {
+        Self::find_with_detection(filename, contents, lang_name_from_cli, lang_name_from_git)
+            .map(|(lang_profile, _)| lang_profile)
+    }
+
+    /// Loads a language like [`Self::find`], also returning how it was determined
+    pub fn find_with_detection<P>(
+        filename: P,
+        contents: &str,
+        lang_name_from_cli: Option<&str>,
+        lang_name_from_git: Option<&str>,
+    ) -> Result<(&'static Self, LanguageDetection), String>
     where
         P: AsRef<Path>,
     {
         let filename = filename.as_ref();
         if let Some(lang_name) = lang_name_from_cli {
             Self::find_by_name(lang_name)
+                .map(|lang_profile| (lang_profile, LanguageDetection::CliOption))
                 .ok_or_else(|| format!("Specified language '{lang_name}' could not be found"))
             // If lookup by name failed, we don't fall back on the other detection methods,
             // because don't want to silently ignore an invalid language name.
         } else if let Some(lang_name) = lang_name_from_git {
-            Self::find_by_name(lang_name).ok_or_else(|| {
-                format!("Attribute-specified language '{lang_name}' could not be found")
-            })
+            Self::find_by_name(lang_name)
+                .map(|lang_profile| (lang_profile, LanguageDetection::GitAttribute))
+                .ok_or_else(|| {
+                    format!("Attribute-specified language '{lang_name}' could not be found")
+                })
         } else {
-            Self::detect_from_filename(filename).ok_or_else(|| {
-                format!(
-                    "Could not find a supported language for '{}'",
-                    filename.display()
-                )
-            })
+            Self::detect_from_path(filename)
+                .or_else(|| {
+                    Self::detect_from_modeline(contents)
+                        .map(|lang_profile| (lang_profile, LanguageDetection::Modeline))
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "Could not find a supported language for '{}'",
+                        filename.display()
+                    )
+                })
         }
     }
 
@@ -138,9 +352,21 @@ impl LangProfile {
     /// This will return any CommutativeParent defined on this node kind.
     /// CommutativeParents defined by queries are ignored.
     pub(crate) fn get_commutative_parent_by_kind(&self, kind: &str) -> Option<&CommutativeParent> {
+        self.enabled_commutative_parents()
+            .find(|cr| cr.parent_type == ParentType::ByKind(kind))
+    }
+
+    /// The commutative parents which should be used for merging, leaving out the
+    /// order-sensitive ones unless [`Self::commute_order_sensitive`] is set.
+    pub(crate) fn enabled_commutative_parents(&self) -> impl Iterator<Item = &CommutativeParent> {
         self.commutative_parents
             .iter()
-            .find(|cr| cr.parent_type == ParentType::ByKind(kind))
+            .filter(|cr| self.is_enabled(cr))
+    }
+
+    /// Whether this commutative parent should be used for merging
+    pub(crate) fn is_enabled(&self, commutative_parent: &CommutativeParent) -> bool {
+        self.commute_order_sensitive || !commutative_parent.order_sensitive
     }
 
     pub(crate) fn find_signature_definition_by_kind(
@@ -157,6 +383,26 @@ impl LangProfile {
         self.atomic_nodes.contains(&node_type)
     }
 
+    /// Should this node type be kept as a single leaf, even if it spans multiple lines?
+    pub(crate) fn is_unsplit_node_type(&self, node_type: &str) -> bool {
+        self.unsplit_nodes.contains(&node_type)
+    }
+
+    /// Should the contents of this node type be kept as they are when reindenting?
+    pub(crate) fn is_verbatim_node_type(&self, node_type: &str) -> bool {
+        self.verbatim_nodes.contains(&node_type)
+    }
+
+    /// Should nodes of this type be left out of the syntax tree when they only consist of whitespace?
+    pub(crate) fn is_whitespace_node_type(&self, node_type: &str) -> bool {
+        self.whitespace_nodes.contains(&node_type)
+    }
+
+    /// Should nodes of this type be split into words when they are merged commutatively?
+    pub(crate) fn is_word_list_node_type(&self, node_type: &str) -> bool {
+        self.word_list_nodes.contains(&node_type)
+    }
+
     /// Check that all node type and field names that are used
     /// in this language profile exist in the tree-sitter language.
     /// This can be used to detect inconsistencies, for instance following
@@ -167,7 +413,10 @@ impl LangProfile {
     #[cfg(test)]
     pub(crate) fn check_kinds(&self) -> Result<(), String> {
         let name_is_valid = |name: &'static str| {
-            self.language.id_for_node_kind(name, true) != 0
+            // the words split from leaves in commutative parents (see `AstNode::internal_new`)
+            // are not part of the grammar but can still be given signatures
+            name == "@virtual_word@"
+                || self.language.id_for_node_kind(name, true) != 0
                 || self.language.id_for_node_kind(name, false) != 0
         };
         let field_is_valid = |name: &'static str| self.language.field_id_for_name(name).is_some();
@@ -178,6 +427,30 @@ impl LangProfile {
             }
         }
 
+        for unsplit_node in self.unsplit_nodes {
+            if !name_is_valid(unsplit_node) {
+                return Err(format!("invalid unsplit node type: {unsplit_node:?}"));
+            }
+        }
+
+        for verbatim_node in self.verbatim_nodes {
+            if !name_is_valid(verbatim_node) {
+                return Err(format!("invalid verbatim node type: {verbatim_node:?}"));
+            }
+        }
+
+        for whitespace_node in self.whitespace_nodes {
+            if !name_is_valid(whitespace_node) {
+                return Err(format!("invalid whitespace node type: {whitespace_node:?}"));
+            }
+        }
+
+        for word_list_node in self.word_list_nodes {
+            if !name_is_valid(word_list_node) {
+                return Err(format!("invalid word list node type: {word_list_node:?}"));
+            }
+        }
+
         for commutative_parent in &self.commutative_parents {
             commutative_parent.check_kinds(&name_is_valid)?;
         }
@@ -247,6 +520,9 @@ pub struct CommutativeParent {
     pub right_delim: Option<&'static str>,
     /// any restrictions on which types of children are allowed to commute together. If empty, all children can commute together.
     pub children_groups: Vec<ChildrenGroup>,
+    /// whether the order of the children can be observed, so that this parent should only be
+    /// treated as commutative if the user opted in to it
+    order_sensitive: bool,
 }
 
 impl CommutativeParent {
@@ -258,6 +534,7 @@ impl CommutativeParent {
             left_delim: None,
             right_delim: None,
             children_groups: Vec::new(),
+            order_sensitive: false,
         }
     }
 
@@ -274,6 +551,7 @@ impl CommutativeParent {
             left_delim: Some(left_delim),
             right_delim: Some(right_delim),
             children_groups: Vec::new(),
+            order_sensitive: false,
         }
     }
 
@@ -289,6 +567,7 @@ impl CommutativeParent {
             left_delim: Some(left_delim),
             right_delim: None,
             children_groups: Vec::new(),
+            order_sensitive: false,
         }
     }
 
@@ -320,6 +599,7 @@ impl CommutativeParent {
             left_delim: Some(left_delim),
             right_delim: Some(right_delim),
             children_groups: Vec::new(),
+            order_sensitive: false,
         }
     }
 
@@ -341,6 +621,7 @@ impl CommutativeParent {
             left_delim: None,
             right_delim: None,
             children_groups: Vec::new(),
+            order_sensitive: false,
         }
     }
 
@@ -352,6 +633,16 @@ impl CommutativeParent {
         }
     }
 
+    /// Mark a commutative parent as order-sensitive: reordering its children is visible to
+    /// the program (for instance, the ordinals of enum entries), so it is only treated as
+    /// commutative when [`LangProfile::commute_order_sensitive`] is enabled.
+    pub(crate) fn order_sensitive(self) -> Self {
+        Self {
+            order_sensitive: true,
+            ..self
+        }
+    }
+
     /// the type of the root node
     pub(crate) fn parent_type(&self) -> &ParentType<'_> {
         &self.parent_type
@@ -511,15 +802,30 @@ mod tests {
     #[test]
     fn find_no_vcs() {
         fn find(filename: &str, name: Option<&str>) -> Result<&'static str, String> {
-            LangProfile::find(filename, name, None).map(|lang_profile| lang_profile.name)
+            LangProfile::find(filename, "", name, None).map(|lang_profile| lang_profile.name)
         }
         assert_eq!(find("file.json", None), Ok("JSON"));
         assert_eq!(find("file.java", Some("JSON")), Ok("JSON"));
         assert!(find("java", None).is_err());
         assert_eq!(find("go.mod", None), Ok("go.mod"));
+        assert_eq!(find("crates/app/Cargo.lock", None), Ok("Cargo.lock"));
+        assert_eq!(find("module/pom.xml", None), Ok("pom.xml"));
+        assert_eq!(
+            find(".github/workflows/ci.yml", None),
+            Ok("GitHub Actions workflow")
+        );
+        assert_eq!(
+            find("C:\\repo\\.github\\workflows\\release.yaml", None),
+            Ok("GitHub Actions workflow")
+        );
+        assert_eq!(find("workflows/ci.yml", None), Ok("YAML"));
+        assert_eq!(find(".github/workflows/ci.json", None), Ok("JSON"));
         assert_eq!(find("Makefile", None), Ok("GNU Make"));
         assert_eq!(find("file", Some("go.mod")), Ok("go.mod"));
         assert!(find("test.go.mod", None).is_err());
+        assert_eq!(find("src\\main.rs", None), Ok("Rust"));
+        assert_eq!(find("C:\\project\\v1.2\\go.mod", None), Ok("go.mod"));
+        assert_eq!(find("src/build.d\\Makefile", None), Ok("GNU Make"));
         assert!(
             find("file.json", Some("non-existent language")).is_err(),
             "If a language name is provided, the file name should be ignored"
@@ -530,10 +836,94 @@ mod tests {
         );
     }
 
+    #[test]
+    fn find_by_modeline() {
+        let find = |filename, contents| {
+            LangProfile::find(filename, contents, None, None).map(|lang_profile| lang_profile.name)
+        };
+
+        assert_eq!(
+            find("script", "#!/bin/env tool\n# -*- mode: python -*-\nx = 1\n"),
+            Ok("Python")
+        );
+        assert_eq!(
+            find("script", "// -*- Mode: c++; coding: utf-8 -*-\nint x;\n"),
+            Ok("C++")
+        );
+        assert_eq!(
+            find("script", "/* -*- java -*- */\nclass A {}\n"),
+            Ok("Java")
+        );
+        let last_lines = format!("{}# vim: set ft=ruby:\n", "x = 1\n".repeat(10));
+        assert_eq!(find("script", &last_lines), Ok("Ruby"));
+        assert_eq!(find("script", "-- vi: filetype=lua\n"), Ok("Lua"));
+        // the extension takes precedence
+        assert_eq!(find("file.rs", "// -*- mode: python -*-\n"), Ok("Rust"));
+        // modelines in the middle of the file are ignored
+        let middle = format!("{0}# vim: ft=ruby\n{0}", "x = 1\n".repeat(10));
+        assert!(find("script", &middle).is_err());
+        assert!(find("script", "# -*- mode: bogus -*-\n").is_err());
+        assert!(find("script", "# novim: ft=ruby\n").is_err());
+    }
+
+    #[test]
+    fn find_by_content() {
+        let detect =
+            |contents| LangProfile::detect_from_content(contents).map(|profile| profile.name);
+
+        assert_eq!(detect("{\n  \"a\": [1, 2]\n}\n"), Some("JSON"));
+        assert_eq!(
+            detect("<?xml version=\"1.0\"?>\n<a><b/></a>\n"),
+            Some("XML")
+        );
+        assert_eq!(
+            detect("import os\n\ndef f(x):\n    return x\n"),
+            Some("Python")
+        );
+        assert_eq!(detect("int main() {\n    return 0;\n}\n"), Some("C"));
+        // the guess is discarded if the contents don't parse in the guessed language
+        assert_eq!(detect("{ \"a\": \n"), None);
+        assert_eq!(detect("just some text\n"), None);
+    }
+
+    #[test]
+    fn find_with_detection() {
+        let detection = |filename, contents, cli_name| {
+            LangProfile::find_with_detection(filename, contents, cli_name, None)
+                .map(|(lang_profile, detection)| (lang_profile.name, detection))
+        };
+
+        assert_eq!(
+            detection("file.rs", "", None),
+            Ok(("Rust", LanguageDetection::Extension))
+        );
+        assert_eq!(
+            detection("file.rs", "", Some("python")),
+            Ok(("Python", LanguageDetection::CliOption))
+        );
+        assert_eq!(
+            detection("src/go.mod", "", None),
+            Ok(("go.mod", LanguageDetection::FileName))
+        );
+        assert_eq!(
+            detection(".github/workflows/ci.yml", "", None),
+            Ok(("GitHub Actions workflow", LanguageDetection::PathPattern))
+        );
+        assert_eq!(
+            detection("script", "# vim: ft=ruby\n", None),
+            Ok(("Ruby", LanguageDetection::Modeline))
+        );
+        assert_eq!(
+            LangProfile::find_with_detection("file.js", "", None, Some("py"))
+                .map(|(_, detection)| detection),
+            Ok(LanguageDetection::GitAttribute)
+        );
+    }
+
     #[test]
     fn find_vcs() {
         let find = |filename, cli_name, git_name| {
-            LangProfile::find(filename, cli_name, Some(git_name))
+            LangProfile::find(filename, "", cli_name, Some(git_name))
                 .map(|lang_profile| lang_profile.name)
         };
 
@@ -14,18 +14,29 @@
 //!
 //! Mergiraf is not designed to be used as a library so far, the Rust API is therefore not meant
 //! to be stable.
+//!
+//! The exception is [`merge_files`], which merges three revisions held in memory. It returns
+//! a [`MergeResult`], whose remaining conflicts can be inspected by parsing it into a
+//! [`ParsedMerge`] with [`MergeResult::parse`]. The [`MergedChunk`]s of the latter give the
+//! sides of each conflict, and [`ParsedMerge::conflict_count`], [`ParsedMerge::conflict_mass`]
+//! and [`ParsedMerge::extract_conflict_oids`] summarize them.
 
 pub mod ast;
 pub mod attempts;
 pub mod bug_reporter;
 pub(crate) mod changeset;
 pub(crate) mod class_mapping;
+pub mod commutative_report;
+pub mod conflict_log;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod git;
 pub mod lang_profile;
 pub mod line_based;
 pub(crate) mod matching;
 pub mod merge;
 pub(crate) mod merge_3dm;
+pub mod merge_history;
 pub(crate) mod merge_result;
 pub(crate) mod merged_text;
 pub(crate) mod merged_tree;
@@ -33,7 +44,9 @@ pub(crate) mod merged_tree;
 pub mod minimize;
 pub(crate) mod multimap;
 pub mod newline;
+pub mod node_trace;
 pub(crate) mod parsed_merge;
+pub(crate) mod patch;
 pub(crate) mod pcs;
 pub mod settings;
 pub(crate) mod signature;
@@ -47,6 +60,9 @@ pub(crate) mod tree_matcher;
 pub mod utils;
 pub(crate) mod visualizer;
 
+#[cfg(feature = "dev")]
+pub use visualizer::write_two_way_matching_to_dotty_file;
+
 use core::fmt::Write;
 use std::{path::Path, time::Instant};
 
@@ -54,11 +70,10 @@ use itertools::Itertools;
 use lang_profile::LangProfile;
 use log::debug;
 
-use merge_result::MergeResult;
-use parsed_merge::{PARSED_MERGE_DIFF2_DETECTED, ParsedMerge};
+use parsed_merge::PARSED_MERGE_DIFF2_DETECTED;
 use pcs::Revision;
 use settings::DisplaySettings;
-use structured::structured_merge;
+use structured::{StructuredMergeOptions, structured_merge};
 use supported_langs::SUPPORTED_LANGUAGES;
 
 /// Current way to disable Mergiraf
@@ -72,12 +87,16 @@ pub const EXIT_SUCCESS: i32 = 0;
 
 // Exit code for merge sub command
 pub const EXIT_MERGE_HAS_CONFLICTS: i32 = 1;
+pub const EXIT_MERGE_TOO_MANY_CONFLICTS: i32 = 3;
+pub const EXIT_MERGE_UNEXPECTED_OUTPUT: i32 = 4;
 
 //Exit code for solve sub command
 pub const EXIT_SOLVE_FAILED: i32 = 1;
 pub const EXIT_SOLVE_HAS_CONFLICTS: i32 = 2;
 
-pub use merge::line_merge_and_structured_resolution;
+pub use merge::{line_merge_and_structured_resolution, merge_files};
+pub use merge_result::{MergeResult, PreferredSide};
+pub use parsed_merge::{MergedChunk, ParsedMerge};
 
 #[cfg(feature = "dev")]
 pub use minimize::minimize;
@@ -90,6 +109,7 @@ pub use minimize::minimize;
 fn resolve_merge<'a>(
     parsed_merge: &ParsedMerge<'a>,
     settings: &DisplaySettings<'a>,
+    options: &StructuredMergeOptions,
     lang_profile: &LangProfile,
     debug_dir: Option<&Path>,
 ) -> Result<MergeResult, String> {
@@ -110,6 +130,7 @@ fn resolve_merge<'a>(
         &right_rev,
         Some(parsed_merge),
         settings,
+        options,
         lang_profile,
         debug_dir,
     )
@@ -125,37 +146,81 @@ fn fxhasher() -> rustc_hash::FxHasher {
 /// Prints the list of supported languages,
 /// either in the format understood by `.gitattributes`,
 /// or in a more human-readable format.
-pub fn languages(gitattributes: bool) -> String {
+/// With `extensions_only`, only the file extensions are listed, one per line.
+pub fn languages(gitattributes: bool, extensions_only: bool) -> String {
     let mut res = String::new();
+    if extensions_only {
+        for extension in supported_extensions() {
+            let _ = writeln!(res, "{extension}");
+        }
+        return res;
+    }
     for lang_profile in &*SUPPORTED_LANGUAGES {
         let extensions = lang_profile.extensions;
         let file_names = lang_profile.file_names;
+        let path_patterns = lang_profile.path_patterns;
         if gitattributes {
             for extension in extensions {
                 let _ = writeln!(res, "*.{extension} merge=mergiraf");
             }
-            for file_name in file_names {
+            for file_name in file_names.iter().chain(path_patterns) {
                 let _ = writeln!(res, "{file_name} merge=mergiraf");
             }
         } else {
             let _ = writeln!(
                 res,
-                "{lang_profile} ({}{}{})",
-                extensions
-                    .iter()
-                    .format_with(", ", |ext, f| f(&format_args!("*.{ext}"))),
-                if extensions.is_empty() || file_names.is_empty() {
-                    ""
-                } else {
-                    ", "
-                },
-                file_names.iter().format(", "),
+                "{lang_profile} ({})",
+                (extensions.iter().map(|ext| format!("*.{ext}")))
+                    .chain(
+                        file_names
+                            .iter()
+                            .chain(path_patterns)
+                            .map(ToString::to_string)
+                    )
+                    .format(", "),
             );
         }
     }
     res
 }
 
+/// The tree-sitter grammar crates Mergiraf is built with, and their versions
+const GRAMMAR_VERSIONS: &[(&str, &str)] =
+    include!(concat!(env!("OUT_DIR"), "/grammar_versions.rs"));
+
+/// The implementation of `mergiraf languages --grammar-versions`.
+///
+/// Lists the tree-sitter grammar crates Mergiraf is built with, one per line, with their versions.
+/// As updating a grammar can change how files are merged, this helps reproducing merges.
+pub fn grammar_versions() -> String {
+    let mut res = String::new();
+    for (name, version) in GRAMMAR_VERSIONS {
+        let _ = writeln!(res, "{name} {version}");
+    }
+    res
+}
+
+/// The file extensions (without the leading `.`) of all the supported languages,
+/// for instance to register file associations in other tools
+pub fn supported_extensions() -> Vec<&'static str> {
+    SUPPORTED_LANGUAGES
+        .iter()
+        .flat_map(|lang_profile| lang_profile.extensions)
+        .copied()
+        .unique()
+        .collect()
+}
+
+/// The full file names (such as `go.mod`) handled by dedicated language profiles
+pub fn supported_filenames() -> Vec<&'static str> {
+    SUPPORTED_LANGUAGES
+        .iter()
+        .flat_map(|lang_profile| lang_profile.file_names)
+        .copied()
+        .unique()
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use crate::structured::ZDIFF3_DETECTED;
@@ -177,21 +242,77 @@ mod test {
 ";
         let settings = DisplaySettings::default();
         let parsed = ParsedMerge::parse(contents, &settings).unwrap();
-        let result = resolve_merge(&parsed, &settings, LangProfile::rust(), None);
+        let result = resolve_merge(
+            &parsed,
+            &settings,
+            &StructuredMergeOptions::default(),
+            LangProfile::rust(),
+            None,
+        );
         assert_eq!(result, Err(ZDIFF3_DETECTED.to_string()));
     }
 
     #[test]
     fn languages_plain() {
-        let plain_text = languages(false);
+        let plain_text = languages(false, false);
         assert!(plain_text.contains("Rust (*.rs)"));
         assert!(plain_text.contains("go.mod (go.mod)"));
     }
 
+    #[test]
+    fn languages_extensions_only() {
+        let extensions = languages(false, true);
+        let extensions = extensions.lines().collect_vec();
+        assert!(extensions.contains(&"rs"));
+        assert!(extensions.contains(&"java"));
+        assert!(extensions.contains(&"json"));
+        assert!(extensions.iter().all_unique());
+    }
+
     #[test]
     fn languages_gitattributes() {
-        let gitattributes_config = languages(true);
+        let gitattributes_config = languages(true, false);
         assert!(gitattributes_config.contains("*.rs merge=mergiraf"));
         assert!(gitattributes_config.contains("go.mod merge=mergiraf"));
     }
+
+    #[test]
+    fn languages_grammar_versions() {
+        let versions = grammar_versions();
+        let version_of = |name| {
+            (versions.lines())
+                .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+                .unwrap_or_else(|| panic!("{name} is missing from {versions}"))
+        };
+        assert!(version_of("tree-sitter-rust-orchard").starts_with("0.14"));
+        assert!(version_of("tree-sitter-java-orchard").starts_with("0.3"));
+    }
+
+    #[test]
+    fn supported_extensions_and_filenames() {
+        assert!(supported_extensions().contains(&"rs"));
+        assert!(supported_filenames().contains(&"go.mod"));
+
+        // an extension or file name claimed by two languages would never select the second one
+        for (kind, names) in [
+            (
+                "extension",
+                (SUPPORTED_LANGUAGES.iter())
+                    .flat_map(|lang_profile| lang_profile.extensions)
+                    .collect_vec(),
+            ),
+            (
+                "file name",
+                (SUPPORTED_LANGUAGES.iter())
+                    .flat_map(|lang_profile| lang_profile.file_names)
+                    .collect_vec(),
+            ),
+        ] {
+            let duplicates = names.iter().duplicates().collect_vec();
+            assert!(
+                duplicates.is_empty(),
+                "{kind}s used by several languages: {duplicates:?}"
+            );
+        }
+    }
 }
@@ -4,6 +4,8 @@ use typed_arena::Arena;
 
 use crate::{lang_profile::LangProfile, parsed_merge::ParsedMerge, settings::DisplaySettings};
 pub const LINE_BASED_METHOD: &str = "line_based";
+/// The method of a line-based merge of revisions which already contain conflict markers
+pub const EXISTING_CONFLICTS_METHOD: &str = "line_based_existing_conflicts";
 
 /// Returns a line-based merge in the form of a [ParsedMerge] object,
 /// representing the merged parts and conflicting parts in a structured way.
@@ -34,19 +36,29 @@ fn line_based_merge_parsed_checked(
     contents_right: &str,
     settings: &DisplaySettings,
 ) -> Option<ParsedMerge<'static>> {
+    let merged_contents = diff3_merge(contents_base, contents_left, contents_right, settings);
+    ParsedMerge::parse(merged_contents.leak(), settings).ok()
+}
+
+/// Merge the revisions with diffy-imara, rendering the conflicts according to the settings
+fn diff3_merge(
+    contents_base: &str,
+    contents_left: &str,
+    contents_right: &str,
+    settings: &DisplaySettings,
+) -> String {
     let merged = MergeOptions::new()
         .set_conflict_marker_length(settings.conflict_marker_size_or_default())
-        .set_conflict_style(if settings.diff3 {
+        .set_conflict_style(if settings.show_base() {
             ConflictStyle::Diff3
         } else {
             ConflictStyle::Merge
         })
         .set_algorithm(Algorithm::Histogram)
         .merge(contents_base, contents_left, contents_right);
-    let merged_contents = match merged {
-        Ok(contents) | Err(contents) => contents.leak(),
-    };
-    ParsedMerge::parse(merged_contents, settings).ok()
+    match merged {
+        Ok(contents) | Err(contents) => contents,
+    }
 }
 
 /// Perform a textual merge with the diff3 algorithm.
@@ -62,6 +74,43 @@ pub fn line_based_merge(
     parsed_merge.into_merge_result(settings)
 }
 
+/// Perform a textual merge of revisions which already contain conflict markers,
+/// for instance because they were committed by mistake after a previous merge.
+///
+/// Unlike [`line_based_merge`], this doesn't attempt to parse the conflicts of the merge, as the
+/// existing conflict markers would get in the way. Instead, every left marker found in the output
+/// is counted as a conflict, and since their sizes are unknown, the entire output is counted in
+/// the conflict mass.
+pub(crate) fn line_based_merge_with_existing_conflicts(
+    contents_base: &str,
+    contents_left: &str,
+    contents_right: &str,
+    settings: &DisplaySettings,
+) -> MergeResult {
+    let contents = diff3_merge(contents_base, contents_left, contents_right, settings);
+    let left_marker = "<".repeat(settings.conflict_marker_size_or_default());
+    let conflict_count = contents
+        .lines()
+        .filter(|line| {
+            line.strip_prefix(&left_marker)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+        })
+        .count();
+    let conflict_mass = if conflict_count > 0 {
+        contents.len()
+    } else {
+        0
+    };
+    MergeResult {
+        contents,
+        conflict_count,
+        conflict_mass,
+        method: EXISTING_CONFLICTS_METHOD,
+        has_additional_issues: true,
+        line_based_conflict_mass: conflict_mass,
+    }
+}
+
 /// Do a line-based merge. If it is conflict-free, also check if it introduced any duplicate signatures,
 /// in which case this is logged as an additional issue on the merge result.
 pub(crate) fn line_based_merge_with_duplicate_signature_detection(
@@ -252,5 +301,30 @@ class OtherClass {
 }
         ",
         );
+
+        // When not attempting to parse the conflicts, the pre-existing one is counted as such
+        let merge = line_based_merge_with_existing_conflicts(
+            contents_base,
+            contents_left_7,
+            contents_right,
+            settings_7,
+        );
+        assert_eq!(merge.conflict_count, 1);
+        assert!(merge.has_additional_issues);
+        assert_eq!(
+            merge.contents,
+            "\
+/**
+<<<<<<< HEAD
+ * Doc comment
+=======
+ * Better docs
+>>>>>>> origin/main
+ */
+class MyClass {
+}
+class OtherClass {
+}"
+        );
     }
 }
@@ -1,6 +1,7 @@
 use std::{
     borrow::Cow,
     env, fs, io,
+    ops::RangeInclusive,
     path::{Path, PathBuf},
     process::{Command, exit},
     sync::Arc,
@@ -10,13 +11,18 @@ use std::{
 use clap::{ArgAction, Args, Parser, Subcommand};
 use log::warn;
 use mergiraf::{
-    ENABLING_ENV_VAR, EXIT_MERGE_HAS_CONFLICTS, EXIT_SOLVE_FAILED, EXIT_SOLVE_HAS_CONFLICTS,
-    EXIT_SUCCESS,
+    ENABLING_ENV_VAR, EXIT_MERGE_HAS_CONFLICTS, EXIT_MERGE_TOO_MANY_CONFLICTS,
+    EXIT_MERGE_UNEXPECTED_OUTPUT, EXIT_SOLVE_FAILED, EXIT_SOLVE_HAS_CONFLICTS, EXIT_SUCCESS,
+    PreferredSide,
     attempts::AttemptsCache,
     bug_reporter::report_bug,
-    languages, line_merge_and_structured_resolution, merge,
-    newline::{imitate_newline_style, infer_newline_style, normalize_to_lf},
-    settings::{ConflictRegexes, DisplaySettings},
+    commutative_report, conflict_log, git, languages,
+    line_based::{EXISTING_CONFLICTS_METHOD, line_based_merge},
+    line_merge_and_structured_resolution, merge,
+    merge_history::merge_history,
+    newline::{LineEndings, normalize_to_lf},
+    node_trace::{self, TracedNode},
+    settings::{DeleteModifyStrategy, DisplaySettings},
     solve,
     utils::{read_file_to_string, write_string_to_file},
 };
@@ -30,6 +36,9 @@ struct CliArgs {
     /// Verbosity
     #[arg(short, long, global = true)]
     verbose: bool,
+    /// Only output errors on stderr, suppressing warnings and informational messages
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
     #[command(subcommand)]
     command: CliCommand,
 }
@@ -57,18 +66,78 @@ struct MergeOrSolveArgs {
     /// Enable syntax-aware merging despite the presence of syntax errors
     #[arg(long, default_missing_value = "true", num_args = 0..=1, require_equals = true)]
     allow_parse_errors: Option<bool>,
+    /// Merge commutatively the elements whose order can be observed, such as enum entries
+    #[arg(long, default_missing_value = "true", num_args = 0..=1, require_equals = true)]
+    commute_order_sensitive: Option<bool>,
+    /// Only merge commutatively the children of nodes of these grammar types, such as
+    /// `--commutative-only=source_file,use_list`
+    #[arg(long, value_delimiter = ',')]
+    commutative_only: Option<Vec<String>>,
+    /// Add a comment such as `/* mergiraf:conflict id=1 */` before each conflict,
+    /// in languages which have comments
+    #[arg(long)]
+    conflict_comment: bool,
+    /// Match elements which were renamed on one side, such as methods, to their original
+    /// version when their contents are similar, so that changes made to them on the other
+    /// side can be carried over
+    #[arg(long)]
+    detect_renames: bool,
+    /// Only resolve the conflicts which can be merged commutatively (such as imports added
+    /// on both sides), leaving the other ones as a line-based merge would
+    #[arg(long)]
+    commutative_only_resolution: bool,
+    /// How to merge an element deleted on one side and modified on the other: leave a
+    /// `conflict`, or `keep-modified` to keep the side which modified it
+    #[arg(long, value_name = "STRATEGY", default_value = "conflict")]
+    delete_modify: DeleteModifyStrategy,
+    /// Run the whole merge on a single thread, for reproducible timings when profiling.
+    /// The timeout is not enforced in this mode
+    #[arg(long)]
+    single_threaded: bool,
+    /// Log all the decisions made during the merge about the node with this id,
+    /// as shown in the files written with `--debug`. Prefix it with `base:`, `left:`
+    /// or `right:` to only follow the node of that revision
+    #[arg(long, value_name = "[REV:]ID")]
+    trace_node: Option<TracedNode>,
+    /// Replace the structured conflicts whose sides are larger than this size (in bytes)
+    /// with a line-based merge of the element they are in, which is often easier to read
+    #[arg(long, value_name = "BYTES")]
+    max_conflict_size: Option<usize>,
+    /// Log each place where additions from both sides were combined by a commutative merge,
+    /// with the signatures of the added elements
+    #[arg(long)]
+    commutative_report: bool,
+    /// Append a line to this file for each conflict which remains, with the signature of the
+    /// element it is in (such as the name of a method)
+    #[arg(long, value_name = "PATH")]
+    conflict_log: Option<PathBuf>,
 }
 
 #[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)] // only constructed once, when parsing the arguments
 enum CliCommand {
     /// Do a three-way merge
     Merge {
         /// Path to the file containing the base revision
+        /// (the left revision with `--input-format=git-stage`)
         base: PathBuf,
         /// Path to the file containing the left revision
+        /// (the base revision with `--input-format=git-stage`)
         left: PathBuf,
         /// Path to the file containing the right revision
         right: PathBuf,
+        /// The convention the revisions are passed in: `mergiraf` (base, left, right)
+        /// or `git-stage` (left, base, right, overwriting the left revision with the result
+        /// unless `--output` is set, like `git merge-file` and merge drivers configured
+        /// with `%A %O %B`)
+        #[arg(long, value_name = "FORMAT", default_value = "mergiraf")]
+        input_format: InputFormat,
+        /// Read the revisions from the Git repository in the current directory instead of the
+        /// working tree. BASE, LEFT and RIGHT are then object names such as `main:src/lib.rs`,
+        /// or revisions such as `main` when `--path-name` is set, in which case the file at
+        /// that path is read from each of them
+        #[arg(long, conflicts_with_all = ["git", "input_format"])]
+        from_git: bool,
         /// Only attempt to merge the files by solving textual conflicts,
         /// without doing a full structured merge from the ground up.
         #[arg(long)]
@@ -81,6 +150,10 @@ enum CliCommand {
         /// The path to the file to write the merge result to
         #[arg(short, long, conflicts_with = "git")]
         output: Option<PathBuf>,
+        /// Keep the left side of unresolved conflicts in the output file
+        /// and write the conflicts themselves to a companion `.rej` file
+        #[arg(long, requires = "output")]
+        reject: bool,
         /// Final path in which the merged result will be stored.
         /// It is used to detect the language of the files using the file extension.
         #[arg(short, long)]
@@ -97,9 +170,80 @@ enum CliCommand {
         #[arg(short = 'y', long)]
         // the choice of 'y' is inherited from Git's merge driver interface
         right_name: Option<String>,
+        /// In conflict markers, replace the revision names which are commit ids
+        /// with the subjects of those commits, read from the repository in the current directory
+        #[arg(long)]
+        commit_subjects: bool,
         /// Maximum number of milliseconds to try doing the merging for, after which we fall back on git's own algorithm. Set to 0 to disable this limit.
         #[arg(short, long)]
         timeout: Option<u64>,
+        /// Always include the base revision in conflicts, even when falling back on Git.
+        /// This lets `mergiraf solve` reconstruct all three revisions later on
+        #[arg(long)]
+        diff3: bool,
+        /// Maximum number of conflicts the merge may have. If it has more, the result is
+        /// discarded and the command exits with a distinct code
+        #[arg(long)]
+        max_conflicts: Option<usize>,
+        /// Instead of discarding a merge with too many conflicts, output a line-based merge
+        #[arg(long, requires = "max_conflicts")]
+        line_based_fallback: bool,
+        /// Only merge in a structured way around these lines of the line-based merge,
+        /// given as `START:END` (1-based, inclusive), to speed up the merge of large files
+        #[arg(long, value_parser = parse_line_range, value_name = "START:END")]
+        range: Option<RangeInclusive<usize>>,
+        /// Resolve the conflicts that remain after merging by keeping the `left` or `right`
+        /// side entirely. The changes from the other side in those regions are lost
+        #[arg(long, value_name = "SIDE", conflicts_with = "reject")]
+        prefer: Option<PreferredSide>,
+        /// Check that the merged output parses again, and fall back on a line-based merge
+        /// if it does not
+        #[arg(long)]
+        selftest_roundtrip: bool,
+        /// Remove the whitespace at the end of lines (outside of string literals)
+        /// in all revisions before merging them
+        #[arg(long)]
+        normalize_trailing_whitespace: bool,
+        /// Print the language used to merge the files, and how it was detected
+        #[arg(long)]
+        print_language: bool,
+        /// Do not interpret the lines looking like conflict markers in the revisions,
+        /// for files such as test fixtures which legitimately contain them.
+        /// The conflicts are then marked with longer markers
+        #[arg(long)]
+        no_parse_markers: bool,
+        /// Only output a structured merge if it has neither more conflicts nor larger ones
+        /// than a line-based merge, and output the line-based merge otherwise
+        #[arg(long)]
+        never_worse_than_line_based: bool,
+        /// Output the changes made to the base revision as a unified diff, instead of
+        /// the entire merged file. Remaining conflicts appear in it with their markers
+        #[arg(long, conflicts_with_all = ["git", "reject"])]
+        changed_only: bool,
+        /// If the language of the files can't be detected from their name, guess it from
+        /// their contents. The guess is only used if the files parse in that language
+        #[arg(long)]
+        detect_language_by_content: bool,
+        /// Print statistics about the merge on standard error: the method which produced it,
+        /// its conflicts, and a heuristic confidence score between 0 and 1 in its resolution
+        #[arg(long)]
+        stats: bool,
+        /// Do the merge without writing anything, only reporting how many conflicts would remain
+        #[arg(long, conflicts_with_all = ["git", "output", "reject", "changed_only", "conflict_log"])]
+        dry_run: bool,
+        /// With `--dry-run`, also list each conflict that would remain, with what both sides
+        /// did to cause it and the element it is in (such as a method)
+        #[arg(long, requires = "dry_run")]
+        explain: bool,
+        /// Only write the merge to the output file (or to the left revision with `--git`)
+        /// if no conflicts remain. Otherwise, print it on standard output and leave the file
+        /// untouched, so that the conflicts can be reviewed first
+        #[arg(long, conflicts_with_all = ["reject", "dry_run"])]
+        stdout_on_conflict: bool,
+        /// Compare the merge with the contents of this file instead of writing it, printing
+        /// their differences and exiting with code 4 if they don't match exactly
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["git", "output", "reject", "dry_run", "stdout_on_conflict"])]
+        expect: Option<PathBuf>,
     },
     /// Solve the conflicts in a merged file
     Solve {
@@ -122,6 +266,21 @@ enum CliCommand {
             conflicts_with = "stdout",
         )]
         keep_backup: bool,
+        /// Keep the left side of unresolved conflicts in the solved file
+        /// and write the conflicts themselves to a companion `.rej` file
+        #[arg(long, conflicts_with = "stdout")]
+        reject: bool,
+        /// Only report whether all conflicts could be solved, without writing any file
+        #[arg(long, conflicts_with_all = ["stdout", "reject", "conflict_log"])]
+        check: bool,
+        /// Merge the base, ours and theirs revisions staged in the Git index
+        /// instead of solving the conflicts marked in the file
+        #[arg(long)]
+        from_index: bool,
+        /// Print the conflicts of the file as JSON, with their sides, byte offsets and the
+        /// signature of the element they are in, without solving them
+        #[arg(long, conflicts_with_all = ["stdout", "reject", "check", "from_index"])]
+        dump_conflicts_json: bool,
     },
     /// Review the resolution of a merge by showing the differences with a line-based merge
     Review {
@@ -133,11 +292,34 @@ enum CliCommand {
         /// Identifier of the merge case (if it did not return conflicts) or path to file with merge conflicts
         merge_id_or_file: String,
     },
+    /// Replay the merge commits of a branch, writing a CSV of the conflicts
+    /// left by line-based merging and by Mergiraf in each of them
+    MergeHistory {
+        /// The branch (or any other revision) whose merge commits should be replayed
+        #[arg(default_value = "HEAD")]
+        branch: String,
+        /// Maximum number of merge commits to replay, starting from the most recent one
+        #[arg(long)]
+        max_commits: Option<usize>,
+        /// The path to the file to write the CSV to (standard output by default)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Maximum number of milliseconds to try merging each file for. Set to 0 to disable this limit.
+        #[arg(short, long, default_value_t = 10000)]
+        timeout: u64,
+    },
     /// Show the supported languages
     Languages {
         /// Print the list in a format suitable for inclusion in gitattributes
         #[arg(long, default_value_t = false)]
         gitattributes: bool,
+        /// Only print the supported file extensions, one per line (without the leading `.`)
+        #[arg(long, conflicts_with = "gitattributes")]
+        extensions_only: bool,
+        /// Print the versions of the tree-sitter grammars Mergiraf is built with instead,
+        /// which is useful to reproduce a merge or report a bug
+        #[arg(long, conflicts_with_all = ["gitattributes", "extensions_only"])]
+        grammar_versions: bool,
     },
 }
 
@@ -147,6 +329,7 @@ fn main() {
     stderrlog::new()
         .module(module_path!())
         .verbosity(if args.verbose { 3 } else { 2 })
+        .quiet(args.quiet)
         .init()
         .unwrap();
 
@@ -160,18 +343,23 @@ fn main() {
 }
 
 fn real_main(args: CliArgs) -> Result<i32, String> {
+    let quiet = args.quiet;
     let return_code = match args.command {
         CliCommand::Merge {
             base,
             left,
             right,
+            input_format,
+            from_git,
             fast,
             path_name,
             git,
             output,
+            reject,
             base_name,
             left_name,
             right_name,
+            commit_subjects,
             merge_or_solve:
                 MergeOrSolveArgs {
                     debug_dir,
@@ -179,11 +367,48 @@ fn real_main(args: CliArgs) -> Result<i32, String> {
                     conflict_marker_size,
                     language,
                     allow_parse_errors,
+                    commute_order_sensitive,
+                    commutative_only,
+                    conflict_comment,
+                    detect_renames,
+                    commutative_only_resolution,
+                    delete_modify,
+                    single_threaded,
+                    max_conflict_size,
+                    trace_node,
+                    commutative_report,
+                    conflict_log,
                 },
             timeout,
+            diff3,
+            max_conflicts,
+            line_based_fallback,
+            range,
+            prefer,
+            selftest_roundtrip,
+            normalize_trailing_whitespace,
+            print_language,
+            no_parse_markers,
+            never_worse_than_line_based,
+            changed_only,
+            detect_language_by_content,
+            stats,
+            dry_run,
+            explain,
+            stdout_on_conflict,
+            expect,
         } => {
             let old_git_detected = base_name.as_deref().is_some_and(|n| n == "%S");
 
+            let [base, left, right] = input_format.arrange([base, left, right]);
+            let git = git || (input_format == InputFormat::GitStage && output.is_none());
+            let [base, left, right] = match &path_name {
+                Some(path_name) if from_git => [base, left, right].map(|revision| {
+                    PathBuf::from(format!("{}:{}", revision.display(), path_name.display()))
+                }),
+                _ => [base, left, right],
+            };
+
             let base = base.leak();
             let left = left.leak();
             let right = right.leak();
@@ -192,7 +417,7 @@ fn real_main(args: CliArgs) -> Result<i32, String> {
             let path_name = path_name.map(|s| &*s.leak());
             let debug_dir = debug_dir.map(|s| &*s.leak());
 
-            let settings: DisplaySettings<'static> = DisplaySettings::new(
+            let mut settings: DisplaySettings<'static> = DisplaySettings::new(
                 compact,
                 conflict_marker_size,
                 match base_name {
@@ -211,16 +436,37 @@ fn real_main(args: CliArgs) -> Result<i32, String> {
                     None => Some(right.to_string_lossy()),
                 },
             );
+            settings.max_conflict_size = max_conflict_size;
+            settings.force_diff3 = diff3;
+            if commit_subjects {
+                let working_dir = env::current_dir().expect("Invalid current directory");
+                for name in [
+                    &mut settings.base_revision_name,
+                    &mut settings.left_revision_name,
+                    &mut settings.right_revision_name,
+                ] {
+                    if let Some(oid) = name.as_deref()
+                        && git::is_oid(oid)
+                        && let Some(subject) = git::commit_subject(&working_dir, oid)
+                    {
+                        *name = Some(Cow::Owned(subject));
+                    }
+                }
+            }
 
             {
                 let mergiraf_disabled = env::var(ENABLING_ENV_VAR).as_deref() == Ok("0");
 
                 if mergiraf_disabled {
-                    return fallback_to_git_merge_file(base, left, right, git, &output, &settings)
-                        .map_err(|e| format!("error when calling git-merge-file: {e}"));
+                    return fallback_to_git_merge_file(
+                        base, left, right, git, from_git, &output, &settings, quiet,
+                    )
+                    .map_err(|e| format!("error when calling git-merge-file: {e}"));
                 }
             }
 
+            node_trace::set_traced_node(trace_node);
+            commutative_report::set_commutative_report(commutative_report);
             if let Some(debug_dir) = debug_dir {
                 fs::create_dir_all(debug_dir)
                     .map_err(|err| format!("could not create the debug directory: {err}"))?;
@@ -230,14 +476,23 @@ fn real_main(args: CliArgs) -> Result<i32, String> {
             let fname_left = &*left;
             let fname_right = &*right;
 
+            let working_dir = env::current_dir().expect("Invalid current directory");
+
+            let read_revision = |revision: &Path| {
+                if from_git {
+                    git::read_blob(&working_dir, &revision.to_string_lossy())
+                } else {
+                    read_file_to_string(revision)
+                }
+            };
             let (
                 Ok(original_contents_base),
                 Ok(original_contents_left),
                 Ok(original_contents_right),
             ) = (
-                read_file_to_string(fname_base),
-                read_file_to_string(fname_left),
-                read_file_to_string(fname_right),
+                read_revision(fname_base),
+                read_revision(fname_left),
+                read_revision(fname_right),
             )
             else {
                 // The case we're actually catching here is an input file being non-UTF-8.
@@ -246,36 +501,21 @@ fn real_main(args: CliArgs) -> Result<i32, String> {
                 // like a file not being present etc. -- but that's okay, since in that case
                 // the output of `git merge-file` is comparable to what we would've emitted
                 // (debug representation of `io::Error`)
-                return fallback_to_git_merge_file(base, left, right, git, &output, &settings)
-                    .map_err(|e| format!("error when calling git-merge-file: {e}"));
+                return fallback_to_git_merge_file(
+                    base, left, right, git, from_git, &output, &settings, quiet,
+                )
+                .map_err(|e| format!("error when calling git-merge-file: {e}"));
             };
 
-            {
-                let ConflictRegexes {
-                    diff2: re_diff2,
-                    diff3: re_diff3,
-                    diff3_no_newline: re_diff3_no_newline,
-                    ..
-                } = settings.conflict_regexes();
-                for (side, contents) in [
-                    ("base", &original_contents_base),
-                    ("left", &original_contents_left),
-                    ("right", &original_contents_right),
-                ] {
-                    if re_diff3.is_match(contents)
-                        || re_diff2.is_match(contents)
-                        || re_diff3_no_newline.is_match(contents)
-                    {
-                        warn!("{side} side contains conflict markers, falling back to Git");
-                        return fallback_to_git_merge_file(
-                            base, left, right, git, &output, &settings,
-                        )
-                        .map_err(|e| format!("error when calling git-merge-file: {e}"));
-                    }
-                }
+            if no_parse_markers {
+                settings.avoid_conflict_markers_in(&[
+                    &original_contents_base,
+                    &original_contents_left,
+                    &original_contents_right,
+                ]);
             }
 
-            let original_newline_style = infer_newline_style(&original_contents_left);
+            let original_line_endings = LineEndings::infer(&original_contents_left);
 
             let contents_base = normalize_to_lf(original_contents_base);
             let contents_left = normalize_to_lf(original_contents_left);
@@ -285,39 +525,180 @@ fn real_main(args: CliArgs) -> Result<i32, String> {
             let contents_left = Arc::new(contents_left);
             let contents_right = Arc::new(contents_right);
 
-            let attempts_cache = AttemptsCache::new(None, None).ok();
+            // successful merges are stored for later review, which a dry run must not do
+            let attempts_cache = (!dry_run)
+                .then(|| AttemptsCache::new(None, None).ok())
+                .flatten();
 
             let fname_base = path_name.unwrap_or(fname_base);
 
-            let working_dir = env::current_dir().expect("Invalid current directory");
+            let reject_settings = reject.then(|| settings.clone());
+            let fallback_settings = line_based_fallback.then(|| settings.clone());
+            let prefer_settings = prefer.map(|side| (side, settings.clone()));
+            let conflict_log_settings = conflict_log.map(|path| (path, settings.clone()));
+            let explain_settings = explain.then(|| settings.clone());
+            let git_fallback_settings = settings.clone();
 
             let mut merge_result = line_merge_and_structured_resolution(
-                contents_base,
-                contents_left,
-                contents_right,
+                Arc::clone(&contents_base),
+                Arc::clone(&contents_left),
+                Arc::clone(&contents_right),
                 fname_base,
                 settings,
                 !fast,
                 attempts_cache.as_ref(),
                 merge::CliOpts {
                     allow_parse_errors,
+                    commute_order_sensitive,
+                    commutative_only,
+                    detect_renames,
+                    commutative_only_resolution,
+                    delete_modify,
+                    single_threaded,
+                    conflict_comment,
                     language: language.as_deref(),
+                    line_range: range,
+                    selftest_roundtrip,
+                    normalize_trailing_whitespace,
+                    print_language,
+                    no_parse_markers,
+                    never_worse_than_line_based,
+                    detect_language_by_content,
                 },
                 Some(&working_dir),
                 debug_dir,
-                Duration::from_millis(timeout.unwrap_or(if fast { 5000 } else { 10000 })),
+                timeout.map_or(
+                    if fast {
+                        merge::DEFAULT_TIMEOUT / 2
+                    } else {
+                        merge::DEFAULT_TIMEOUT
+                    },
+                    Duration::from_millis,
+                ),
             );
-            merge_result.contents =
-                imitate_newline_style(&merge_result.contents, original_newline_style);
-            if let Some(fname_out) = output {
-                write_string_to_file(&fname_out, &merge_result.contents)?;
+            if merge_result.method == EXISTING_CONFLICTS_METHOD {
+                // the revisions already contain conflict markers, which Git merges as it would
+                // have without Mergiraf
+                return fallback_to_git_merge_file(
+                    base,
+                    left,
+                    right,
+                    git,
+                    from_git,
+                    &output,
+                    &git_fallback_settings,
+                    quiet,
+                )
+                .map_err(|e| format!("error when calling git-merge-file: {e}"));
+            }
+            let exceeded_max_conflicts =
+                max_conflicts.filter(|max| merge_result.conflict_count > *max);
+            if let Some(max_conflicts) = exceeded_max_conflicts {
+                warn!(
+                    "The merge has {} conflicts, more than the maximum of {max_conflicts}",
+                    merge_result.conflict_count,
+                );
+                let Some(fallback_settings) = fallback_settings else {
+                    return Ok(EXIT_MERGE_TOO_MANY_CONFLICTS);
+                };
+                merge_result = line_based_merge(
+                    &contents_base,
+                    &contents_left,
+                    &contents_right,
+                    &fallback_settings,
+                );
+            }
+            // computed before conflicts are resolved by keeping one side, which isn't
+            // a resolution the score should reward
+            let stats = stats.then(|| {
+                format!(
+                    "Mergiraf: {} merge with {} conflict(s) of mass {} (line-based merge: {}), confidence {:.2}",
+                    merge_result.method,
+                    merge_result.conflict_count,
+                    merge_result.conflict_mass,
+                    merge_result.line_based_conflict_mass,
+                    merge_result.confidence(),
+                )
+            });
+            if let Some((side, prefer_settings)) = prefer_settings {
+                merge_result = merge_result.resolve_in_favor_of(side, &prefer_settings)?;
+            }
+            // the conflict log is computed from the merged contents, even when only the
+            // changes from the base are output
+            let merged_contents = original_line_endings.imitate(&merge_result.contents);
+            merge_result.contents = if changed_only {
+                original_line_endings.imitate(
+                    &merge_result.changes_from_base(&contents_base, &fname_base.to_string_lossy()),
+                )
+            } else {
+                merged_contents.clone()
+            };
+            let mut unexpected_output = false;
+            if dry_run {
+                println!(
+                    "{}: {} conflict(s) would remain",
+                    fname_base.display(),
+                    merge_result.conflict_count
+                );
+                if let Some(settings) = explain_settings {
+                    print!(
+                        "{}",
+                        conflict_log::explain_conflicts(
+                            fname_base,
+                            &merge_result.contents,
+                            &settings,
+                            language.as_deref(),
+                            Some(&working_dir),
+                        )?
+                    );
+                }
+            } else if let Some(fname_expected) = &expect {
+                let expected = read_file_to_string(fname_expected)?;
+                if let Some(differences) = merge_result
+                    .differences_with_expected(&expected, &fname_expected.to_string_lossy())
+                {
+                    print!("{differences}");
+                    unexpected_output = true;
+                }
+            } else if stdout_on_conflict && merge_result.conflict_count > 0 {
+                print!("{}", merge_result.contents);
+            } else if let Some(fname_out) = output {
+                if let Some(reject_settings) = reject_settings {
+                    write_with_rejects(
+                        &fname_out,
+                        merge_result.split_rejects(&reject_settings)?,
+                        &original_line_endings,
+                    )?;
+                } else {
+                    write_string_to_file(&fname_out, &merge_result.contents)?;
+                }
             } else if git {
                 write_string_to_file(fname_left, &merge_result.contents)?;
             } else {
                 print!("{}", merge_result.contents);
             }
+            if let Some((log_path, settings)) = conflict_log_settings
+                && merge_result.conflict_count > 0
+            {
+                conflict_log::append_to_conflict_log(
+                    &log_path,
+                    fname_base,
+                    &merged_contents,
+                    &settings,
+                    language.as_deref(),
+                    Some(&working_dir),
+                )?;
+            }
 
-            if merge_result.conflict_count > 0 {
+            if let Some(stats) = stats {
+                eprintln!("{stats}");
+            }
+
+            if unexpected_output {
+                EXIT_MERGE_UNEXPECTED_OUTPUT
+            } else if exceeded_max_conflicts.is_some() {
+                EXIT_MERGE_TOO_MANY_CONFLICTS
+            } else if merge_result.conflict_count > 0 {
                 if old_git_detected {
                     warn!(
                         "Using Git v2.44.0 or above is recommended to get meaningful revision names on conflict markers when using Mergiraf."
@@ -337,9 +718,24 @@ fn real_main(args: CliArgs) -> Result<i32, String> {
                     conflict_marker_size,
                     language,
                     allow_parse_errors,
+                    commute_order_sensitive,
+                    commutative_only,
+                    conflict_comment,
+                    detect_renames,
+                    commutative_only_resolution,
+                    delete_modify,
+                    single_threaded,
+                    max_conflict_size,
+                    trace_node,
+                    commutative_report,
+                    conflict_log,
                 },
             stdout,
             keep_backup,
+            reject,
+            check,
+            from_index,
+            dump_conflicts_json,
         } => {
             if conflict_location_looks_like_jj_repo(&fname_conflicts) {
                 return Err(
@@ -354,6 +750,8 @@ fn real_main(args: CliArgs) -> Result<i32, String> {
                 );
             }
 
+            node_trace::set_traced_node(trace_node);
+            commutative_report::set_commutative_report(commutative_report);
             if let Some(debug_dir) = &debug_dir {
                 fs::create_dir_all(debug_dir)
                     .map_err(|err| format!("could not create the debug directory: {err}"))?;
@@ -362,24 +760,71 @@ fn real_main(args: CliArgs) -> Result<i32, String> {
             let original_conflict_contents = read_file_to_string(&fname_conflicts)?;
 
             let working_dir = env::current_dir().expect("Invalid current directory");
+            if dump_conflicts_json {
+                let settings =
+                    DisplaySettings::new(compact, conflict_marker_size, None, None, None);
+                let dump = conflict_log::conflicts_to_json(
+                    &fname_conflicts,
+                    &original_conflict_contents,
+                    &settings,
+                    language.as_deref(),
+                    Some(&working_dir),
+                )?;
+                println!("{dump}");
+                return Ok(EXIT_SUCCESS);
+            }
             let postprocessed = solve::solve(
                 &fname_conflicts,
                 &original_conflict_contents,
                 solve::CliOpts {
                     allow_parse_errors,
+                    commute_order_sensitive,
+                    commutative_only,
                     compact,
+                    conflict_comment,
                     conflict_marker_size,
+                    detect_renames,
+                    commutative_only_resolution,
+                    delete_modify,
+                    single_threaded,
+                    max_conflict_size,
                     language: language.as_deref(),
+                    from_index,
                 },
                 &working_dir,
                 debug_dir.as_deref(),
             );
             match postprocessed {
                 Ok(merged) => {
-                    if stdout {
+                    if check {
+                        if merged.conflict_count > 0 {
+                            println!(
+                                "{}: {} conflict(s) would remain",
+                                fname_conflicts.display(),
+                                merged.conflict_count
+                            );
+                        } else {
+                            println!("{}: all conflicts can be solved", fname_conflicts.display());
+                        }
+                    } else if stdout {
                         print!("{}", merged.contents);
                     } else {
-                        write_string_to_file(&fname_conflicts, &merged.contents)?;
+                        if reject {
+                            let settings = DisplaySettings::new(
+                                compact,
+                                conflict_marker_size,
+                                None,
+                                None,
+                                None,
+                            );
+                            write_with_rejects(
+                                &fname_conflicts,
+                                merged.split_rejects(&settings)?,
+                                &LineEndings::infer(&original_conflict_contents),
+                            )?;
+                        } else {
+                            write_string_to_file(&fname_conflicts, &merged.contents)?;
+                        }
                         if keep_backup {
                             write_string_to_file(
                                 fname_conflicts.with_added_extension("orig"),
@@ -387,6 +832,20 @@ fn real_main(args: CliArgs) -> Result<i32, String> {
                             )?;
                         }
                     };
+                    if let Some(log_path) = conflict_log
+                        && merged.conflict_count > 0
+                    {
+                        let settings =
+                            DisplaySettings::new(compact, conflict_marker_size, None, None, None);
+                        conflict_log::append_to_conflict_log(
+                            &log_path,
+                            &fname_conflicts,
+                            &merged.contents,
+                            &settings,
+                            language.as_deref(),
+                            Some(&working_dir),
+                        )?;
+                    }
                     if merged.conflict_count > 0 {
                         EXIT_SOLVE_HAS_CONFLICTS
                     } else {
@@ -404,9 +863,44 @@ fn real_main(args: CliArgs) -> Result<i32, String> {
             attempts_cache.review_merge(&merge_id)?;
             EXIT_SUCCESS
         }
-        CliCommand::Languages { gitattributes } => {
-            let res = languages(gitattributes);
-            println!("{res}");
+        CliCommand::MergeHistory {
+            branch,
+            max_commits,
+            output,
+            timeout,
+        } => {
+            let working_dir = env::current_dir().expect("Invalid current directory");
+            let timeout = Duration::from_millis(timeout);
+            if let Some(output) = output {
+                let mut file = fs::File::create(&output)
+                    .map_err(|err| format!("could not create {}: {err}", output.display()))?;
+                merge_history(&working_dir, &branch, max_commits, timeout, &mut file)?;
+            } else {
+                merge_history(
+                    &working_dir,
+                    &branch,
+                    max_commits,
+                    timeout,
+                    &mut io::stdout(),
+                )?;
+            }
+            EXIT_SUCCESS
+        }
+        CliCommand::Languages {
+            gitattributes,
+            extensions_only,
+            grammar_versions,
+        } => {
+            let res = if grammar_versions {
+                mergiraf::grammar_versions()
+            } else {
+                languages(gitattributes, extensions_only)
+            };
+            if extensions_only || grammar_versions {
+                print!("{res}");
+            } else {
+                println!("{res}");
+            }
             EXIT_SUCCESS
         }
         CliCommand::Report { merge_id_or_file } => {
@@ -417,19 +911,97 @@ fn real_main(args: CliArgs) -> Result<i32, String> {
     Ok(return_code)
 }
 
+/// The order in which `mergiraf merge` expects the revisions to merge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputFormat {
+    /// Base, left, right: Mergiraf's own convention (`%O %A %B` in a Git merge driver)
+    Mergiraf,
+    /// Left, base, right, with the result written to the left revision: the convention of
+    /// `git merge-file` and of merge drivers configured with `%A %O %B`
+    GitStage,
+}
+
+impl InputFormat {
+    /// Reorder the revisions passed on the command line as base, left, right
+    fn arrange<T>(self, [first, second, third]: [T; 3]) -> [T; 3] {
+        match self {
+            Self::Mergiraf => [first, second, third],
+            Self::GitStage => [second, first, third],
+        }
+    }
+}
+
+impl std::str::FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mergiraf" => Ok(Self::Mergiraf),
+            "git-stage" => Ok(Self::GitStage),
+            _ => Err(format!(
+                "invalid input format `{s}`, expected `mergiraf` or `git-stage`"
+            )),
+        }
+    }
+}
+
+fn parse_line_range(range: &str) -> Result<RangeInclusive<usize>, String> {
+    let (start, end) = range
+        .split_once(':')
+        .ok_or("expected a range of lines such as 10:20")?;
+    let start: usize = start
+        .parse()
+        .map_err(|err| format!("invalid start: {err}"))?;
+    let end: usize = end.parse().map_err(|err| format!("invalid end: {err}"))?;
+    if start == 0 || end < start {
+        return Err("expected 1 <= START <= END".into());
+    }
+    Ok(start..=end)
+}
+
+/// Write the cleanly merged parts of a merge to `path`, and the remaining conflicts
+/// (if there are any) to the same path with the `.rej` suffix added, both with the line endings
+/// of the original file
+fn write_with_rejects(
+    path: &Path,
+    (merged, rejects): (String, String),
+    line_endings: &LineEndings,
+) -> Result<(), String> {
+    write_string_to_file(path, &line_endings.imitate(&merged))?;
+    if !rejects.is_empty() {
+        write_string_to_file(
+            path.with_added_extension("rej"),
+            &line_endings.imitate(&rejects),
+        )?;
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn fallback_to_git_merge_file(
     base: &Path,
     left: &Path,
     right: &Path,
     git: bool,
+    from_git: bool,
     output: &Option<PathBuf>,
     settings: &DisplaySettings,
+    quiet: bool,
 ) -> io::Result<i32> {
     let mut command = Command::new("git");
     command.arg("merge-file").arg("--diff-algorithm=histogram");
     if !git {
         command.arg("-p");
     }
+    if quiet {
+        command.arg("-q");
+    }
+    if settings.force_diff3 {
+        command.arg("--diff3");
+    }
+    if from_git {
+        command.arg("--object-id");
+    }
     if let Some(left_rev_name) = settings.left_revision_name.as_deref() {
         command.args(["-L", left_rev_name]);
 
@@ -605,4 +1177,62 @@ mod test {
         };
         assert!(!keep_backup);
     }
+
+    #[test]
+    fn input_format_flag() {
+        // as configured with `driver = mergiraf merge --input-format=git-stage %A %O %B -l %L -p %P`
+        let CliCommand::Merge {
+            base,
+            left,
+            right,
+            input_format,
+            path_name,
+            merge_or_solve:
+                MergeOrSolveArgs {
+                    conflict_marker_size,
+                    ..
+                },
+            ..
+        } = CliArgs::parse_from([
+            "mergiraf",
+            "merge",
+            "--input-format=git-stage",
+            ".merge_file_ours",
+            ".merge_file_base",
+            ".merge_file_theirs",
+            "-l",
+            "9",
+            "-p",
+            "src/main.rs",
+        ])
+        .command
+        else {
+            unreachable!("`mergiraf merge` should invoke the `Merge` subcommand")
+        };
+        assert_eq!(input_format, InputFormat::GitStage);
+        assert_eq!(
+            input_format.arrange([base, left, right]),
+            [
+                PathBuf::from(".merge_file_base"),
+                PathBuf::from(".merge_file_ours"),
+                PathBuf::from(".merge_file_theirs"),
+            ]
+        );
+        assert_eq!(conflict_marker_size, Some(9));
+        assert_eq!(path_name, Some(PathBuf::from("src/main.rs")));
+
+        // the default keeps Mergiraf's own order
+        let CliCommand::Merge { input_format, .. } =
+            CliArgs::parse_from(["mergiraf", "merge", "foo.c", "bar.c", "baz.c"]).command
+        else {
+            unreachable!("`mergiraf merge` should invoke the `Merge` subcommand")
+        };
+        assert_eq!(input_format, InputFormat::Mergiraf);
+        assert_eq!(input_format.arrange([1, 2, 3]), [1, 2, 3]);
+
+        assert!(
+            CliArgs::try_parse_from(["mergiraf", "merge", "--input-format=jdime", "a", "b", "c"])
+                .is_err()
+        );
+    }
 }
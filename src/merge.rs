@@ -3,22 +3,37 @@
 use std::{
     borrow::Cow,
     cmp::Ordering,
+    ops::{Range, RangeInclusive},
     path::Path,
+    ptr,
     sync::Arc,
     thread,
     time::{Duration, Instant},
 };
 
-use log::{debug, warn};
+use itertools::Itertools;
+use log::{debug, info, warn};
+
+use typed_arena::Arena;
 
 use crate::{
     DisplaySettings, LangProfile, MergeResult,
+    ast::AstNode,
     attempts::AttemptsCache,
     git::attr::GitAttrsForMerge,
+    lang_profile::LanguageDetection,
     line_based::{
-        LINE_BASED_METHOD, line_based_merge, line_based_merge_with_duplicate_signature_detection,
+        LINE_BASED_METHOD, line_based_merge, line_based_merge_parsed,
+        line_based_merge_with_duplicate_signature_detection,
+        line_based_merge_with_existing_conflicts,
     },
-    resolve_merge, structured_merge,
+    parsed_merge::ParsedMerge,
+    patch,
+    pcs::Revision,
+    resolve_merge,
+    settings::DeleteModifyStrategy,
+    structured::StructuredMergeOptions,
+    structured_merge,
 };
 
 /// Some options can be both:
@@ -29,7 +44,82 @@ use crate::{
 #[derive(Default)]
 pub struct CliOpts<'a> {
     pub allow_parse_errors: Option<bool>,
+    pub commute_order_sensitive: Option<bool>,
+    /// If set, only the commutative parents of these grammar node types are merged
+    /// commutatively, the others being merged like any other node
+    pub commutative_only: Option<Vec<String>>,
+    /// Whether to run all the steps of the merge on the current thread, without any timeout,
+    /// so that the timings of successive runs can be compared
+    pub single_threaded: bool,
+    /// How to merge the elements deleted on one side and modified on the other
+    pub delete_modify: DeleteModifyStrategy,
+    /// Whether to only combine the changes of both sides in commutative parents, leaving
+    /// the other nodes changed on both sides to a line-based merge
+    pub commutative_only_resolution: bool,
+    /// Whether to match nodes across renames, based on the similarity of their contents
+    pub detect_renames: bool,
+    pub conflict_comment: bool,
     pub language: Option<&'a str>,
+    /// If set, only merge in a structured way around these (1-based) lines of the line-based merge
+    pub line_range: Option<RangeInclusive<usize>>,
+    /// If set, check that the revisions reconstructed from the merge output parse again,
+    /// and fall back on a line-based merge otherwise
+    pub selftest_roundtrip: bool,
+    /// If set, remove the whitespace at the end of lines of all revisions before merging them
+    pub normalize_trailing_whitespace: bool,
+    /// If set, log which language was used for the merge and how it was determined
+    pub print_language: bool,
+    /// If set, the revisions are trusted not to contain conflicts, so that the lines
+    /// looking like conflict markers in them are not interpreted as such
+    pub no_parse_markers: bool,
+    /// If set, only keep a structured merge if it has neither more conflicts nor a larger
+    /// conflict mass than the line-based merge, and output the line-based merge otherwise
+    pub never_worse_than_line_based: bool,
+    /// If set, guess the language from the contents of the files when it can't be detected
+    /// otherwise
+    pub detect_language_by_content: bool,
+}
+
+/// The default timeout of `mergiraf merge`, after which the fastest merge available is used
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(10000);
+
+/// Merges three revisions of a file held in memory, for programs which don't deal with
+/// files on disk (for instance to merge Git notes or blobs).
+///
+/// The language to parse the revisions with is detected from `file_name`, unless `language`
+/// is provided: it is then the name of the language, which can also be a file extension or
+/// a file name (see `mergiraf languages`). If no supported language is found, the revisions
+/// are merged line-based.
+///
+/// The structured merges are abandoned after `timeout` (see [`DEFAULT_TIMEOUT`]).
+///
+/// The conflicts which remain can be inspected with [`MergeResult::parse`], passing it
+/// the same settings.
+pub fn merge_files(
+    contents_base: &str,
+    contents_left: &str,
+    contents_right: &str,
+    file_name: &Path,
+    language: Option<&str>,
+    settings: &DisplaySettings<'static>,
+    timeout: Duration,
+) -> MergeResult {
+    line_merge_and_structured_resolution(
+        Arc::new(Cow::Owned(contents_base.to_owned())),
+        Arc::new(Cow::Owned(contents_left.to_owned())),
+        Arc::new(Cow::Owned(contents_right.to_owned())),
+        file_name,
+        settings.clone(),
+        true,
+        None,
+        CliOpts {
+            language,
+            ..Default::default()
+        },
+        None,
+        None,
+        timeout,
+    )
 }
 
 /// Merge the files textually and then attempt to merge any conflicts
@@ -41,8 +131,8 @@ pub fn line_merge_and_structured_resolution(
     contents_base: Arc<Cow<'static, str>>,
     contents_left: Arc<Cow<'static, str>>,
     contents_right: Arc<Cow<'static, str>>,
-    fname_base: &'static Path,
-    settings: DisplaySettings<'static>,
+    fname_base: &Path,
+    mut settings: DisplaySettings<'static>,
     full_merge: bool,
     attempts_cache: Option<&AttemptsCache>,
     cli_opts: CliOpts,
@@ -52,32 +142,157 @@ pub fn line_merge_and_structured_resolution(
 ) -> MergeResult {
     // Read the relevant Git attributes, and set the corresponding parameters, if they aren't
     // already specified via CLI
-    let (language_git, allow_parse_errors_git) = if let Some(repo_dir) = repo_dir
+    let (language_git, allow_parse_errors_git, commute_order_sensitive_git) = if let Some(repo_dir) =
+        repo_dir
         && let Some(git_attrs) = GitAttrsForMerge::new(repo_dir, fname_base)
     {
-        (git_attrs.language, git_attrs.allow_parse_errors)
+        (
+            git_attrs.language,
+            git_attrs.allow_parse_errors,
+            git_attrs.commute_order_sensitive,
+        )
     } else {
-        (None, None)
+        (None, None, None)
     };
     let allow_parse_errors = cli_opts.allow_parse_errors.or(allow_parse_errors_git);
+    let commute_order_sensitive =
+        (cli_opts.commute_order_sensitive).or(commute_order_sensitive_git);
+    let options = StructuredMergeOptions {
+        commutative_only: cli_opts.commutative_only,
+        single_threaded: cli_opts.single_threaded,
+        delete_modify: cli_opts.delete_modify,
+        commutative_only_resolution: cli_opts.commutative_only_resolution,
+        detect_renames: cli_opts.detect_renames,
+    };
 
-    let Ok(lang_profile) =
-        LangProfile::find(fname_base, cli_opts.language, language_git.as_deref())
-    else {
-        return line_based_merge(&contents_base, &contents_left, &contents_right, &settings);
+    if cli_opts.no_parse_markers {
+        // The revisions are trusted not to contain any conflict, so the lines looking like
+        // conflict markers in them are kept as they are, and our own markers are made longer
+        settings.avoid_conflict_markers_in(&[&contents_base, &contents_left, &contents_right]);
+    } else {
+        // Revisions which already contain conflict markers (typically committed by mistake after
+        // a previous merge) can neither be parsed reliably nor merged in a structured way.
+        // Callers can recognize the resulting merge by its method to use another fallback.
+        for (side, contents) in [
+            ("base", &contents_base),
+            ("left", &contents_left),
+            ("right", &contents_right),
+        ] {
+            if settings.conflict_regexes().is_match(contents) {
+                warn!("{side} side contains conflict markers, falling back to line-based merging");
+                return line_based_merge_with_existing_conflicts(
+                    &contents_base,
+                    &contents_left,
+                    &contents_right,
+                    &settings,
+                );
+            }
+        }
+    }
+
+    if cli_opts.language.is_none()
+        && language_git.is_none()
+        && patch::is_patch_file(fname_base)
+        && let Some(merge) =
+            patch::merge_patches(&contents_base, &contents_left, &contents_right, &settings)
+    {
+        return merge;
+    }
+
+    let detection = LangProfile::find_with_detection(
+        fname_base,
+        &contents_base,
+        cli_opts.language,
+        language_git.as_deref(),
+    )
+    .or_else(|err| {
+        // a language which was specified explicitly is never overridden by a guess
+        if cli_opts.detect_language_by_content
+            && cli_opts.language.is_none()
+            && language_git.is_none()
+        {
+            LangProfile::detect_from_content(&contents_base)
+                .map(|lang_profile| (lang_profile, LanguageDetection::Content))
+                .ok_or(err)
+        } else {
+            Err(err)
+        }
+    });
+    let lang_profile = match detection {
+        Ok((lang_profile, detection)) => {
+            if cli_opts.print_language {
+                info!("Language: {lang_profile} (detected from {detection})");
+            }
+            lang_profile
+        }
+        Err(err) => {
+            if cli_opts.print_language {
+                info!("{err}, merging line by line");
+            }
+            return line_based_merge(&contents_base, &contents_left, &contents_right, &settings);
+        }
     };
     let mut lang_profile = Cow::Borrowed(lang_profile);
     if let Some(allow_parse_errors) = allow_parse_errors {
         lang_profile.to_mut().allow_parse_errors = allow_parse_errors;
     }
+    if let Some(commute_order_sensitive) = commute_order_sensitive {
+        lang_profile.to_mut().commute_order_sensitive = commute_order_sensitive;
+    }
+    if cli_opts.conflict_comment {
+        settings.conflict_comment = lang_profile.comment_delimiters;
+    }
+    let (contents_base, contents_left, contents_right) = if cli_opts.normalize_trailing_whitespace {
+        let normalize = |contents: &str| {
+            Arc::new(Cow::Owned(normalize_trailing_whitespace(
+                contents,
+                &lang_profile,
+            )))
+        };
+        (
+            normalize(&contents_base),
+            normalize(&contents_left),
+            normalize(&contents_right),
+        )
+    } else {
+        (contents_base, contents_left, contents_right)
+    };
+
+    if let Some(line_range) = &cli_opts.line_range
+        && let Some(merge) = merge_in_line_range(
+            &contents_base,
+            &contents_left,
+            &contents_right,
+            line_range,
+            &settings,
+            &options,
+            &lang_profile,
+            debug_dir,
+        )
+    {
+        return if cli_opts.selftest_roundtrip {
+            check_roundtrip(
+                merge,
+                &contents_base,
+                &contents_left,
+                &contents_right,
+                &settings,
+                &lang_profile,
+            )
+        } else {
+            merge
+        };
+    }
     let lang_profile = Arc::new(lang_profile);
+    let roundtrip_settings = cli_opts.selftest_roundtrip.then(|| settings.clone());
 
     let merges = cascading_merge(
         Arc::clone(&contents_base),
         Arc::clone(&contents_left),
         Arc::clone(&contents_right),
-        lang_profile,
+        Arc::clone(&lang_profile),
         settings,
+        options,
         full_merge,
         debug_dir,
         timeout,
@@ -85,7 +300,20 @@ pub fn line_merge_and_structured_resolution(
 
     let merge_result = match select_best_merge(merges) {
         LineBasedAndBestAre::TheSame(merge) => merge,
-        LineBasedAndBestAre::NotTheSame { line_based, best } => {
+        LineBasedAndBestAre::NotTheSame { line_based, best }
+            if cli_opts.never_worse_than_line_based && is_worse_than(&best, &line_based) =>
+        {
+            info!(
+                "The {} merge is worse than the line-based merge, keeping the latter",
+                best.method
+            );
+            line_based
+        }
+        LineBasedAndBestAre::NotTheSame {
+            line_based,
+            mut best,
+        } => {
+            best.line_based_conflict_mass = line_based.conflict_mass;
             if best.conflict_count == 0 {
                 // for successful merges that aren't line-based,
                 // give the opportunity to the user to review Mergiraf's work
@@ -111,7 +339,103 @@ pub fn line_merge_and_structured_resolution(
         }
     };
     debug!("Selected best merge: {}", merge_result.method);
-    merge_result
+    if let Some(settings) = roundtrip_settings {
+        check_roundtrip(
+            merge_result,
+            &contents_base,
+            &contents_left,
+            &contents_right,
+            &settings,
+            &lang_profile,
+        )
+    } else {
+        merge_result
+    }
+}
+
+/// Removes the whitespace at the end of lines, except inside tokens which span multiple lines
+/// (such as multi-line string literals), where it is part of their value.
+/// If the contents can't be parsed, they are returned unchanged.
+fn normalize_trailing_whitespace(contents: &str, lang_profile: &LangProfile) -> String {
+    let arena = Arena::new();
+    let ref_arena = Arena::new();
+    let tree = match AstNode::parse(contents, lang_profile, &arena, &ref_arena) {
+        Ok(tree) => tree,
+        Err(err) => {
+            debug!("not normalizing trailing whitespace, as the file does not parse: {err}");
+            return contents.to_owned();
+        }
+    };
+    let multiline_tokens: Vec<&Range<usize>> = tree
+        .dfs()
+        // multi-line tokens are split into one `@virtual_line@` child per line
+        .filter(|node| {
+            node.source.contains('\n')
+                && (node.children.iter()).all(|child| child.kind == "@virtual_line@")
+        })
+        .map(|node| &node.byte_range)
+        .collect();
+
+    let mut result = String::with_capacity(contents.len());
+    let mut offset = 0;
+    for line in contents.split_inclusive('\n') {
+        let (text, line_ending) = line.split_at(line.trim_end_matches(['\r', '\n']).len());
+        let line_end = offset + text.len();
+        if multiline_tokens
+            .iter()
+            .any(|token| token.start < line_end && line_end < token.end)
+        {
+            result.push_str(text);
+        } else {
+            result.push_str(text.trim_end_matches([' ', '\t']));
+        }
+        result.push_str(line_ending);
+        offset += line.len();
+    }
+    result
+}
+
+/// Checks that the revisions reconstructed from a merge output (the output itself, if it has
+/// no conflicts) parse again. If they don't, the structured merge produced invalid syntax,
+/// so we fall back on a line-based merge.
+fn check_roundtrip(
+    merge_result: MergeResult,
+    contents_base: &str,
+    contents_left: &str,
+    contents_right: &str,
+    settings: &DisplaySettings,
+    lang_profile: &LangProfile,
+) -> MergeResult {
+    if merge_result.method == LINE_BASED_METHOD {
+        return merge_result;
+    }
+    let start = Instant::now();
+    let error = match ParsedMerge::parse(&merge_result.contents, settings) {
+        Ok(parsed) => [Revision::Left, Revision::Right]
+            .into_iter()
+            .find_map(|revision| {
+                let arena = Arena::new();
+                let ref_arena = Arena::new();
+                let contents = parsed.reconstruct_revision(revision);
+                AstNode::parse(&contents, lang_profile, &arena, &ref_arena)
+                    .err()
+                    .map(|err| {
+                        format!("the {revision} revision of the merge does not parse: {err}")
+                    })
+            }),
+        Err(err) => Some(format!("its conflicts could not be parsed: {err}")),
+    };
+    debug!(
+        "checking that the merge parses again took {:?}",
+        start.elapsed()
+    );
+    match error {
+        None => merge_result,
+        Some(error) => {
+            warn!("Mergiraf produced invalid output ({error}), falling back on line-based merging");
+            line_based_merge(contents_base, contents_left, contents_right, settings)
+        }
+    }
 }
 
 /// Attempts various merging methods in turn, and stops early when
@@ -123,6 +447,7 @@ pub fn cascading_merge(
     contents_right: Arc<Cow<'static, str>>,
     lang_profile: Arc<Cow<'static, LangProfile>>,
     settings: DisplaySettings<'static>,
+    options: StructuredMergeOptions,
     full_merge: bool,
     debug_dir: Option<&'static Path>,
     timeout: Duration,
@@ -142,14 +467,20 @@ pub fn cascading_merge(
     }
 
     let (tx, rx) = oneshot::channel();
+    let single_threaded = options.single_threaded;
 
-    thread::spawn(move || {
+    let structured_attempts = move || {
         let mut merges = Vec::new();
 
         // second attempt: to solve the conflicts from the line-based merge
         if !line_based_merge.has_additional_issues {
-            let solved_merge =
-                resolve_merge(&parsed_conflicts, &settings, &lang_profile, debug_dir);
+            let solved_merge = resolve_merge(
+                &parsed_conflicts,
+                &settings,
+                &options,
+                &lang_profile,
+                debug_dir,
+            );
 
             match solved_merge {
                 Ok(recovered_merge) => {
@@ -174,6 +505,7 @@ pub fn cascading_merge(
                 &contents_right,
                 None,
                 &settings,
+                &options,
                 &lang_profile,
                 debug_dir,
             );
@@ -185,9 +517,17 @@ pub fn cascading_merge(
             };
         }
         let _ = tx.send(merges);
-    });
+    };
+
+    // when running on a single thread, the attempts have completed by the time we
+    // wait for their results, so the timeout can't be enforced
+    if single_threaded {
+        structured_attempts();
+    } else {
+        thread::spawn(structured_attempts);
+    }
 
-    let mut merges = if timeout.is_zero() {
+    let mut merges = if timeout.is_zero() || single_threaded {
         rx.recv().unwrap()
     } else {
         match rx.recv_timeout(timeout) {
@@ -204,6 +544,247 @@ pub fn cascading_merge(
     merges
 }
 
+/// Merges the revisions in a structured way only around the supplied lines of the line-based
+/// merge (1-based, inclusive), keeping the line-based merge everywhere else.
+///
+/// The lines are extended to the smallest enclosing syntax node of the (reconstructed) left
+/// revision, whose bounds don't fall inside conflicts. The rest of the file is treated as
+/// context, identical in all three revisions, so that it doesn't need to be merged.
+///
+/// Returns `None` if the merge could not be scoped to a part of the file.
+#[allow(clippy::too_many_arguments)]
+fn merge_in_line_range(
+    contents_base: &str,
+    contents_left: &str,
+    contents_right: &str,
+    line_range: &RangeInclusive<usize>,
+    settings: &DisplaySettings,
+    options: &StructuredMergeOptions,
+    lang_profile: &LangProfile,
+    debug_dir: Option<&Path>,
+) -> Option<MergeResult> {
+    let start = Instant::now();
+    // parse the rendered merge again, so that the offsets of its chunks refer to the lines
+    // the user sees (without any conflict comments)
+    let mut plain_settings = settings.clone();
+    plain_settings.conflict_comment = None;
+    let merged_text = line_based_merge_parsed(
+        contents_base,
+        contents_left,
+        contents_right,
+        &plain_settings,
+    )
+    .render(&plain_settings);
+    let parsed_merge = ParsedMerge::parse(&merged_text, &plain_settings).ok()?;
+
+    let line_offset = |line: usize| match line {
+        0 => None,
+        1 => Some(0),
+        _ => (merged_text.match_indices('\n').nth(line - 2)).map(|(idx, _)| idx + 1),
+    };
+    let Some(merged_start) = line_offset(*line_range.start()) else {
+        warn!(
+            "Line {} is out of the merged file, merging the entire file",
+            line_range.start()
+        );
+        return None;
+    };
+    let merged_end = line_offset(line_range.end().saturating_add(1)).unwrap_or(merged_text.len());
+
+    let left_rev = parsed_merge.reconstruct_revision(Revision::Left);
+    let left_start = parsed_merge.merged_offset_to_rev_offset(merged_start, Revision::Left, true);
+    let left_end = parsed_merge.merged_offset_to_rev_offset(merged_end, Revision::Left, false);
+
+    let arena = Arena::new();
+    let ref_arena = Arena::new();
+    let tree = AstNode::parse(&left_rev, lang_profile, &arena, &ref_arena)
+        .inspect_err(|err| debug!("could not parse the left revision: {err}"))
+        .ok()?;
+    // the separators of commutative parents which end the selected lines shouldn't
+    // make us select their parent entirely
+    let (left_start, left_end) = tree
+        .dfs()
+        .filter(|node| {
+            node.is_leaf()
+                && left_start <= node.byte_range.start
+                && node.byte_range.end <= left_end
+                && trimmed_separator(node) != Some(node.source)
+        })
+        .fold(None, |range, node| match range {
+            None => Some((node.byte_range.start, node.byte_range.end)),
+            Some((start, _)) => Some((start, node.byte_range.end)),
+        })
+        .unwrap_or((left_start, left_end));
+    let enclosing_node = tree
+        .dfs()
+        .filter(|node| node.byte_range.start <= left_start && left_end <= node.byte_range.end)
+        .last()?;
+
+    let (node, range, merged_range) = enclosing_node
+        .ancestors()
+        .take_while(|node| !node.is_root())
+        .find_map(|node| {
+            let range = extend_to_lines(&left_rev, node);
+            let start =
+                parsed_merge.rev_range_to_merged_range(range.start..range.start, Revision::Left)?;
+            let end =
+                parsed_merge.rev_range_to_merged_range(range.end..range.end, Revision::Left)?;
+            Some((node, range, start.start..end.start))
+        })?;
+
+    debug!(
+        "merging the {} node spanning bytes {range:?} of the left revision",
+        node.kind
+    );
+    let [before, inside, after] = parsed_merge.split_at_merged_range(merged_range);
+    let (context_before, context_after) = minimal_context(&left_rev, node, range);
+    let with_context = |revision| {
+        format!(
+            "{context_before}{}{context_after}",
+            inside.reconstruct_revision(revision)
+        )
+    };
+
+    let merge = structured_merge(
+        &with_context(Revision::Base),
+        &with_context(Revision::Left),
+        &with_context(Revision::Right),
+        None,
+        settings,
+        options,
+        lang_profile,
+        debug_dir,
+    )
+    .inspect_err(|err| debug!("structured merge within the line range failed: {err}"))
+    .ok()?;
+    let Some(merged_inside) = (merge.contents)
+        .strip_prefix(&context_before)
+        .and_then(|contents| contents.strip_suffix(&context_after))
+    else {
+        debug!("structured merge within the line range did not preserve its context");
+        return None;
+    };
+    debug!("merge within the line range took {:?}", start.elapsed());
+
+    Some(MergeResult {
+        contents: before.render(settings) + merged_inside + &after.render(settings),
+        conflict_count: before.conflict_count() + merge.conflict_count + after.conflict_count(),
+        conflict_mass: before.conflict_mass() + merge.conflict_mass + after.conflict_mass(),
+        method: merge.method,
+        has_additional_issues: merge.has_additional_issues,
+        line_based_conflict_mass: parsed_merge.conflict_mass(),
+    })
+}
+
+/// Extends the range of the node to entire lines when only whitespace surrounds it (or a
+/// separator after it), as the conflicts of line-based merges are made of entire lines too
+fn extend_to_lines<'a>(source: &str, node: &'a AstNode<'a>) -> Range<usize> {
+    let Range { start, end } = node.byte_range;
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[end..]
+        .find('\n')
+        .map_or(source.len(), |i| end + i + 1);
+    let rest_of_line = source[end..line_end].trim();
+    let start = if source[line_start..start].trim().is_empty() {
+        line_start
+    } else {
+        start
+    };
+    let end = if rest_of_line.is_empty() || trimmed_separator(node) == Some(rest_of_line) {
+        line_end
+    } else {
+        end
+    };
+    start..end
+}
+
+/// The separator between the node and its siblings, if its parent is commutative
+fn trimmed_separator<'a>(node: &'a AstNode<'a>) -> Option<&'static str> {
+    let separator = node
+        .parent()?
+        .commutative_parent_definition()?
+        .trimmed_separator();
+    (!separator.is_empty()).then_some(separator)
+}
+
+/// Extracts the text before and after `range` in `source`, whose tree contains `node` enclosing
+/// the range. To keep this context short, the elements of the enclosing commutative parents
+/// are left out, along with their separators: this keeps the context syntactically valid.
+fn minimal_context<'a>(
+    source: &str,
+    node: &'a AstNode<'a>,
+    range: Range<usize>,
+) -> (String, String) {
+    let mut omitted = Vec::new();
+    for (child, parent) in node.ancestors().tuple_windows() {
+        let Some(commutative_parent) = parent.commutative_parent_definition() else {
+            continue;
+        };
+        let separator = commutative_parent.trimmed_separator();
+        let is_separator = |sibling: &AstNode| {
+            !separator.is_empty() && sibling.is_leaf() && sibling.source == separator
+        };
+        let siblings = &parent.children;
+        let position = (siblings.iter())
+            .position(|sibling| ptr::eq(*sibling, child))
+            .expect("a node must be among the children of its parent");
+
+        // the elements before the child are omitted along with the separator following them
+        let mut previous_end = parent.byte_range.start;
+        let mut siblings_before = siblings[..position].iter().peekable();
+        while let Some(sibling) = siblings_before.next() {
+            if !sibling.is_leaf() {
+                let end = siblings_before
+                    .next_if(|next| is_separator(next))
+                    .unwrap_or(sibling)
+                    .byte_range
+                    .end;
+                omitted.push(previous_end..end);
+                previous_end = end;
+            } else {
+                previous_end = sibling.byte_range.end;
+            }
+        }
+        // and those after it along with the separator preceding them, except if that separator
+        // is part of the range, in which case the next element is kept to avoid a dangling separator
+        let mut keep_next = true;
+        for (index, sibling) in siblings.iter().enumerate().skip(position + 1) {
+            if !sibling.is_leaf() {
+                let separator_before = index - 1 > position && is_separator(siblings[index - 1]);
+                if keep_next && separator_before && siblings[index - 1].byte_range.end <= range.end
+                {
+                    keep_next = false;
+                    continue;
+                }
+                keep_next = false;
+                let previous = siblings[if separator_before {
+                    index - 2
+                } else {
+                    index - 1
+                }];
+                omitted.push(previous.byte_range.end..sibling.byte_range.end);
+            }
+        }
+    }
+    omitted.sort_by_key(|omitted| omitted.start);
+
+    let kept = |bounds: Range<usize>| {
+        let mut text = String::new();
+        let mut position = bounds.start;
+        for omitted in &omitted {
+            let start = omitted.start.clamp(bounds.start, bounds.end);
+            let end = omitted.end.clamp(bounds.start, bounds.end);
+            if start > position {
+                text.push_str(&source[position..start]);
+            }
+            position = position.max(end);
+        }
+        text.push_str(&source[position..bounds.end]);
+        text
+    };
+    (kept(0..range.start), kept(range.end..source.len()))
+}
+
 enum LineBasedAndBestAre {
     TheSame(MergeResult),
     NotTheSame {
@@ -253,3 +834,433 @@ fn select_best_merge(mut merges: Vec<MergeResult>) -> LineBasedAndBestAre {
         }
     }
 }
+
+/// Whether a merge is worse than the line-based one, by having either more conflicts
+/// or a larger conflict mass. A line-based merge with additional issues is never preferred.
+fn is_worse_than(merge: &MergeResult, line_based: &MergeResult) -> bool {
+    !line_based.has_additional_issues
+        && (merge.conflict_count > line_based.conflict_count
+            || merge.conflict_mass > line_based.conflict_mass)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{line_based::EXISTING_CONFLICTS_METHOD, structured::FULLY_STRUCTURED_METHOD};
+
+    /// Merges the base, left and right revisions of a file with the given settings and options,
+    /// on the current thread
+    fn merge_with(
+        revisions: [&str; 3],
+        file_name: &'static str,
+        settings: DisplaySettings<'static>,
+        cli_opts: CliOpts,
+    ) -> MergeResult {
+        merge_with_timeout(revisions, file_name, settings, cli_opts, Duration::ZERO)
+    }
+
+    /// Merges the base, left and right revisions of a file with the given settings and options,
+    /// running the merges in parallel unless the timeout is zero
+    fn merge_with_timeout(
+        [base, left, right]: [&str; 3],
+        file_name: &'static str,
+        settings: DisplaySettings<'static>,
+        cli_opts: CliOpts,
+        timeout: Duration,
+    ) -> MergeResult {
+        line_merge_and_structured_resolution(
+            Arc::new(Cow::Owned(base.to_owned())),
+            Arc::new(Cow::Owned(left.to_owned())),
+            Arc::new(Cow::Owned(right.to_owned())),
+            Path::new(file_name),
+            settings,
+            true,
+            None,
+            cli_opts,
+            None,
+            None,
+            timeout,
+        )
+    }
+
+    #[test]
+    fn existing_conflict_markers_in_input() {
+        let contents_base = "[1, 2, 3]\n";
+        let contents_left = "\
+<<<<<<< HEAD
+[1, 2, 3, 4]
+=======
+[1, 2, 3, 5]
+>>>>>>> origin/main
+";
+        let contents_right = "[0, 1, 2, 3]\n";
+
+        let merge = merge_with(
+            [contents_base, contents_left, contents_right],
+            "file.json",
+            DisplaySettings::default(),
+            CliOpts::default(),
+        );
+
+        assert_eq!(merge.method, EXISTING_CONFLICTS_METHOD);
+        assert!(merge.conflict_count > 0);
+        assert!(merge.contents.contains("[1, 2, 3, 4]\n"));
+        assert!(merge.contents.contains("[0, 1, 2, 3]\n"));
+    }
+
+    #[test]
+    fn never_worse_than_line_based() {
+        let contents_base = "class A {\n    int a = 1;\n    int b = 1;\n}\n";
+        let contents_left = "class A {\n    int a = 2;\n    int b = 2;\n}\n";
+        let contents_right = "class A {\n    int a = 3;\n    int b = 3;\n}\n";
+
+        let merge = |never_worse_than_line_based| {
+            merge_with(
+                [contents_base, contents_left, contents_right],
+                "A.java",
+                DisplaySettings::default(),
+                CliOpts {
+                    never_worse_than_line_based,
+                    ..Default::default()
+                },
+            )
+        };
+
+        // the structured merge splits the line-based conflict in two smaller ones
+        let structured = merge(false);
+        assert_ne!(structured.method, LINE_BASED_METHOD);
+        assert_eq!(structured.conflict_count, 2);
+
+        let line_based = merge(true);
+        assert_eq!(line_based.method, LINE_BASED_METHOD);
+        assert_eq!(line_based.conflict_count, 1);
+    }
+
+    #[test]
+    fn isolated_syntax_error_away_from_changes() {
+        let contents_base = "\
+import java.util.List;
+
+class A {
+    void broken() {
+        int x = ;
+    }
+}
+";
+        let contents_left = contents_base.replace(
+            "import java.util.List;\n",
+            "import java.util.List;\nimport java.util.Set;\n",
+        );
+        let contents_right = contents_base.replace(
+            "import java.util.List;\n",
+            "import java.util.List;\nimport java.io.File;\n",
+        );
+        let merge = |allow_parse_errors| {
+            merge_with(
+                [contents_base, &contents_left, &contents_right],
+                "A.java",
+                DisplaySettings::default(),
+                CliOpts {
+                    allow_parse_errors: Some(allow_parse_errors),
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert_eq!(merge(false).method, LINE_BASED_METHOD);
+        assert_eq!(merge(false).conflict_count, 1);
+
+        // the erroneous statement is kept as an atomic leaf, and the rest of the file is
+        // merged structurally
+        let merge = merge(true);
+        assert_eq!(merge.conflict_count, 0);
+        assert!(
+            merge
+                .contents
+                .contains("import java.util.Set;\nimport java.io.File;\n")
+        );
+        assert!(merge.contents.contains("int x = ;"));
+    }
+
+    #[test]
+    fn commutative_only_resolution() {
+        let contents_base = "\
+import java.util.List;
+
+class A {
+    void f() {
+        a();
+        b();
+    }
+}
+";
+        let contents_left = contents_base
+            .replace("List;\n", "List;\nimport java.util.Set;\n")
+            .replace("a();", "a(1);");
+        let contents_right = contents_base
+            .replace("List;\n", "List;\nimport java.io.File;\n")
+            .replace("b();", "b(2);");
+        let merge = |commutative_only_resolution| {
+            merge_with(
+                [contents_base, &contents_left, &contents_right],
+                "A.java",
+                DisplaySettings::default(),
+                CliOpts {
+                    commutative_only_resolution,
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert_eq!(merge(false).conflict_count, 0);
+
+        // the imports are merged commutatively, but the changes to adjacent statements
+        // are left in conflict, like in a line-based merge
+        let merge = merge(true);
+        assert_eq!(merge.conflict_count, 1);
+        assert!(
+            merge.contents.starts_with(
+                "import java.util.List;\nimport java.util.Set;\nimport java.io.File;\n"
+            )
+        );
+        assert!(
+            merge
+                .contents
+                .contains("        a(1);\n        b();\n||||||| ")
+        );
+    }
+
+    #[test]
+    fn delete_modify_strategies() {
+        let contents_base = "\
+class A {
+    void f() {
+        a();
+    }
+
+    void g() {
+        b();
+    }
+}
+";
+        let contents_left = "\
+class A {
+    void g() {
+        b();
+    }
+}
+";
+        let contents_right = contents_base.replace("a();", "a(1);");
+        let merge = |delete_modify| {
+            merge_with(
+                [contents_base, contents_left, &contents_right],
+                "A.java",
+                DisplaySettings::default(),
+                CliOpts {
+                    delete_modify,
+                    ..Default::default()
+                },
+            )
+        };
+
+        assert_eq!(merge(DeleteModifyStrategy::Conflict).conflict_count, 1);
+
+        let merge = merge(DeleteModifyStrategy::KeepModified);
+        assert_eq!(merge.conflict_count, 0);
+        assert_eq!(merge.contents, contents_right);
+    }
+
+    #[test]
+    fn single_threaded_merge_is_identical() {
+        let contents_base = "\
+class A {
+    void f() {
+        a();
+    }
+
+    void g() {
+        b();
+    }
+}
+";
+        let contents_left = contents_base.replace("a();", "a(1);");
+        let contents_right = contents_base
+            .replace("a();", "a(2);")
+            .replace("b();", "b(2);");
+        let merge = |single_threaded| {
+            // with a timeout, the merges are otherwise run in parallel
+            merge_with_timeout(
+                [contents_base, &contents_left, &contents_right],
+                "A.java",
+                DisplaySettings::default(),
+                CliOpts {
+                    single_threaded,
+                    ..Default::default()
+                },
+                Duration::from_secs(10),
+            )
+        };
+
+        let parallel = merge(false);
+        let sequential = merge(true);
+        assert_eq!(parallel.conflict_count, 1);
+        assert_eq!(sequential.contents, parallel.contents);
+        assert_eq!(sequential.conflict_count, parallel.conflict_count);
+        assert_eq!(sequential.method, parallel.method);
+    }
+
+    #[test]
+    fn roundtrip_check_falls_back_on_invalid_output() {
+        let contents_base = "[1, 2]\n";
+        let contents_left = "[1, 2, 3]\n";
+        let contents_right = "[0, 1, 2]\n";
+        let settings = DisplaySettings::default();
+        let lang_profile = LangProfile::find_by_name("json").unwrap();
+        let structured = |contents: &str| MergeResult {
+            contents: contents.to_owned(),
+            conflict_count: 0,
+            conflict_mass: 0,
+            method: FULLY_STRUCTURED_METHOD,
+            has_additional_issues: false,
+            line_based_conflict_mass: 0,
+        };
+        let check = |merge_result| {
+            check_roundtrip(
+                merge_result,
+                contents_base,
+                contents_left,
+                contents_right,
+                &settings,
+                lang_profile,
+            )
+        };
+
+        // a merge which parses again is kept
+        let valid = structured("[0, 1, 2, 3]\n");
+        assert_eq!(check(valid.clone()), valid);
+
+        // but if the structured merge produced broken syntax, we fall back on a line-based merge
+        let invalid = check(structured("[0, 1, 2, 3\n"));
+        assert_eq!(
+            invalid,
+            line_based_merge(contents_base, contents_left, contents_right, &settings)
+        );
+    }
+
+    #[test]
+    fn normalize_trailing_whitespace_before_merging() {
+        let contents_base = "# a note  \nx = 1\n";
+        let contents_left = "# a note\nx = 1\n";
+        let contents_right = "# a new note  \nx = 1\n";
+
+        let merge = |normalize_trailing_whitespace| {
+            merge_with(
+                [contents_base, contents_left, contents_right],
+                "file.py",
+                DisplaySettings::default(),
+                CliOpts {
+                    normalize_trailing_whitespace,
+                    ..Default::default()
+                },
+            )
+        };
+
+        // the comment was changed on both sides
+        assert_eq!(merge(false).conflict_count, 1);
+
+        let normalized = merge(true);
+        assert_eq!(normalized.conflict_count, 0);
+        assert_eq!(normalized.contents, "# a new note\nx = 1\n");
+    }
+
+    #[test]
+    fn normalize_trailing_whitespace_in_strings() {
+        let lang_profile = LangProfile::find_by_name("python").unwrap();
+        let contents = "x = 1 \r\ny = \"\"\"a  \nb \"\"\"  \n# comment \n";
+
+        assert_eq!(
+            normalize_trailing_whitespace(contents, lang_profile),
+            "x = 1\r\ny = \"\"\"a  \nb \"\"\"\n# comment\n"
+        );
+        // files which do not parse are left as they are
+        assert_eq!(
+            normalize_trailing_whitespace("x = (  \n", lang_profile),
+            "x = (  \n"
+        );
+    }
+
+    #[test]
+    fn large_json_object_merged_on_both_sides() {
+        // keys added on both sides of a large object (with some removed on one side)
+        // are merged commutatively, without the cost of the merge blowing up
+        let object = |keys: &[String]| format!("{{\n{}\n}}\n", keys.join(",\n"));
+        let base_keys = (0..1000)
+            .map(|i| format!("  \"key{i}\": {i}"))
+            .collect_vec();
+        let left_keys = (base_keys.iter().cloned())
+            .chain((0..50).map(|i| format!("  \"left{i}\": {i}")))
+            .collect_vec();
+        let right_keys = (base_keys.iter().enumerate())
+            .filter(|(i, _)| i % 7 != 0)
+            .map(|(_, key)| key.clone())
+            .chain((0..50).map(|i| format!("  \"right{i}\": {i}")))
+            .collect_vec();
+
+        let merge = merge_with(
+            [
+                &object(&base_keys),
+                &object(&left_keys),
+                &object(&right_keys),
+            ],
+            "file.json",
+            DisplaySettings::default(),
+            CliOpts::default(),
+        );
+
+        assert_eq!(merge.conflict_count, 0);
+        assert!(merge.contents.contains("\"key1\": 1,\n"));
+        assert!(!merge.contents.contains("\"key7\""));
+        assert!(merge.contents.contains("\"left49\": 49"));
+        assert!(merge.contents.contains("\"right49\": 49"));
+    }
+
+    #[test]
+    fn patch_series_changing_the_same_file_twice() {
+        let commit = |subject: &str, old: &str, new: &str| {
+            format!(
+                "\
+From 1111111111111111111111111111111111111111 Mon Sep 17 00:00:00 2001
+Subject: [PATCH] {subject}
+
+---
+diff --git a/f.txt b/f.txt
+--- a/f.txt
++++ b/f.txt
+@@ -1 +1 @@
+-{old}
++{new}
+"
+            )
+        };
+        let base = commit("One", "a", "b") + &commit("Two", "b", "c");
+        let left = commit("First", "a", "b") + &commit("Two", "b", "c");
+        let right = commit("One", "a", "b") + &commit("Second", "b", "c");
+
+        // both sections can't be merged hunk by hunk, as they patch the same file
+        let merge = merge_with(
+            [&base, &left, &right],
+            "series.patch",
+            DisplaySettings::default(),
+            CliOpts::default(),
+        );
+
+        assert_ne!(merge.method, patch::PATCH_METHOD);
+        assert_eq!(merge.conflict_count, 0);
+        assert_eq!(
+            merge.contents,
+            commit("First", "a", "b") + &commit("Second", "b", "c")
+        );
+    }
+}
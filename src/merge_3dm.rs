@@ -9,8 +9,10 @@ use crate::{
     line_based::line_based_merge_parsed,
     matching::{ApproxExactMatching, Matching},
     merged_tree::MergedTree,
-    pcs::Revision,
+    node_trace::{find_traced, is_traced_pcs_node, trace_node, traced_node},
+    pcs::{PCS, Revision},
     settings::DisplaySettings,
+    structured::{PhaseTimings, StructuredMergeOptions},
     tree_builder::TreeBuilder,
     tree_matcher::{DetailedMatching, TreeMatcher},
     visualizer::write_matching_to_dotty_file,
@@ -29,6 +31,8 @@ use crate::{
 /// A good overview of this algorithm can be found in
 /// [Spork: Structured Merge for Java with Formatting Preservation](https://arxiv.org/abs/2202.05329)
 /// by Simon Larsén, Jean-Rémy Falleri, Benoit Baudry and Martin Monperrus
+///
+/// The time spent matching the trees and building the merged tree is recorded in `timings`.
 #[allow(clippy::too_many_arguments)]
 pub fn three_way_merge<'a>(
     base: &'a AstNode<'a>,
@@ -38,8 +42,11 @@ pub fn three_way_merge<'a>(
     primary_matcher: &TreeMatcher,
     auxiliary_matcher: &TreeMatcher,
     settings: &DisplaySettings<'a>,
+    options: &StructuredMergeOptions,
     debug_dir: Option<&Path>,
+    timings: &mut PhaseTimings,
 ) -> (MergedTree<'a>, ClassMapping<'a>) {
+    let start = Instant::now();
     // match all pairs of revisions
     let (base_left_matching, base_right_matching, left_right_matching) = generate_matchings(
         base,
@@ -48,6 +55,7 @@ pub fn three_way_merge<'a>(
         initial_matchings,
         primary_matcher,
         auxiliary_matcher,
+        options.single_threaded,
         debug_dir,
     );
 
@@ -57,13 +65,32 @@ pub fn three_way_merge<'a>(
         &base_right_matching,
         &left_right_matching,
     );
+    if traced_node().is_some() {
+        for (rev, tree) in [
+            (Revision::Base, base),
+            (Revision::Left, left),
+            (Revision::Right, right),
+        ] {
+            if let Some(traced) = find_traced(rev, tree) {
+                let rev_node = RevNode::new(rev, traced);
+                let leader = class_mapping.map_to_leader(rev_node);
+                trace_node!(
+                    "{rev_node} belongs to the class of {leader}, present in {}",
+                    class_mapping.revision_set(&leader)
+                );
+            }
+        }
+    }
+
+    timings.matching = start.elapsed();
 
+    let start = Instant::now();
     // convert all the trees to PCS triples
     let (changeset, base_changeset) =
         generate_pcs_triples(base, left, right, &class_mapping, debug_dir);
 
     // try to fix all inconsistencies in the merged changeset
-    let cleaned_changeset = fix_pcs_inconsistencies(&changeset, debug_dir);
+    let cleaned_changeset = fix_pcs_inconsistencies(&changeset, &class_mapping, debug_dir);
 
     // construct the merged tree!
     let merged_tree = build_tree(
@@ -74,10 +101,12 @@ pub fn three_way_merge<'a>(
         &base_changeset,
         &cleaned_changeset,
         settings,
+        options,
     );
 
     // post-process to highlight signature conflicts
     let postprocessed_tree = postprocess_tree(merged_tree, &class_mapping);
+    timings.build = start.elapsed();
 
     (postprocessed_tree, class_mapping)
 }
@@ -89,8 +118,12 @@ pub fn three_way_merge<'a>(
 /// are available when the revisions were obtained from a line-based
 /// merge.
 ///
+/// Unless `single_threaded` is set, the "base <-> left" and "base <-> right"
+/// matchings are computed in parallel.
+///
 /// When a `debug_dir` is provided, the matchings are dumped in this
 /// directory to ease their analysis.
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn generate_matchings<'a>(
     base: &'a AstNode<'a>,
     left: &'a AstNode<'a>,
@@ -98,6 +131,7 @@ pub(crate) fn generate_matchings<'a>(
     initial_matchings: Option<&(ApproxExactMatching<'a>, ApproxExactMatching<'a>)>,
     primary_matcher: &TreeMatcher,
     auxiliary_matcher: &TreeMatcher,
+    single_threaded: bool,
     debug_dir: Option<&Path>,
 ) -> (
     DetailedMatching<'a>,
@@ -105,32 +139,34 @@ pub(crate) fn generate_matchings<'a>(
     DetailedMatching<'a>,
 ) {
     let start = Instant::now();
-    let (base_left_matching, base_right_matching) = thread::scope(|scope| {
-        let base_left = scope.spawn(|| {
-            debug!("matching base to left");
-            primary_matcher.match_trees(
-                base,
-                left,
-                initial_matchings.as_ref().map(|(left, _)| left),
-            )
-        });
-        let base_right = scope.spawn(|| {
-            debug!("matching base to right");
-            primary_matcher.match_trees(
-                base,
-                right,
-                initial_matchings.as_ref().map(|(_, right)| right),
-            )
-        });
-        (
-            base_left
-                .join()
-                .expect("error in thread matching base and left revisions"),
-            base_right
-                .join()
-                .expect("error in thread matching base and right revisions"),
+    let match_base_left = || {
+        debug!("matching base to left");
+        primary_matcher.match_trees(base, left, initial_matchings.as_ref().map(|(left, _)| left))
+    };
+    let match_base_right = || {
+        debug!("matching base to right");
+        primary_matcher.match_trees(
+            base,
+            right,
+            initial_matchings.as_ref().map(|(_, right)| right),
         )
-    });
+    };
+    let (base_left_matching, base_right_matching) = if single_threaded {
+        (match_base_left(), match_base_right())
+    } else {
+        thread::scope(|scope| {
+            let base_left = scope.spawn(match_base_left);
+            let base_right = scope.spawn(match_base_right);
+            (
+                base_left
+                    .join()
+                    .expect("error in thread matching base and left revisions"),
+                base_right
+                    .join()
+                    .expect("error in thread matching base and right revisions"),
+            )
+        })
+    };
     debug!("matching left to right");
     let composed_matching = Matching::compose_base_left_and_base_right(
         &base_left_matching.full,
@@ -143,6 +179,30 @@ pub(crate) fn generate_matchings<'a>(
     );
     debug!("matching all three pairs took {:?}", start.elapsed());
 
+    if traced_node().is_some() {
+        trace_matching(
+            Revision::Base,
+            base,
+            Revision::Left,
+            left,
+            &base_left_matching,
+        );
+        trace_matching(
+            Revision::Base,
+            base,
+            Revision::Right,
+            right,
+            &base_right_matching,
+        );
+        trace_matching(
+            Revision::Left,
+            left,
+            Revision::Right,
+            right,
+            &left_right_matching,
+        );
+    }
+
     // save the matchings for debugging purposes
     if let Some(debug_dir) = debug_dir {
         thread::scope(|s| {
@@ -176,6 +236,49 @@ pub(crate) fn generate_matchings<'a>(
     (base_left_matching, base_right_matching, left_right_matching)
 }
 
+/// Logs how the traced node was matched (or not) between the two revisions
+fn trace_matching<'a>(
+    left_rev: Revision,
+    left: &'a AstNode<'a>,
+    right_rev: Revision,
+    right: &'a AstNode<'a>,
+    matching: &DetailedMatching<'a>,
+) {
+    let matching_kind = |from: &'a AstNode<'a>, to: &'a AstNode<'a>| {
+        if matching.container.are_matched(from, to) {
+            "container"
+        } else if matching.recovery.are_matched(from, to) {
+            "recovery"
+        } else {
+            "exact"
+        }
+    };
+    if let Some(traced) = find_traced(left_rev, left) {
+        let traced_rev_node = RevNode::new(left_rev, traced);
+        match matching.full.get_from_left(traced) {
+            Some(matched) => trace_node!(
+                "{traced_rev_node} matched to {} (id {}) by {} matching",
+                RevNode::new(right_rev, matched),
+                matched.id,
+                matching_kind(traced, matched)
+            ),
+            None => trace_node!("{traced_rev_node} not matched to any node in {right_rev}"),
+        }
+    }
+    if let Some(traced) = find_traced(right_rev, right) {
+        let traced_rev_node = RevNode::new(right_rev, traced);
+        match matching.full.get_from_right(traced) {
+            Some(matched) => trace_node!(
+                "{traced_rev_node} matched to {} (id {}) by {} matching",
+                RevNode::new(left_rev, matched),
+                matched.id,
+                matching_kind(matched, traced)
+            ),
+            None => trace_node!("{traced_rev_node} not matched to any node in {left_rev}"),
+        }
+    }
+}
+
 /// Compute equivalence classes between nodes from all three revisions,
 /// using matchings between each pair of revisions.
 ///
@@ -267,6 +370,13 @@ fn generate_pcs_triples<'a>(
     (changeset, base_changeset)
 }
 
+/// Whether the traced node is involved in this PCS triple
+fn is_traced_pcs<'a>(pcs: &PCS<'a>, class_mapping: &ClassMapping<'a>) -> bool {
+    [pcs.parent, pcs.predecessor, pcs.successor]
+        .iter()
+        .any(|node| is_traced_pcs_node(node, class_mapping))
+}
+
 /// Scan the set of PCS triples provided and remove the ones which come from
 /// the base revision and are overridden by other triples from the left or right
 /// revisions.
@@ -276,6 +386,7 @@ fn generate_pcs_triples<'a>(
 /// constructing the merged tree.
 fn fix_pcs_inconsistencies<'a>(
     changeset: &ChangeSet<'a>,
+    class_mapping: &ClassMapping<'a>,
     debug_dir: Option<&Path>,
 ) -> ChangeSet<'a> {
     let start: Instant = Instant::now();
@@ -293,6 +404,9 @@ fn fix_pcs_inconsistencies<'a>(
                 conflicting_triples.find(|triple| triple.revision != Revision::Base)
             {
                 trace!("eliminating {pcs} by {triple}");
+                if is_traced_pcs(pcs, class_mapping) {
+                    trace_node!("eliminating {pcs} from the base revision, overridden by {triple}");
+                }
                 conflict_found = true;
             }
         }
@@ -320,9 +434,16 @@ fn build_tree<'a>(
     base_changeset: &ChangeSet<'a>,
     cleaned_changeset: &ChangeSet<'a>,
     settings: &DisplaySettings<'a>,
+    options: &StructuredMergeOptions,
 ) -> MergedTree<'a> {
     let start: Instant = Instant::now();
-    let tree_builder = TreeBuilder::new(cleaned_changeset, base_changeset, class_mapping, settings);
+    let tree_builder = TreeBuilder::new(
+        cleaned_changeset,
+        base_changeset,
+        class_mapping,
+        settings,
+        options,
+    );
     let merged_tree = tree_builder.build_tree().unwrap_or_else(|_| {
         let line_based = line_based_merge_parsed(base.source, left.source, right.source, settings);
         MergedTree::LineBasedMerge {
@@ -351,6 +472,8 @@ fn postprocess_tree<'a>(
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
+
     use crate::{
         settings::DisplaySettings,
         test_utils::{ctx, json_matchers},
@@ -378,7 +501,9 @@ mod tests {
             &primary_matcher,
             &auxiliary_matcher,
             &settings,
+            &StructuredMergeOptions::default(),
             None,
+            &mut PhaseTimings::default(),
         );
 
         debug!("{merged_tree}");
@@ -406,7 +531,9 @@ mod tests {
             &primary_matcher,
             &auxiliary_matcher,
             &settings,
+            &StructuredMergeOptions::default(),
             None,
+            &mut PhaseTimings::default(),
         );
 
         let pretty_printed = merged_tree.pretty_print(&class_mapping, &settings);
@@ -444,7 +571,9 @@ mod tests {
             &primary_matcher,
             &auxiliary_matcher,
             &settings,
+            &StructuredMergeOptions::default(),
             None,
+            &mut PhaseTimings::default(),
         );
 
         let pretty_printed = result_tree.pretty_print(&class_mapping, &settings);
@@ -482,7 +611,9 @@ mod tests {
             &primary_matcher,
             &auxiliary_matcher,
             &settings,
+            &StructuredMergeOptions::default(),
             None,
+            &mut PhaseTimings::default(),
         );
 
         let pretty_printed = merged_tree.pretty_print(&class_mapping, &settings);
@@ -520,7 +651,9 @@ mod tests {
             &primary_matcher,
             &auxiliary_matcher,
             &settings,
+            &StructuredMergeOptions::default(),
             None,
+            &mut PhaseTimings::default(),
         );
 
         let pretty_printed = merged_tree.pretty_print(&class_mapping, &settings);
@@ -558,7 +691,9 @@ mod tests {
             &primary_matcher,
             &auxiliary_matcher,
             &settings,
+            &StructuredMergeOptions::default(),
             None,
+            &mut PhaseTimings::default(),
         );
 
         let pretty_printed = merged_tree.pretty_print(&class_mapping, &settings);
@@ -585,7 +720,9 @@ mod tests {
             &primary_matcher,
             &auxiliary_matcher,
             &settings,
+            &StructuredMergeOptions::default(),
             None,
+            &mut PhaseTimings::default(),
         );
 
         let pretty_printed = merged_tree.pretty_print(&class_mapping, &settings);
@@ -612,7 +749,9 @@ mod tests {
             &primary_matcher,
             &auxiliary_matcher,
             &settings,
+            &StructuredMergeOptions::default(),
             None,
+            &mut PhaseTimings::default(),
         );
 
         let pretty_printed = merged_tree.pretty_print(&class_mapping, &settings);
@@ -639,7 +778,9 @@ mod tests {
             &primary_matcher,
             &auxiliary_matcher,
             &settings,
+            &StructuredMergeOptions::default(),
             None,
+            &mut PhaseTimings::default(),
         );
 
         let _pretty_printed = merged_tree.pretty_print(&class_mapping, &settings);
@@ -652,12 +793,14 @@ mod tests {
             sim_threshold: 0.5,
             max_recovery_size: 100,
             use_rted: true,
+            detect_renames: false,
         };
         let auxiliary_matcher = TreeMatcher {
             min_height: 1,
             sim_threshold: 0.5,
             max_recovery_size: 100,
             use_rted: false,
+            detect_renames: false,
         };
         (primary_matcher, auxiliary_matcher)
     }
@@ -685,7 +828,9 @@ mod tests {
             &primary_matcher,
             &auxiliary_matcher,
             &settings,
+            &StructuredMergeOptions::default(),
             None,
+            &mut PhaseTimings::default(),
         );
 
         let pretty_printed = merged_tree.pretty_print(&class_mapping, &settings);
@@ -748,12 +893,14 @@ fn baz() {
             sim_threshold: 0.4,
             max_recovery_size: 100,
             use_rted: true,
+            detect_renames: false,
         };
         let auxiliary_matcher = TreeMatcher {
             min_height: 2,
             sim_threshold: 0.6,
             max_recovery_size: 100,
             use_rted: false,
+            detect_renames: false,
         };
 
         let settings = DisplaySettings::new(Some(true), Some(9), None, None, None);
@@ -766,7 +913,9 @@ fn baz() {
             &primary_matcher,
             &auxiliary_matcher,
             &settings,
+            &StructuredMergeOptions::default(),
             None,
+            &mut PhaseTimings::default(),
         );
 
         /// Whether line-based fallback was performed on any node in this tree
@@ -785,4 +934,177 @@ fn baz() {
         let pretty_printed = merged_tree.pretty_print(&class_mapping, &settings);
         assert_eq!(pretty_printed, expected);
     }
+
+    #[test]
+    fn large_conflict_falls_back_on_line_based_merge() {
+        let ctx = ctx();
+
+        let base = "\
+fn foo() {
+    let a = 1;
+    let b = 2;
+    let c = 3;
+}";
+
+        let left = "\
+fn foo() {
+    let a = 1;
+    let b = compute_the_left_value(a, Some(\"first\"), Some(\"second\"));
+    let c = 3;
+}";
+
+        let right = "\
+fn foo() {
+    let a = 1;
+    let b = compute_the_right_value(a, None, Some(\"third\"));
+    let c = 3;
+}";
+
+        let base = ctx.parse("a.rs", base);
+        let left = ctx.parse("a.rs", left);
+        let right = ctx.parse("a.rs", right);
+
+        let (primary_matcher, auxiliary_matcher) = rust_matchers();
+
+        let settings = DisplaySettings::default_compact();
+        let (merged_tree, class_mapping) = three_way_merge(
+            base,
+            left,
+            right,
+            None,
+            &primary_matcher,
+            &auxiliary_matcher,
+            &settings,
+            &StructuredMergeOptions::default(),
+            None,
+            &mut PhaseTimings::default(),
+        );
+        let pretty_printed = merged_tree.pretty_print(&class_mapping, &settings);
+        assert_eq!(
+            pretty_printed,
+            "\
+fn foo() {
+    let a = 1;
+    let b =
+<<<<<<< LEFT
+ compute_the_left_value(a, Some(\"first\"), Some(\"second\"))
+||||||| BASE
+ 2
+=======
+ compute_the_right_value(a, None, Some(\"third\"))
+>>>>>>> RIGHT
+;
+    let c = 3;
+}"
+        );
+
+        // with a cap on the size of conflicts, the enclosing statement is merged line by line instead
+        let mut settings = DisplaySettings::default_compact();
+        settings.max_conflict_size = Some(20);
+        let (merged_tree, class_mapping) = three_way_merge(
+            base,
+            left,
+            right,
+            None,
+            &primary_matcher,
+            &auxiliary_matcher,
+            &settings,
+            &StructuredMergeOptions::default(),
+            None,
+            &mut PhaseTimings::default(),
+        );
+        let pretty_printed = merged_tree.pretty_print(&class_mapping, &settings);
+        assert_eq!(
+            pretty_printed,
+            "\
+fn foo() {
+    let a = 1;
+<<<<<<< LEFT
+    let b = compute_the_left_value(a, Some(\"first\"), Some(\"second\"));
+||||||| BASE
+    let b = 2;
+=======
+    let b = compute_the_right_value(a, None, Some(\"third\"));
+>>>>>>> RIGHT
+    let c = 3;
+}"
+        );
+    }
+
+    #[test]
+    #[ignore = "compares durations, so it should be run in release mode: \
+        `cargo test --release -- --ignored commutative_merge_of_large_object`"]
+    fn commutative_merge_of_large_object_scales_linearly() {
+        let (primary_matcher, auxiliary_matcher) = json_matchers();
+
+        // the time spent building the merged tree of an object with `size` keys,
+        // with keys added on both sides and some removed on one side,
+        // so that the object is merged commutatively
+        let build_duration = |size: usize| {
+            let object = |keys: &[String]| format!("{{\n{}\n}}\n", keys.join(",\n"));
+            let base_keys = (0..size)
+                .map(|i| format!("  \"key{i}\": {i}"))
+                .collect_vec();
+            let left_keys = (base_keys.iter().cloned())
+                .chain((0..size / 20).map(|i| format!("  \"left{i}\": {i}")))
+                .collect_vec();
+            let right_keys = (base_keys.iter().enumerate())
+                .filter(|(i, _)| i % 7 != 0)
+                .map(|(_, key)| key.clone())
+                .chain((0..size / 20).map(|i| format!("  \"right{i}\": {i}")))
+                .collect_vec();
+            let [base, left, right] = [base_keys, left_keys, right_keys].map(|keys| object(&keys));
+            let ctx = ctx();
+            let base = ctx.parse("a.json", &base);
+            let left = ctx.parse("a.json", &left);
+            let right = ctx.parse("a.json", &right);
+            let settings = DisplaySettings::default();
+
+            let (base_left_matching, base_right_matching, left_right_matching) = generate_matchings(
+                base,
+                left,
+                right,
+                None,
+                &primary_matcher,
+                &auxiliary_matcher,
+                true,
+                None,
+            );
+            let class_mapping = create_class_mapping(
+                &base_left_matching,
+                &base_right_matching,
+                &left_right_matching,
+            );
+            let (changeset, base_changeset) =
+                generate_pcs_triples(base, left, right, &class_mapping, None);
+            let cleaned_changeset = fix_pcs_inconsistencies(&changeset, &class_mapping, None);
+
+            let start = Instant::now();
+            let merged_tree = build_tree(
+                base,
+                left,
+                right,
+                &class_mapping,
+                &base_changeset,
+                &cleaned_changeset,
+                &settings,
+                &StructuredMergeOptions::default(),
+            );
+            let duration = start.elapsed();
+
+            let merged = merged_tree.pretty_print(&class_mapping, &settings);
+            assert!(!merged.contains("<<<<<<<"));
+            assert!(merged.contains(&format!("\"left{}\"", size / 20 - 1)));
+            assert!(merged.contains(&format!("\"right{}\"", size / 20 - 1)));
+            duration
+        };
+
+        let small = build_duration(1000);
+        let large = build_duration(5000);
+        // a quadratic merge would take about 25 times longer on the large object
+        assert!(
+            large < small * 10,
+            "merging 5000 keys took {large:?}, against {small:?} for 1000 keys"
+        );
+    }
 }
@@ -0,0 +1,118 @@
+//! Replaying the merges of a repository's history, to measure how many conflicts Mergiraf solves
+
+use std::{borrow::Cow, collections::HashSet, io::Write, path::Path, sync::Arc, time::Duration};
+
+use log::{info, warn};
+
+use crate::{
+    git::{changed_files, list_merge_commits, merge_base, read_content_from_commits},
+    lang_profile::LangProfile,
+    line_based::line_based_merge,
+    merge::{CliOpts, line_merge_and_structured_resolution},
+    settings::DisplaySettings,
+};
+
+/// Conflict statistics for all the files merged in a merge commit
+#[derive(Debug, Default, PartialEq, Eq)]
+struct MergeStats {
+    /// The number of files modified on both sides, in a supported language
+    files: usize,
+    /// The number of conflicts left by a line-based merge
+    line_based_conflicts: usize,
+    /// The number of conflicts left by Mergiraf
+    mergiraf_conflicts: usize,
+}
+
+/// Replays the merge commits reachable from `revision` (at most `max_commits` of them,
+/// most recent first), and writes a CSV file comparing the number of conflicts left by
+/// a line-based merge and by Mergiraf in each of them.
+///
+/// Only the files modified on both sides of a merge and in a supported language are merged.
+pub fn merge_history(
+    repo_dir: &Path,
+    revision: &str,
+    max_commits: Option<usize>,
+    timeout: Duration,
+    output: &mut impl Write,
+) -> Result<(), String> {
+    let write_error = |err: std::io::Error| format!("could not write the CSV output: {err}");
+    writeln!(
+        output,
+        "commit,files,line_based_conflicts,mergiraf_conflicts"
+    )
+    .map_err(write_error)?;
+
+    for (commit, left, right) in list_merge_commits(repo_dir, revision, max_commits)? {
+        let Some(base) = merge_base(repo_dir, &left, &right) else {
+            warn!("Skipping merge commit {commit}, whose parents have no common ancestor");
+            continue;
+        };
+        info!("Replaying merge commit {commit}");
+        let stats = replay_merge(repo_dir, (&base, &left, &right), timeout)?;
+        writeln!(
+            output,
+            "{commit},{},{},{}",
+            stats.files, stats.line_based_conflicts, stats.mergiraf_conflicts
+        )
+        .map_err(write_error)?;
+    }
+    Ok(())
+}
+
+/// Merges all the files modified on both sides of a merge
+fn replay_merge(
+    repo_dir: &Path,
+    (base, left, right): (&str, &str, &str),
+    timeout: Duration,
+) -> Result<MergeStats, String> {
+    let changed_on_right: HashSet<_> = changed_files(repo_dir, base, right)?.into_iter().collect();
+    let mut stats = MergeStats::default();
+
+    for path in changed_files(repo_dir, base, left)? {
+        if !changed_on_right.contains(&path) || LangProfile::detect_from_filename(&path).is_none() {
+            continue;
+        }
+        // files added or deleted on either side can't be merged
+        let Some((contents_base, contents_left, contents_right)) =
+            read_content_from_commits(repo_dir, (base, left, right), &path)
+        else {
+            continue;
+        };
+        let settings = DisplaySettings::default();
+        // conflict markers committed by mistake would prevent parsing the line-based merge
+        if [&contents_base, &contents_left, &contents_right]
+            .iter()
+            .any(|contents| settings.conflict_regexes().is_match(contents))
+        {
+            warn!(
+                "Skipping {}, which contains conflict markers",
+                path.display()
+            );
+            continue;
+        }
+        let line_based =
+            line_based_merge(&contents_base, &contents_left, &contents_right, &settings);
+        let mergiraf_conflicts = if line_based.conflict_count == 0 {
+            0
+        } else {
+            line_merge_and_structured_resolution(
+                Arc::new(Cow::Owned(contents_base)),
+                Arc::new(Cow::Owned(contents_left)),
+                Arc::new(Cow::Owned(contents_right)),
+                &path,
+                settings,
+                true,
+                None,
+                CliOpts::default(),
+                Some(repo_dir),
+                None,
+                timeout,
+            )
+            .conflict_count
+        };
+        stats.files += 1;
+        stats.line_based_conflicts += line_based.conflict_count;
+        stats.mergiraf_conflicts += mergiraf_conflicts;
+    }
+    Ok(stats)
+}
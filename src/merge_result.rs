@@ -1,5 +1,11 @@
-use crate::attempts::Attempt;
-use log::info;
+use std::str::FromStr;
+
+use diffy_imara::DiffOptions;
+
+use crate::{
+    attempts::Attempt, parsed_merge::ParsedMerge, pcs::Revision, settings::DisplaySettings,
+};
+use log::{info, warn};
 
 /// A merged output (represented as a string) together with statistics
 /// about the conflicts it contains.
@@ -15,9 +21,111 @@ pub struct MergeResult {
     pub method: &'static str,
     /// Indicates that there are known conflicts which haven't been marked as such (such as duplicate signatures)
     pub has_additional_issues: bool,
+    /// The conflict mass of the line-based merge of the same revisions, which the other
+    /// merging methods attempt to reduce
+    pub line_based_conflict_mass: usize,
 }
 
 impl MergeResult {
+    /// Parses the merged contents into chunks, to inspect the conflicts they contain.
+    /// The settings must be those the merge was made with, so that the conflict markers
+    /// are recognized.
+    pub fn parse<'a>(&'a self, settings: &DisplaySettings) -> Result<ParsedMerge<'a>, String> {
+        ParsedMerge::parse(&self.contents, settings)
+    }
+
+    /// A heuristic estimate, between 0 and 1, of how confident one can be in the resolution
+    /// of this merge.
+    ///
+    /// It is the share of the line-based merge's conflict mass which was resolved, so that
+    /// a merge without any textual conflict in the first place scores 1, and falling back on
+    /// the line-based merge scores 0 if it has conflicts. As each remaining conflict needs
+    /// to be looked at anyway, this share is divided by one plus their number. It is then
+    /// halved if the merge may have issues which aren't marked as conflicts, such as
+    /// duplicate signatures or, for merges which weren't checked for them, a language
+    /// Mergiraf doesn't support.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn confidence(&self) -> f64 {
+        let resolved = if self.line_based_conflict_mass == 0 {
+            1.0
+        } else {
+            let resolved_mass = self
+                .line_based_conflict_mass
+                .saturating_sub(self.conflict_mass);
+            resolved_mass as f64 / self.line_based_conflict_mass as f64
+        };
+        let confidence = resolved / (1 + self.conflict_count) as f64;
+        if self.has_additional_issues {
+            confidence / 2.0
+        } else {
+            confidence
+        }
+    }
+
+    /// Separate the remaining conflicts from the cleanly merged parts, so that they can be
+    /// written to a `.rej` file.
+    ///
+    /// Returns the merged contents (with the left side kept in place of each conflict)
+    /// and the contents of the reject file.
+    pub fn split_rejects(&self, settings: &DisplaySettings) -> Result<(String, String), String> {
+        let parsed = ParsedMerge::parse(&self.contents, settings)?;
+        let mut settings = settings.clone();
+        settings.add_revision_names(&parsed);
+        Ok(parsed.split_rejects(&settings))
+    }
+
+    /// Resolve the remaining conflicts by keeping one of their sides entirely.
+    ///
+    /// The changes made on the other side in those conflicting regions are lost.
+    pub fn resolve_in_favor_of(
+        &self,
+        side: PreferredSide,
+        settings: &DisplaySettings,
+    ) -> Result<Self, String> {
+        if self.conflict_count == 0 {
+            return Ok(self.clone());
+        }
+        let parsed = ParsedMerge::parse(&self.contents, settings)?;
+        let revision = match side {
+            PreferredSide::Left => Revision::Left,
+            PreferredSide::Right => Revision::Right,
+        };
+        warn!(
+            "Mergiraf: Kept the {side} side of {} conflict(s), discarding the changes from the other side",
+            self.conflict_count
+        );
+        Ok(Self {
+            contents: parsed.reconstruct_revision(revision),
+            conflict_count: 0,
+            conflict_mass: 0,
+            method: self.method,
+            has_additional_issues: self.has_additional_issues,
+            line_based_conflict_mass: self.line_based_conflict_mass,
+        })
+    }
+
+    /// Render the changes the merge makes to the base revision as a unified diff,
+    /// in which the remaining conflicts appear with their markers
+    pub fn changes_from_base(&self, contents_base: &str, file_name: &str) -> String {
+        DiffOptions::new()
+            .set_original_filename(format!("a/{file_name}"))
+            .set_modified_filename(format!("b/{file_name}"))
+            .create_patch(contents_base, &self.contents)
+            .to_string()
+    }
+
+    /// Render the differences between the expected output of the merge and its actual
+    /// contents as a unified diff, or `None` if they are identical
+    pub fn differences_with_expected(&self, expected: &str, expected_name: &str) -> Option<String> {
+        (expected != self.contents).then(|| {
+            DiffOptions::new()
+                .set_original_filename(expected_name.to_owned())
+                .set_modified_filename("merged")
+                .create_patch(expected, &self.contents)
+                .to_string()
+        })
+    }
+
     /// Helper to store a merge result in an attempt
     pub(crate) fn store_in_attempt(&self, attempt: &Attempt) {
         attempt.write(self.method, &self.contents).ok();
@@ -44,3 +152,31 @@ impl MergeResult {
         }
     }
 }
+
+/// The side to keep in place of the conflicts that could not be solved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreferredSide {
+    Left,
+    Right,
+}
+
+impl FromStr for PreferredSide {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "left" => Ok(Self::Left),
+            "right" => Ok(Self::Right),
+            _ => Err(format!("invalid side `{s}`, expected `left` or `right`")),
+        }
+    }
+}
+
+impl std::fmt::Display for PreferredSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Left => write!(f, "left"),
+            Self::Right => write!(f, "right"),
+        }
+    }
+}
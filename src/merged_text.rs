@@ -198,7 +198,8 @@ impl<'a> MergedText<'a> {
         }
 
         if settings.compact_or_default() {
-            self.render_compact(settings)
+            self.split_conflicts_on_common_lines()
+                .render_compact(settings)
         } else {
             self.render_full_lines(settings)
         }
@@ -211,6 +212,7 @@ impl<'a> MergedText<'a> {
         let mut left_buffer = String::new();
         let mut right_buffer = String::new();
         let mut gathering_conflict = false;
+        let mut conflict_id = 0;
         for section in &self.sections {
             match section {
                 MergeSection::Merged(contents) => {
@@ -230,6 +232,7 @@ impl<'a> MergedText<'a> {
                             &left_buffer,
                             &right_buffer,
                             settings,
+                            &mut conflict_id,
                             &mut output,
                         );
                         output.push_str(rest);
@@ -286,6 +289,7 @@ impl<'a> MergedText<'a> {
                             &left_buffer,
                             &right_buffer,
                             settings,
+                            &mut conflict_id,
                             &mut output,
                         );
                     }
@@ -299,6 +303,7 @@ impl<'a> MergedText<'a> {
                 &left_buffer,
                 &right_buffer,
                 settings,
+                &mut conflict_id,
                 &mut output,
             );
         }
@@ -310,15 +315,21 @@ impl<'a> MergedText<'a> {
         left: &str,
         right: &str,
         settings: &DisplaySettings,
+        conflict_id: &mut usize,
         output: &mut String,
     ) {
+        *conflict_id += 1;
         Self::maybe_add_newline(output);
+        if let Some(comment) = settings.conflict_comment(*conflict_id) {
+            output.push_str(&comment);
+            output.push('\n');
+        }
         output.push_str(&settings.left_marker_or_default());
         output.push('\n');
         if !left.trim().is_empty() {
             output.push_str(left);
         }
-        if settings.diff3 {
+        if settings.show_base() {
             Self::maybe_add_newline(output);
             output.push_str(&settings.base_marker_or_default());
             output.push('\n');
@@ -337,22 +348,114 @@ impl<'a> MergedText<'a> {
         output.push('\n');
     }
 
+    /// Splits each conflict around the lines it has in common in all three revisions,
+    /// and resolves the parts between those lines which are identical on both sides,
+    /// so that only the lines which actually differ are left in conflict.
+    ///
+    /// Lines only common to the left and right revisions are kept in the conflict,
+    /// as the base revision cannot be split around them.
+    fn split_conflicts_on_common_lines(&self) -> Self {
+        let mut split = Self::new();
+        for section in &self.sections {
+            let MergeSection::Conflict { base, left, right } = section else {
+                split.sections.push(section.clone());
+                continue;
+            };
+            let base_lines = base.split_inclusive('\n').collect_vec();
+            let left_lines = left.split_inclusive('\n').collect_vec();
+            let right_lines = right.split_inclusive('\n').collect_vec();
+            let (Some(left_matching), Some(right_matching)) = (
+                Self::match_lines(&base_lines, &left_lines),
+                Self::match_lines(&base_lines, &right_lines),
+            ) else {
+                split.sections.push(section.clone());
+                continue;
+            };
+
+            let mut start = (0, 0, 0);
+            let sync_points = (left_matching.into_iter().zip(right_matching).enumerate())
+                .filter_map(|(base_idx, matched)| match matched {
+                    (Some(left_idx), Some(right_idx)) => Some((base_idx, left_idx, right_idx)),
+                    _ => None,
+                })
+                .chain([(base_lines.len(), left_lines.len(), right_lines.len())]);
+            for end in sync_points {
+                let base_part = base_lines[start.0..end.0].concat();
+                let left_part = left_lines[start.1..end.1].concat();
+                let right_part = right_lines[start.2..end.2].concat();
+                if left_part == right_part {
+                    split.push_merged(left_part.into());
+                } else {
+                    split.sections.push(MergeSection::Conflict {
+                        base: base_part.into(),
+                        left: left_part.into(),
+                        right: right_part.into(),
+                    });
+                }
+                if let Some(common_line) = base_lines.get(end.0) {
+                    split.push_merged(Cow::Owned((*common_line).to_owned()));
+                }
+                start = (end.0 + 1, end.1 + 1, end.2 + 1);
+            }
+        }
+        split
+    }
+
+    /// Matches identical lines of two sequences along their longest common subsequence,
+    /// giving for each line of the first sequence the index of the matching line in the second.
+    /// Gives up on sequences which are too long for this to be cheap.
+    fn match_lines(first: &[&str], second: &[&str]) -> Option<Vec<Option<usize>>> {
+        const MAX_TABLE_SIZE: usize = 1 << 20;
+        if (first.len() + 1) * (second.len() + 1) > MAX_TABLE_SIZE {
+            return None;
+        }
+        // lengths of the longest common subsequences of the suffixes of both sequences
+        let width = second.len() + 1;
+        let mut lengths = vec![0_usize; (first.len() + 1) * width];
+        for i in (0..first.len()).rev() {
+            for j in (0..second.len()).rev() {
+                lengths[i * width + j] = if first[i] == second[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+                };
+            }
+        }
+        let mut matching = vec![None; first.len()];
+        let (mut i, mut j) = (0, 0);
+        while i < first.len() && j < second.len() {
+            if first[i] == second[j] {
+                matching[i] = Some(j);
+                i += 1;
+                j += 1;
+            } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Some(matching)
+    }
+
     /// Renders the merged text without expanding conflict boundaries so that they match newlines.
     /// Instead, insert newlines around the conflict boundaries directly.
     fn render_compact(&self, settings: &DisplaySettings) -> String {
         let mut output = String::new();
-        let mut last_was_conflict = false;
+        // whether the last section was a conflict not ending with a newline,
+        // in which case a newline was inserted after it
+        let mut last_was_unfinished_conflict = false;
+        let mut conflict_id = 0;
         let leading_whitespace_pattern = Regex::new("^[\t ]*\n").expect("Invalid regex");
         let trailing_whitespace_pattern = Regex::new("[\t ]+$").expect("Invalid regex");
         for section in &self.sections {
             match section {
                 MergeSection::Merged(contents) => {
-                    if last_was_conflict {
+                    if last_was_unfinished_conflict {
                         output.push_str(&leading_whitespace_pattern.replace(contents, ""));
                     } else {
                         output.push_str(contents);
                     }
-                    last_was_conflict = false;
+                    last_was_unfinished_conflict = false;
                 }
                 MergeSection::Conflict { base, left, right } => {
                     if let Some(occurrence) = trailing_whitespace_pattern.find(&output) {
@@ -374,11 +477,27 @@ impl<'a> MergedText<'a> {
                         } else {
                             &(whitespace_to_prepend + right).into()
                         };
-                        Self::render_conflict(new_base, new_left, new_right, settings, &mut output);
+                        Self::render_conflict(
+                            new_base,
+                            new_left,
+                            new_right,
+                            settings,
+                            &mut conflict_id,
+                            &mut output,
+                        );
                     } else {
-                        Self::render_conflict(base, left, right, settings, &mut output);
+                        Self::render_conflict(
+                            base,
+                            left,
+                            right,
+                            settings,
+                            &mut conflict_id,
+                            &mut output,
+                        );
                     }
-                    last_was_conflict = true;
+                    last_was_unfinished_conflict = [base, left, right]
+                        .iter()
+                        .any(|side| !side.is_empty() && !side.ends_with('\n'));
                 }
             }
         }
@@ -398,13 +517,22 @@ impl<'a> MergedText<'a> {
         settings: &DisplaySettings,
         method: &'static str,
     ) -> MergeResult {
-        let rendered = self.render(settings);
+        // compact rendering splits conflicts, so count them after doing so
+        let split;
+        let (merged_text, rendered) = if settings.compact_or_default() {
+            split = self.split_conflicts_on_common_lines();
+            (&split, split.render_compact(settings))
+        } else {
+            (self, self.render(settings))
+        };
         MergeResult {
             contents: rendered,
-            conflict_count: self.count_conflicts(),
-            conflict_mass: self.conflict_mass(),
+            conflict_count: merged_text.count_conflicts(),
+            conflict_mass: merged_text.conflict_mass(),
             method,
             has_additional_issues: false,
+            // only known once the merge is compared to the line-based one
+            line_based_conflict_mass: merged_text.conflict_mass(),
         }
     }
 }
@@ -467,6 +595,71 @@ class Cls {
         );
     }
 
+    #[test]
+    fn compact_mode_splits_conflicts_on_common_lines() {
+        let merged_text = MergedText {
+            sections: vec![
+                merged("fn main() {\n"),
+                conflict(
+                    "    let a = 1;\n    let b = 2;\n    let c = 3;\n",
+                    "    let a = 1;\n    let b = 4;\n    let c = 3;\n",
+                    "    let a = 1;\n    let b = 5;\n    let c = 3;\n",
+                ),
+                merged("}\n"),
+            ],
+        };
+
+        let expected_compact = "\
+fn main() {
+    let a = 1;
+<<<<<<< LEFT
+    let b = 4;
+||||||| BASE
+    let b = 2;
+=======
+    let b = 5;
+>>>>>>> RIGHT
+    let c = 3;
+}
+";
+        let settings = DisplaySettings::default_compact();
+        assert_eq!(merged_text.render(&settings), expected_compact);
+        let merge_result = merged_text.into_merge_result(&settings, "test");
+        assert_eq!(merge_result.contents, expected_compact);
+        assert_eq!(merge_result.conflict_count, 1);
+    }
+
+    #[test]
+    fn compact_mode_keeps_lines_missing_from_base_in_conflict() {
+        let merged_text = MergedText {
+            sections: vec![conflict("a\n\nb\n", "A\n\nsame\nb\n", "A2\n\nsame\nB\n")],
+        };
+
+        // the empty line is common to all revisions, `same` is not part of the base
+        let expected_compact = "\
+<<<<<<< LEFT
+A
+||||||| BASE
+a
+=======
+A2
+>>>>>>> RIGHT
+
+<<<<<<< LEFT
+same
+b
+||||||| BASE
+b
+=======
+same
+B
+>>>>>>> RIGHT
+";
+        let settings = DisplaySettings::default_compact();
+        assert_eq!(merged_text.render(&settings), expected_compact);
+        assert_eq!(merged_text.reconstruct_revision(Revision::Base), "a\n\nb\n");
+    }
+
     #[test]
     fn multiple_conflicts_on_same_line() {
         let merged_text = MergedText {
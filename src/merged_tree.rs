@@ -1,6 +1,6 @@
 use std::{
     borrow::Cow,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fmt::Display,
     hash::{Hash, Hasher},
     iter,
@@ -298,6 +298,29 @@ impl<'a> MergedTree<'a> {
         }
     }
 
+    /// Resolve the conflicts of the line-based merges of the supplied nodes by keeping
+    /// the side associated with each node
+    pub(crate) fn keep_side_in_line_based_merges(
+        self,
+        sides: &HashMap<Leader<'a>, Revision>,
+    ) -> Self {
+        match self {
+            Self::LineBasedMerge { node, parsed } if let Some(side) = sides.get(&node) => {
+                Self::LineBasedMerge {
+                    node,
+                    parsed: parsed.keep_side(*side),
+                }
+            }
+            Self::MixedTree { node, children, .. } => Self::new_mixed(
+                node,
+                (children.into_iter())
+                    .map(|child| child.keep_side_in_line_based_merges(sides))
+                    .collect(),
+            ),
+            _ => self,
+        }
+    }
+
     /// Recurse on a [RevNode] to create a corresponding `MergedTree`
     /// where any descendant node included in the supplied set has been
     /// replaced by a line-based merge.
@@ -538,7 +561,7 @@ impl<'a> MergedTree<'a> {
     }
 
     /// Extracts a signature for the given node if there is a signature definition
-    /// for this type of nodes in the language profile.
+    /// for this type of nodes in the language profile, and it identifies the node.
     pub(crate) fn signature<'b>(
         &'b self,
         class_mapping: &ClassMapping<'a>,
@@ -549,8 +572,7 @@ impl<'a> MergedTree<'a> {
             | MergedTree::LineBasedMerge { node, .. } => node.signature_definition(),
             MergedTree::Conflict { .. } | MergedTree::CommutativeChildSeparator { .. } => None,
         }?;
-        let signature = definition.extract_signature_from_merged_node(self, class_mapping);
-        Some(signature)
+        definition.extract_signature_from_merged_node(self, class_mapping)
     }
 }
 
@@ -566,6 +588,7 @@ mod test {
 
     use crate::{
         merge_3dm::three_way_merge,
+        structured::{PhaseTimings, StructuredMergeOptions},
         test_utils::{ctx, json_matchers},
     };
 
@@ -585,7 +608,9 @@ mod test {
             &primary_matcher,
             &auxiliary_matcher,
             &DisplaySettings::default(),
+            &StructuredMergeOptions::default(),
             None,
+            &mut PhaseTimings::default(),
         );
         assert_eq!(
             merged_tree.to_string(),
@@ -9,6 +9,7 @@ use crate::{
     class_mapping::{ClassMapping, Leader, RevNode},
     lang_profile::CommutativeParent,
     merged_tree::{Conflict, MergedTree},
+    node_trace::{merged_tree_contains_traced, trace_node},
     pcs::Revision,
     signature::isomorphic_merged_trees,
 };
@@ -70,14 +71,20 @@ fn highlight_duplicate_signatures<'a>(
     let mut conflict_found = false;
     sig_to_indices
         .iter()
-        .filter_map(|(signature, indices)| (indices.len() > 1).then_some(signature))
-        .for_each(|signature| {
+        .filter(|(_, indices)| indices.len() > 1)
+        .for_each(|(signature, indices)| {
             conflict_found = true;
             debug!(
                 "signature conflict found in {}: {}",
                 commutative_parent.parent_type(),
                 signature
             );
+            if indices
+                .iter()
+                .any(|idx| merged_tree_contains_traced(&elements[*idx], class_mapping))
+            {
+                trace_node!("{parent} has elements with the same signature {signature}, grouped in a conflict");
+            }
         });
     if !conflict_found {
         return elements;
@@ -166,7 +173,9 @@ fn highlight_duplicate_signatures<'a>(
                         let conflict_add_separator = match add_separator {
                             AddSeparator::OnlyInside => AddSeparator::OnlyInside,
                             AddSeparator::AtEnd => {
-                                if let Some((_, true, _)) = filtered_elements.get(filtered_idx - 1)
+                                if let Some((_, true, _)) = filtered_idx
+                                    .checked_sub(1)
+                                    .and_then(|i| filtered_elements.get(i))
                                 {
                                     AddSeparator::AtEnd
                                 } else {
@@ -192,8 +201,9 @@ fn highlight_duplicate_signatures<'a>(
                             match add_separator {
                                 AddSeparator::OnlyInside => {}
                                 AddSeparator::AtEnd => {
-                                    if let Some((_, true, _)) =
-                                        filtered_elements.get(filtered_idx - 1)
+                                    if let Some((_, true, _)) = filtered_idx
+                                        .checked_sub(1)
+                                        .and_then(|i| filtered_elements.get(i))
                                     {
                                         skip_next_separator = true;
                                     }
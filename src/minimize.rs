@@ -179,12 +179,14 @@ fn attempt_minimization_step(
         sim_threshold: 0.4,
         max_recovery_size: 100,
         use_rted: true,
+        detect_renames: false,
     };
     let auxiliary_matcher = TreeMatcher {
         min_height: 2,
         sim_threshold: 0.6,
         max_recovery_size: 100,
         use_rted: false,
+        detect_renames: false,
     };
     let (base_left_matching, base_right_matching, left_right_matching) = generate_matchings(
         tree_base,
@@ -193,6 +195,7 @@ fn attempt_minimization_step(
         None,
         &primary_matcher,
         &auxiliary_matcher,
+        false,
         None,
     );
 
@@ -1,4 +1,7 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, ops::Range};
+
+use imara_diff::{Algorithm, diff, intern::InternedInput};
+use itertools::Itertools;
 
 /// Type of newlines present in a file
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -35,6 +38,123 @@ pub fn imitate_newline_style(contents: &str, style: NewlineStyle) -> String {
     }
 }
 
+impl NewlineStyle {
+    fn line_ending(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+            Self::Cr => "\r",
+        }
+    }
+}
+
+/// The line endings of a file, to be restored after merging its contents normalized to LF
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEndings {
+    /// The most common style of line endings in the file
+    style: NewlineStyle,
+    /// If the file mixes several styles, its contents normalized to LF,
+    /// with the original ending of each line
+    mixed: Option<(String, Vec<&'static str>)>,
+}
+
+impl LineEndings {
+    /// Record the line endings used in a file
+    pub fn infer(contents: &str) -> Self {
+        let lines = lines_with_endings(contents);
+        let is_mixed = (lines.iter())
+            .filter(|(_, ending)| !ending.is_empty())
+            .map(|(_, ending)| ending)
+            .dedup()
+            .nth(1)
+            .is_some();
+        Self {
+            style: infer_newline_style(contents),
+            mixed: is_mixed.then(|| {
+                let endings = lines.iter().map(|(_, ending)| *ending).collect();
+                (normalize_to_lf(contents).into_owned(), endings)
+            }),
+        }
+    }
+
+    /// Renormalize a string derived from the file to use its line endings.
+    ///
+    /// If the file mixes several styles of line endings, the lines which are still present
+    /// keep their original ending, and the others get the most common one.
+    pub fn imitate(&self, contents: &str) -> String {
+        let Some((original, original_endings)) = &self.mixed else {
+            return imitate_newline_style(contents, self.style);
+        };
+        let contents = normalize_to_lf(contents);
+        let input = InternedInput::new(original.as_str(), contents.as_ref());
+
+        let mut changes = Vec::new();
+        diff(
+            Algorithm::Histogram,
+            &input,
+            |before: Range<u32>, after: Range<u32>| changes.push((before, after)),
+        );
+        let end_of_input = (
+            input.before.len() as u32..input.before.len() as u32,
+            input.after.len() as u32..input.after.len() as u32,
+        );
+
+        // for each line of the contents, the ending of the original line it corresponds to, if any
+        let mut kept_endings = vec![None; input.after.len()];
+        let (mut original_pos, mut contents_pos) = (0, 0);
+        for (before, after) in changes.into_iter().chain([end_of_input]) {
+            // the lines between two changes are the same on both sides
+            for (original_line, contents_line) in
+                (original_pos..before.start).zip(contents_pos..after.start)
+            {
+                kept_endings[contents_line as usize] =
+                    Some(original_endings[original_line as usize]);
+            }
+            original_pos = before.end;
+            contents_pos = after.end;
+        }
+
+        let default_ending = self.style.line_ending();
+        let mut result = String::with_capacity(contents.len());
+        for (line, kept_ending) in contents.split_inclusive('\n').zip(kept_endings) {
+            match line.strip_suffix('\n') {
+                Some(line) => {
+                    result.push_str(line);
+                    result.push_str(
+                        kept_ending
+                            .filter(|e| !e.is_empty())
+                            .unwrap_or(default_ending),
+                    );
+                }
+                None => result.push_str(line),
+            }
+        }
+        result
+    }
+}
+
+/// Split contents into lines, each with the line ending which follows it (if any)
+fn lines_with_endings(contents: &str) -> Vec<(&str, &'static str)> {
+    let mut lines = Vec::new();
+    let mut rest = contents;
+    while !rest.is_empty() {
+        let Some(end) = rest.find(['\r', '\n']) else {
+            lines.push((rest, ""));
+            break;
+        };
+        let ending = if rest[end..].starts_with("\r\n") {
+            "\r\n"
+        } else if rest[end..].starts_with('\r') {
+            "\r"
+        } else {
+            "\n"
+        };
+        lines.push((&rest[..end], ending));
+        rest = &rest[end + ending.len()..];
+    }
+    lines
+}
+
 /// Normalize a string to only contain newline characters `\n`, no carriage return `\r`
 pub fn normalize_to_lf<'a>(contents: impl Into<Cow<'a, str>>) -> Cow<'a, str> {
     let contents = contents.into();
@@ -72,4 +192,23 @@ mod tests {
         let result = imitate("A\rB\r\nC\nD", NewlineStyle::Cr);
         assert_eq!(result, "A\rB\rC\rD");
     }
+
+    #[test]
+    fn line_endings_of_single_style() {
+        let line_endings = LineEndings::infer("a\r\nb\r\n");
+
+        assert_eq!(line_endings.imitate("a\nc\nb\n"), "a\r\nc\r\nb\r\n");
+    }
+
+    #[test]
+    fn mixed_line_endings() {
+        let line_endings = LineEndings::infer("a\r\nb\nc\r\nd\re");
+
+        // unchanged lines keep their endings, new ones get the most common one
+        assert_eq!(
+            line_endings.imitate("a\nnew\nb\nc\nd\nmore\ne"),
+            "a\r\nnew\r\nb\nc\r\nd\rmore\r\ne"
+        );
+        assert_eq!(line_endings.imitate("b\nd\n"), "b\nd\r");
+    }
 }
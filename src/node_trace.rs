@@ -0,0 +1,199 @@
+//! Following a single node through the merge, to understand what happened to it.
+//!
+//! The node is designated by its id, as shown in the files written to the debug directory.
+//! Since ids are attributed separately in each revision, the revision can be specified
+//! too, as in `left:12`. Otherwise, the nodes with this id in all three revisions are followed.
+
+use std::{
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    ast::AstNode,
+    class_mapping::{ClassMapping, Leader, RevNode},
+    merged_tree::{Conflict, MergedTree},
+    pcs::{PCSNode, Revision},
+};
+
+/// A node to follow through the merge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TracedNode {
+    /// The revision the node belongs to, or `None` for all revisions
+    revision: Option<Revision>,
+    /// The id of the node in its revision
+    id: usize,
+}
+
+impl FromStr for TracedNode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (revision, id) = match s.split_once(':') {
+            None => (None, s),
+            Some((revision, id)) => {
+                let revision = match revision.to_lowercase().as_str() {
+                    "base" => Revision::Base,
+                    "left" => Revision::Left,
+                    "right" => Revision::Right,
+                    _ => {
+                        return Err(format!(
+                            "invalid revision `{revision}`, expected `base`, `left` or `right`"
+                        ));
+                    }
+                };
+                (Some(revision), id)
+            }
+        };
+        match id.parse::<usize>() {
+            // the id must leave room for the revision once packed in `TRACED_NODE`
+            Ok(id) if id > 0 && id.checked_mul(4).is_some() => Ok(Self { revision, id }),
+            _ => Err(format!("invalid node id `{id}`")),
+        }
+    }
+}
+
+/// The traced node, packed as `id * 4 + revision`, or 0 if no node is traced
+/// (node ids start at 1)
+static TRACED_NODE: AtomicUsize = AtomicUsize::new(0);
+
+/// Log all the decisions made about the given node during the merge.
+pub fn set_traced_node(node: Option<TracedNode>) {
+    let packed = node.map_or(0, |TracedNode { revision, id }| {
+        let revision = match revision {
+            None => 0,
+            Some(Revision::Base) => 1,
+            Some(Revision::Left) => 2,
+            Some(Revision::Right) => 3,
+        };
+        id * 4 + revision
+    });
+    TRACED_NODE.store(packed, Ordering::Relaxed);
+}
+
+/// The node currently traced, if any
+pub(crate) fn traced_node() -> Option<TracedNode> {
+    let packed = TRACED_NODE.load(Ordering::Relaxed);
+    let revision = match packed % 4 {
+        1 => Some(Revision::Base),
+        2 => Some(Revision::Left),
+        3 => Some(Revision::Right),
+        _ => None,
+    };
+    (packed != 0).then_some(TracedNode {
+        revision,
+        id: packed / 4,
+    })
+}
+
+/// Whether this node is the one traced
+pub(crate) fn is_traced(revision: Revision, node: &AstNode) -> bool {
+    traced_node().is_some_and(|traced| {
+        traced.id == node.id && traced.revision.is_none_or(|rev| rev == revision)
+    })
+}
+
+/// Finds the traced node in the tree of a revision, if any
+pub(crate) fn find_traced<'a>(
+    revision: Revision,
+    tree: &'a AstNode<'a>,
+) -> Option<&'a AstNode<'a>> {
+    traced_node()?;
+    tree.dfs().find(|node| is_traced(revision, node))
+}
+
+/// Whether the traced node belongs to the class of this leader
+pub(crate) fn is_traced_class<'a>(leader: &Leader<'a>, class_mapping: &ClassMapping<'a>) -> bool {
+    traced_node().is_some()
+        && class_mapping
+            .representatives(leader)
+            .iter()
+            .any(|RevNode { rev, node }| is_traced(*rev, node))
+}
+
+/// Whether the traced node belongs to the class of this PCS node
+pub(crate) fn is_traced_pcs_node<'a>(node: &PCSNode<'a>, class_mapping: &ClassMapping<'a>) -> bool {
+    match node {
+        PCSNode::Node { node, .. } => is_traced_class(node, class_mapping),
+        PCSNode::VirtualRoot | PCSNode::LeftMarker | PCSNode::RightMarker => false,
+    }
+}
+
+/// Whether the traced node is a descendant (or the node itself) of any representative
+/// of this leader
+pub(crate) fn contains_traced<'a>(leader: &Leader<'a>, class_mapping: &ClassMapping<'a>) -> bool {
+    traced_node().is_some()
+        && class_mapping
+            .representatives(leader)
+            .iter()
+            .any(|RevNode { rev, node }| find_traced(*rev, node).is_some())
+}
+
+/// Whether the traced node is part of one of the sides of this conflict
+pub(crate) fn conflict_contains_traced(conflict: &Conflict) -> bool {
+    let Conflict { base, left, right } = conflict;
+    traced_node().is_some()
+        && [
+            (Revision::Base, base),
+            (Revision::Left, left),
+            (Revision::Right, right),
+        ]
+        .into_iter()
+        .any(|(rev, side)| side.iter().any(|node| find_traced(rev, node).is_some()))
+}
+
+/// Whether the traced node is part of any of the revisions this merged tree is built from
+pub(crate) fn merged_tree_contains_traced<'a>(
+    tree: &MergedTree<'a>,
+    class_mapping: &ClassMapping<'a>,
+) -> bool {
+    match tree {
+        MergedTree::ExactTree { node, .. }
+        | MergedTree::MixedTree { node, .. }
+        | MergedTree::LineBasedMerge { node, .. } => contains_traced(node, class_mapping),
+        MergedTree::Conflict(conflict) => conflict_contains_traced(conflict),
+        MergedTree::CommutativeChildSeparator { .. } => false,
+    }
+}
+
+/// Logs a message about the traced node
+macro_rules! trace_node {
+    ($($arg:tt)+) => {
+        log::info!("[trace-node] {}", format_args!($($arg)+))
+    };
+}
+
+pub(crate) use trace_node;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_traced_node() {
+        assert_eq!(
+            "12".parse(),
+            Ok(TracedNode {
+                revision: None,
+                id: 12
+            })
+        );
+        assert_eq!(
+            "Left:3".parse(),
+            Ok(TracedNode {
+                revision: Some(Revision::Left),
+                id: 3
+            })
+        );
+        assert!("middle:3".parse::<TracedNode>().is_err());
+        assert!("base:0".parse::<TracedNode>().is_err());
+        assert!("base:x".parse::<TracedNode>().is_err());
+        assert!((usize::MAX / 4).to_string().parse::<TracedNode>().is_ok());
+        assert!(
+            (usize::MAX / 4 + 1)
+                .to_string()
+                .parse::<TracedNode>()
+                .is_err()
+        );
+    }
+}
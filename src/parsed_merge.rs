@@ -1,7 +1,8 @@
-use std::{cell::LazyCell, collections::HashMap, ops::Range};
+use std::{cell::LazyCell, collections::HashMap, fmt::Write, ops::Range};
 
 use crate::{
     ast::AstNode,
+    git,
     line_based::LINE_BASED_METHOD,
     matching::Matching,
     merge_result::MergeResult,
@@ -69,7 +70,7 @@ struct OffsetMap {
 impl<'a> ParsedMerge<'a> {
     /// Parse a file into a series of chunks.
     /// Fails if the conflict markers do not appear in a consistent order.
-    pub(crate) fn parse(source: &'a str, settings: &DisplaySettings) -> Result<Self, String> {
+    pub fn parse(source: &'a str, settings: &DisplaySettings) -> Result<Self, String> {
         let mut chunks = Vec::new();
 
         let ConflictRegexes {
@@ -120,6 +121,18 @@ impl<'a> ParsedMerge<'a> {
 
             if let Some(captures) = (diff3_captures.as_ref()).or(diff3_no_newline_captures.as_ref())
             {
+                // a side containing conflict markers of the same size means that conflicts are nested,
+                // in which case we can't tell which markers delimit which conflict
+                let marker_size = settings.conflict_marker_size_or_default();
+                if [2, 4, 5].into_iter().any(|i| {
+                    captures
+                        .get(i)
+                        .is_some_and(|side| contains_conflict_marker(side.as_str(), marker_size))
+                }) {
+                    return Err(format!(
+                        "Mergiraf cannot solve nested conflicts: a conflict contains lines which look like conflict markers of size {marker_size}. Create the outer conflicts with a larger conflict marker size instead"
+                    ));
+                }
                 chunks.push(MergedChunk::Conflict {
                     left_name: captures.get(1).map(|m| m.as_str()),
                     left: captures.get(2).map(|m| m.as_str()),
@@ -141,7 +154,7 @@ impl<'a> ParsedMerge<'a> {
     }
 
     /// Construct a parsed merge by indexing the provided chunks
-    fn new(chunks: Vec<MergedChunk<'a>>) -> Self {
+    pub(crate) fn new(chunks: Vec<MergedChunk<'a>>) -> Self {
         let mut left_offset = 0;
         let mut base_offset = 0;
         let mut right_offset = 0;
@@ -188,6 +201,29 @@ impl<'a> ParsedMerge<'a> {
         }
     }
 
+    /// Resolve all conflicts by keeping their side from the given revision
+    pub(crate) fn keep_side(&self, revision: Revision) -> Self {
+        let mut offset = 0;
+        let chunks = (self.chunks.iter())
+            .filter_map(|chunk| {
+                let contents = match *chunk {
+                    MergedChunk::Resolved { contents, .. } => contents,
+                    MergedChunk::Conflict {
+                        left, base, right, ..
+                    } => match revision {
+                        Revision::Base => base.unwrap_or_default(),
+                        Revision::Left => left.unwrap_or_default(),
+                        Revision::Right => right.unwrap_or_default(),
+                    },
+                };
+                let chunk = MergedChunk::Resolved { offset, contents };
+                offset += contents.len();
+                (!contents.is_empty()).then_some(chunk)
+            })
+            .collect();
+        Self::new(chunks)
+    }
+
     /// Reconstruct the source of a revision based on the merged output.
     ///
     /// Because some changes from both revisions have likely already been
@@ -229,6 +265,95 @@ impl<'a> ParsedMerge<'a> {
         Some(matched_start..matched_start + length)
     }
 
+    /// Find out at which index of the reconstructed revision a byte offset of the merged file
+    /// can be found.
+    ///
+    /// Offsets which lie inside a conflict (or its markers) are moved to the start of the
+    /// conflict if `round_down` is set, and to its end otherwise.
+    pub(crate) fn merged_offset_to_rev_offset(
+        &self,
+        offset: usize,
+        revision: Revision,
+        round_down: bool,
+    ) -> usize {
+        let mut rev_offset = 0;
+        // the offset in the revision at which the conflicts since the last resolved chunk start
+        let mut conflicts_start = None;
+        for chunk in &self.chunks {
+            match chunk {
+                MergedChunk::Resolved {
+                    offset: chunk_offset,
+                    contents,
+                } => {
+                    if offset < *chunk_offset {
+                        break;
+                    } else if offset <= chunk_offset + contents.len() {
+                        return rev_offset + offset - chunk_offset;
+                    }
+                    rev_offset += contents.len();
+                    conflicts_start = None;
+                }
+                MergedChunk::Conflict {
+                    left, base, right, ..
+                } => {
+                    conflicts_start.get_or_insert(rev_offset);
+                    let side = match revision {
+                        Revision::Base => base,
+                        Revision::Left => left,
+                        Revision::Right => right,
+                    };
+                    rev_offset += side.map_or(0, str::len);
+                }
+            }
+        }
+        if round_down {
+            conflicts_start.unwrap_or(rev_offset)
+        } else {
+            rev_offset
+        }
+    }
+
+    /// Split the parsed merge into three: the parts before, inside and after the supplied range
+    /// of the merged file. The bounds of the range are expected not to fall inside conflicts.
+    pub(crate) fn split_at_merged_range(&self, range: Range<usize>) -> [Self; 3] {
+        let mut parts: [Vec<MergedChunk<'a>>; 3] = Default::default();
+        // the end of the last resolved chunk, which is where any following conflict starts
+        let mut position = 0;
+        for chunk in &self.chunks {
+            match *chunk {
+                MergedChunk::Resolved { offset, contents } => {
+                    let end = offset + contents.len();
+                    let bounds = [
+                        offset,
+                        range.start.clamp(offset, end),
+                        range.end.clamp(offset, end),
+                        end,
+                    ];
+                    for (part, bounds) in parts.iter_mut().zip(bounds.windows(2)) {
+                        if bounds[0] < bounds[1] {
+                            part.push(MergedChunk::Resolved {
+                                offset: bounds[0],
+                                contents: &contents[bounds[0] - offset..bounds[1] - offset],
+                            });
+                        }
+                    }
+                    position = end;
+                }
+                MergedChunk::Conflict { .. } => {
+                    let part = if position < range.start {
+                        0
+                    } else if position < range.end {
+                        1
+                    } else {
+                        2
+                    };
+                    parts[part].push(chunk.clone());
+                }
+            }
+        }
+        parts.map(Self::new)
+    }
+
     /// Generate a matching between the ASTs of two revisions generated by this parsed merge,
     /// by matching elements whenever they correspond to the same merged range.
     pub(crate) fn generate_matching<'b>(
@@ -278,61 +403,114 @@ impl<'a> ParsedMerge<'a> {
         };
     }
 
-    /// Render the parsed merge back to a string representation
+    /// Render the parsed merge back to a string representation.
+    ///
+    /// Conflict markers always start a line, as they would not be recognized otherwise,
+    /// and the sides of each conflict are copied as is, keeping their indentation.
     pub(crate) fn render(&self, settings: &DisplaySettings) -> String {
+        let mut conflict_id = 0;
         self.chunks.iter().fold(String::new(), |mut result, chunk| {
             match chunk {
                 MergedChunk::Resolved { contents, .. } => result.push_str(contents),
                 MergedChunk::Conflict {
                     left, base, right, ..
                 } => {
-                    // we check whether all 3 sides of the conflict[^1] used ot end with a newline.
-                    // If any of them didn't, then the conflict should be rendered in a special way:
-                    // - a newline is added to all three sides (even if the particular side used to
-                    //   have a newline already)
-                    // - *no* newline is added after the right marker, i.e. at the end of conflict
-                    //
-                    // [^1]: the ones that weren't empty, anyway
-                    let add_after_right_marker = if let (None, None, None) = (base, left, right) {
-                        unreachable!("wouldn't have been a conflict in the first place")
-                    } else {
-                        left.is_none_or(|l| l.ends_with('\n'))
-                            && base.is_none_or(|b| b.ends_with('\n'))
-                            && right.is_none_or(|r| r.ends_with('\n'))
-                    };
-                    let add_after_lines = !add_after_right_marker;
-
-                    result.push_str(&settings.left_marker_or_default());
-                    result.push('\n');
-                    result.push_str(left.unwrap_or_default());
-                    if add_after_lines {
+                    conflict_id += 1;
+                    // conflict markers are only recognized at the start of a line
+                    if !result.is_empty() && !result.ends_with('\n') {
                         result.push('\n');
                     }
-
-                    if settings.diff3 {
-                        result.push_str(&settings.base_marker_or_default());
+                    if let Some(comment) = settings.conflict_comment(conflict_id) {
+                        result.push_str(&comment);
                         result.push('\n');
-                        result.push_str(base.unwrap_or_default());
-                        if add_after_lines {
-                            result.push('\n');
-                        }
                     }
+                    Self::render_conflict(&mut result, *left, *base, *right, settings);
+                }
+            }
+            result
+        })
+    }
 
-                    result.push_str(&settings.middle_marker_or_default());
-                    result.push('\n');
+    /// Render a single conflict, with its markers, at the end of the supplied string
+    fn render_conflict(
+        result: &mut String,
+        left: Option<&str>,
+        base: Option<&str>,
+        right: Option<&str>,
+        settings: &DisplaySettings,
+    ) {
+        // we check whether all 3 sides of the conflict[^1] used ot end with a newline.
+        // If any of them didn't, then the conflict should be rendered in a special way:
+        // - a newline is added to all three sides (even if the particular side used to
+        //   have a newline already)
+        // - *no* newline is added after the right marker, i.e. at the end of conflict
+        //
+        // [^1]: the ones that weren't empty, anyway
+        let add_after_right_marker = if let (None, None, None) = (base, left, right) {
+            unreachable!("wouldn't have been a conflict in the first place")
+        } else {
+            left.is_none_or(|l| l.ends_with('\n'))
+                && base.is_none_or(|b| b.ends_with('\n'))
+                && right.is_none_or(|r| r.ends_with('\n'))
+        };
+        let add_after_lines = !add_after_right_marker;
 
-                    result.push_str(right.unwrap_or_default());
-                    if add_after_lines {
-                        result.push('\n');
-                    }
-                    result.push_str(&settings.right_marker_or_default());
-                    if add_after_right_marker {
-                        result.push('\n');
+        result.push_str(&settings.left_marker_or_default());
+        result.push('\n');
+        result.push_str(left.unwrap_or_default());
+        if add_after_lines {
+            result.push('\n');
+        }
+
+        if settings.show_base() {
+            result.push_str(&settings.base_marker_or_default());
+            result.push('\n');
+            result.push_str(base.unwrap_or_default());
+            if add_after_lines {
+                result.push('\n');
+            }
+        }
+
+        result.push_str(&settings.middle_marker_or_default());
+        result.push('\n');
+
+        result.push_str(right.unwrap_or_default());
+        if add_after_lines {
+            result.push('\n');
+        }
+        result.push_str(&settings.right_marker_or_default());
+        if add_after_right_marker {
+            result.push('\n');
+        }
+    }
+
+    /// Separate the conflicts from the cleanly merged parts, in the spirit of the `.rej` files
+    /// produced by `patch`.
+    ///
+    /// Returns two strings:
+    /// - the merged contents, where each conflict is replaced by its left side
+    /// - the contents of the reject file, listing each conflict (with its markers) preceded by
+    ///   a header indicating the line of the merged contents at which its left side was kept
+    pub(crate) fn split_rejects(&self, settings: &DisplaySettings) -> (String, String) {
+        let mut merged = String::new();
+        let mut rejects = String::new();
+        for chunk in &self.chunks {
+            match chunk {
+                MergedChunk::Resolved { contents, .. } => merged.push_str(contents),
+                MergedChunk::Conflict {
+                    left, base, right, ..
+                } => {
+                    let line = merged.matches('\n').count() + 1;
+                    let _ = writeln!(rejects, "@@ line {line} @@");
+                    Self::render_conflict(&mut rejects, *left, *base, *right, settings);
+                    if !rejects.ends_with('\n') {
+                        rejects.push('\n');
                     }
+                    merged.push_str(left.unwrap_or_default());
                 }
             }
-            result
-        })
+        }
+        (merged, rejects)
     }
 
     /// If the parsed merge contains no conflicts, "render" it by concatenating all the chunks.
@@ -419,15 +597,14 @@ impl<'a> ParsedMerge<'a> {
             // the line-based merge might have come from a non-syntax-aware tool,
             // and we cautiously assume that it does have issues
             has_additional_issues: true,
+            line_based_conflict_mass: self.conflict_mass(),
         }
     }
 
     /// Attempt to extract OIDs from the first conflict's marker names (left, base, right).
     /// Returns (left_oid, base_oid, right_oid) if all are present and look like OIDs.
-    pub(crate) fn extract_conflict_oids(&self) -> Option<(&str, &str, &str)> {
-        fn is_oid(s: &&str) -> bool {
-            s.len() == 40 && s.chars().all(|c| c.is_ascii_hexdigit())
-        }
+    pub fn extract_conflict_oids(&self) -> Option<(&str, &str, &str)> {
+        let is_oid = |name: &&str| git::is_oid(name);
         self.chunks.iter().find_map(|chunk| {
             if let MergedChunk::Conflict {
                 base_name,
@@ -449,6 +626,19 @@ impl<'a> ParsedMerge<'a> {
     }
 }
 
+/// Whether the text contains a line which is a conflict marker of exactly the given size.
+///
+/// Separators (`=======`) are not considered, as they are also used to underline headings
+/// (in RST or Markdown for instance), and the conflict can still be parsed unambiguously.
+fn contains_conflict_marker(text: &str, marker_size: usize) -> bool {
+    text.lines().any(|line| {
+        ['<', '|', '>'].into_iter().any(|marker| {
+            line.strip_prefix(&marker.to_string().repeat(marker_size))
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+        })
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use std::borrow::Cow;
@@ -681,6 +871,87 @@ my_struct_t instance = {
             assert_eq!(parse_err, PARSED_MERGE_DIFF2_DETECTED);
         }
 
+        #[test]
+        fn nested_conflicts() {
+            let source = "\
+let x = [
+<<<<<<<<< OUTER_LEFT
+<<<<<<< INNER_LEFT
+    1,
+||||||| INNER_BASE
+=======
+    2,
+>>>>>>> INNER_RIGHT
+|||||||||
+=========
+    3,
+>>>>>>>>> OUTER_RIGHT
+];
+";
+
+            let same_size_source = "\
+let x = [
+<<<<<<< OUTER_LEFT
+<<<<<<< INNER_LEFT
+    1,
+||||||| INNER_BASE
+=======
+    2,
+>>>>>>> INNER_RIGHT
+|||||||
+=======
+    3,
+>>>>>>> OUTER_RIGHT
+];
+";
+            let parse_err = ParsedMerge::parse(same_size_source, &DisplaySettings::default())
+                .expect_err("expected a parse failure for nested conflicts");
+            assert!(
+                parse_err.contains("nested conflicts"),
+                "unexpected error: {parse_err}"
+            );
+
+            // with a larger marker size for the outer conflict, the inner markers are content
+            let parsed = ParsedMerge::parse(source, &DisplaySettings::from_conflict_marker_size(9))
+                .expect("could not parse nested conflicts with a larger outer marker size");
+            assert_eq!(parsed.conflict_count(), 1);
+            assert_eq!(
+                parsed.reconstruct_revision(Revision::Left),
+                "let x = [\n<<<<<<< INNER_LEFT\n    1,\n||||||| INNER_BASE\n=======\n    2,\n>>>>>>> INNER_RIGHT\n];\n"
+            );
+            assert_eq!(
+                parsed.reconstruct_revision(Revision::Right),
+                "let x = [\n    3,\n];\n"
+            );
+        }
+
+        #[test]
+        fn heading_underline_in_a_conflict_side() {
+            let source = r#"<<<<<<< LEFT
+import os
+import sys
+||||||| BASE
+import os
+=======
+"""
+Helpers
+=======
+"""
+import os
+import re
+>>>>>>> RIGHT
+"#;
+
+            let parsed = ParsedMerge::parse(source, &DisplaySettings::default())
+                .expect("a lone separator in a side is not a nested conflict");
+            assert_eq!(parsed.conflict_count(), 1);
+            assert_eq!(parsed.reconstruct_revision(Revision::Base), "import os\n");
+            assert_eq!(
+                parsed.reconstruct_revision(Revision::Right),
+                "\"\"\"\nHelpers\n=======\n\"\"\"\nimport os\nimport re\n"
+            );
+        }
+
         #[test]
         fn non_standard_conflict_marker_size() {
             let parsed_expected = ParsedMerge::new(vec![
@@ -1143,6 +1414,165 @@ my_struct_t instance = {
         assert_eq!(rendered, source);
     }
 
+    #[test]
+    fn render_preserves_indentation_of_conflicts() {
+        let source = "\
+fn main() {
+    let x = 1;
+<<<<<<< LEFT
+    if x > 0 {
+\tprintln!(\"left\");
+    }
+||||||| BASE
+    println!(\"base\");
+=======
+      println!(\"right\");  
+>>>>>>> RIGHT
+}
+";
+        let parsed = parse(source);
+        let mut settings = DisplaySettings::default();
+        settings.add_revision_names(&parsed);
+
+        let rendered = parsed.render(&settings);
+
+        assert_eq!(rendered, source);
+        for line in rendered.lines() {
+            if let Some(marker) = ["<<<<<<<", "|||||||", "=======", ">>>>>>>"]
+                .into_iter()
+                .find(|marker| line.trim_start().starts_with(marker))
+            {
+                assert!(line.starts_with(marker), "marker not at column 0: {line:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn render_puts_markers_at_start_of_line() {
+        let parsed = ParsedMerge::new(vec![
+            MergedChunk::Resolved {
+                offset: 0,
+                contents: "    let x = ",
+            },
+            MergedChunk::Conflict {
+                left: Some("    1;\n"),
+                base: Some("    0;\n"),
+                right: Some("    2;\n"),
+                left_name: None,
+                base_name: None,
+                right_name: None,
+            },
+        ]);
+
+        let rendered = parsed.render(&DisplaySettings::default());
+
+        assert_eq!(
+            rendered,
+            "    let x = \n<<<<<<< LEFT\n    1;\n||||||| BASE\n    0;\n=======\n    2;\n>>>>>>> RIGHT\n"
+        );
+    }
+
+    #[test]
+    fn split_rejects() {
+        let source = "\
+first line
+<<<<<<< LEFT
+left side
+||||||| BASE
+base side
+=======
+right side
+>>>>>>> RIGHT
+middle line
+<<<<<<< LEFT
+||||||| BASE
+removed
+=======
+changed
+>>>>>>> RIGHT
+last line
+";
+        let mut settings = DisplaySettings::default();
+        let parsed = parse(source);
+        settings.add_revision_names(&parsed);
+
+        let (merged, rejects) = parsed.split_rejects(&settings);
+
+        assert_eq!(merged, "first line\nleft side\nmiddle line\nlast line\n");
+        assert_eq!(
+            rejects,
+            "\
+@@ line 2 @@
+<<<<<<< LEFT
+left side
+||||||| BASE
+base side
+=======
+right side
+>>>>>>> RIGHT
+@@ line 4 @@
+<<<<<<< LEFT
+||||||| BASE
+removed
+=======
+changed
+>>>>>>> RIGHT
+"
+        );
+    }
+
+    #[test]
+    fn split_at_merged_range() {
+        let source = "\
+first line
+<<<<<<< LEFT
+left side
+||||||| BASE
+base side
+=======
+right side
+>>>>>>> RIGHT
+middle line
+<<<<<<< LEFT
+||||||| BASE
+removed
+=======
+changed
+>>>>>>> RIGHT
+last line
+";
+        let parsed = parse(source);
+        let settings = DisplaySettings::default();
+
+        // offsets inside conflicts are moved to their bounds
+        let in_first_conflict = source.find("left side").unwrap();
+        let middle_line = source.find("middle line").unwrap();
+        assert_eq!(
+            parsed.merged_offset_to_rev_offset(in_first_conflict, Revision::Left, true),
+            "first line\n".len()
+        );
+        assert_eq!(
+            parsed.merged_offset_to_rev_offset(in_first_conflict, Revision::Base, false),
+            "first line\nbase side\n".len()
+        );
+        assert_eq!(
+            parsed.merged_offset_to_rev_offset(middle_line, Revision::Right, true),
+            "first line\nright side\n".len()
+        );
+
+        // split around the first conflict and the middle line
+        let [before, inside, after] = parsed
+            .split_at_merged_range("first line\n".len()..source.find("<<<<<<< LEFT\n||").unwrap());
+        assert_eq!(before.render(&settings), "first line\n");
+        assert_eq!(
+            inside.reconstruct_revision(Revision::Right),
+            "right side\nmiddle line\n"
+        );
+        assert_eq!(inside.conflict_count(), 1);
+        assert_eq!(after.conflict_count(), 1);
+        assert_eq!(after.reconstruct_revision(Revision::Left), "last line\n");
+    }
+
     mod matching {
         use super::*;
 
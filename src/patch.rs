@@ -0,0 +1,704 @@
+//! Merging of patch files (`.patch`, `.diff`) in the unified diff format, hunk by hunk.
+//!
+//! A line-based merge of such files tends to report conflicts between unrelated hunks
+//! added at the same place of the patch, or to interleave them in invalid ways. Instead,
+//! hunks are identified by the file they patch and the range of lines they replace in it.
+//! Hunks patching different files or disjoint ranges of lines are independent and
+//! merged commutatively, while overlapping hunks added on both sides conflict.
+
+use std::{borrow::Cow, path::Path};
+
+use itertools::Itertools;
+
+use crate::{
+    MergeResult,
+    line_based::line_based_merge_parsed,
+    parsed_merge::{MergedChunk, ParsedMerge},
+    settings::DisplaySettings,
+};
+
+pub(crate) const PATCH_METHOD: &str = "patch_hunks";
+
+/// Whether the file at this path is a patch, to be merged hunk by hunk
+pub(crate) fn is_patch_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("patch") || ext.eq_ignore_ascii_case("diff"))
+}
+
+/// Merge three revisions of a patch file hunk by hunk.
+///
+/// Returns `None` if any of the revisions can't be parsed as a unified diff,
+/// or if it patches the same file more than once.
+pub(crate) fn merge_patches(
+    contents_base: &str,
+    contents_left: &str,
+    contents_right: &str,
+    settings: &DisplaySettings,
+) -> Option<MergeResult> {
+    let base = Patch::parse(contents_base)?;
+    let left = Patch::parse(contents_left)?;
+    let right = Patch::parse(contents_right)?;
+    // a series of patches (such as the output of `git format-patch --stdout` for several commits)
+    // can change the same file in different sections, which can't be told apart by their key
+    if [&base, &left, &right]
+        .iter()
+        .any(|patch| !patch.files.iter().map(|file| file.key).all_unique())
+    {
+        return None;
+    }
+
+    // the preamble (such as the commit message of `git format-patch`) is free text
+    let preamble = line_based_merge_parsed(base.preamble, left.preamble, right.preamble, settings);
+    let mut pieces: Vec<Piece> = (preamble.chunks.into_iter())
+        .map(|chunk| match chunk {
+            MergedChunk::Resolved { contents, .. } => Piece::Resolved(Cow::Borrowed(contents)),
+            MergedChunk::Conflict {
+                left, base, right, ..
+            } => Piece::Conflict {
+                left: left.map(Cow::Borrowed),
+                base: base.map(Cow::Borrowed),
+                right: right.map(Cow::Borrowed),
+            },
+        })
+        .collect();
+
+    let file_keys = merged_order(&[
+        &left.files.iter().map(|file| file.key).collect::<Vec<_>>(),
+        &right.files.iter().map(|file| file.key).collect::<Vec<_>>(),
+        &base.files.iter().map(|file| file.key).collect::<Vec<_>>(),
+    ]);
+    for key in file_keys {
+        merge_files(base.file(key), left.file(key), right.file(key), &mut pieces);
+    }
+
+    let mut offset = 0;
+    let chunks = (pieces.iter())
+        .map(|piece| match piece {
+            Piece::Resolved(contents) => {
+                let chunk = MergedChunk::Resolved {
+                    offset,
+                    contents: contents.as_ref(),
+                };
+                offset += contents.len();
+                chunk
+            }
+            Piece::Conflict { left, base, right } => MergedChunk::Conflict {
+                left: left.as_deref(),
+                base: base.as_deref(),
+                right: right.as_deref(),
+                left_name: None,
+                base_name: None,
+                right_name: None,
+            },
+        })
+        .collect();
+    let merge_result = ParsedMerge::new(chunks).into_merge_result(settings);
+    Some(MergeResult {
+        method: PATCH_METHOD,
+        has_additional_issues: false,
+        ..merge_result
+    })
+}
+
+/// A parsed patch file
+struct Patch<'a> {
+    /// Anything before the first patched file, such as a commit message
+    preamble: &'a str,
+    /// The changes to each file
+    files: Vec<FilePatch<'a>>,
+}
+
+/// The changes made by a patch to a single file
+struct FilePatch<'a> {
+    /// Identifies the patched file across revisions: the `diff` line if any,
+    /// otherwise the `+++` line (without any timestamp)
+    key: &'a str,
+    /// The lines before the first hunk, such as `diff --git`, `index`, `---` and `+++`
+    header: &'a str,
+    /// The hunks, in the order of the patch
+    hunks: Vec<Hunk<'a>>,
+    /// The entire source of the changes to this file
+    source: &'a str,
+}
+
+/// A hunk, starting with a `@@ -a,b +c,d @@` line
+struct Hunk<'a> {
+    /// The first line replaced by this hunk in the original file
+    old_start: usize,
+    /// The number of lines replaced by this hunk in the original file
+    old_len: usize,
+    /// The first line of this hunk in the patched file
+    new_start: usize,
+    /// The number of lines of this hunk in the patched file
+    new_len: usize,
+    /// The net number of lines added by the previous hunks of the same file
+    shift: isize,
+    /// The `a,b` range of the header, as written
+    old_range: &'a str,
+    /// Whether the `c,d` range of the header has an explicit length
+    explicit_new_len: bool,
+    /// The rest of the header line after the ranges, such as the enclosing function
+    header_rest: &'a str,
+    /// The lines after the header
+    body: &'a str,
+    /// The entire source of the hunk
+    source: &'a str,
+}
+
+impl<'a> Patch<'a> {
+    /// Parse a patch file, returning `None` if it doesn't look like a unified diff
+    fn parse(source: &'a str) -> Option<Self> {
+        let lines: Vec<&str> = source.split_inclusive('\n').collect();
+        let offsets: Vec<usize> = (lines.iter())
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len();
+                Some(start)
+            })
+            .chain([source.len()])
+            .collect();
+        // whether a new file patch starts at this line
+        let starts_file = |i: usize| {
+            lines[i].starts_with("diff ")
+                || (lines[i].starts_with("--- ")
+                    && lines
+                        .get(i + 1)
+                        .is_some_and(|next| next.starts_with("+++ ")))
+        };
+
+        let mut i = 0;
+        while i < lines.len() && !starts_file(i) {
+            i += 1;
+        }
+        let preamble = &source[..offsets[i]];
+
+        let mut files = Vec::new();
+        while i < lines.len() {
+            let file_start = i;
+            let mut diff_line = None;
+            let mut plus_line = None;
+            while i < lines.len() && !lines[i].starts_with("@@ -") {
+                let line = lines[i];
+                if i > file_start
+                    && (line.starts_with("diff ") || (plus_line.is_some() && starts_file(i)))
+                {
+                    // a file patch without hunks, such as a mode change
+                    break;
+                }
+                if line.starts_with("diff ") {
+                    diff_line = Some(line.trim_end());
+                } else if line.starts_with("+++ ") {
+                    plus_line = line.trim_end().split('\t').next();
+                }
+                i += 1;
+            }
+            let key = diff_line.or(plus_line)?;
+            let header = &source[offsets[file_start]..offsets[i]];
+
+            let mut hunks = Vec::new();
+            let mut shift = 0;
+            while i < lines.len() && lines[i].starts_with("@@ -") {
+                let hunk_start = i;
+                let (ranges, header_rest) = lines[i]["@@ -".len()..].split_once(" @@")?;
+                let (old_range, new_range) = ranges.split_once(" +")?;
+                let (old_start, old_len) = parse_range(old_range)?;
+                let (new_start, new_len) = parse_range(new_range)?;
+
+                i += 1;
+                let (mut old_remaining, mut new_remaining) = (old_len, new_len);
+                while old_remaining > 0 || new_remaining > 0 {
+                    match lines.get(i)?.as_bytes()[0] {
+                        // some tools strip the trailing space of empty context lines
+                        b' ' | b'\n' | b'\r' => {
+                            old_remaining = old_remaining.checked_sub(1)?;
+                            new_remaining = new_remaining.checked_sub(1)?;
+                        }
+                        b'-' => old_remaining = old_remaining.checked_sub(1)?,
+                        b'+' => new_remaining = new_remaining.checked_sub(1)?,
+                        b'\\' => {}
+                        _ => return None,
+                    }
+                    i += 1;
+                }
+                // "\ No newline at end of file" and any trailing text, such as the
+                // signature of `git format-patch` after the last hunk
+                while i < lines.len() && !lines[i].starts_with("@@ -") && !starts_file(i) {
+                    i += 1;
+                }
+
+                hunks.push(Hunk {
+                    old_start,
+                    old_len,
+                    new_start,
+                    new_len,
+                    shift,
+                    old_range,
+                    explicit_new_len: new_range.contains(','),
+                    header_rest,
+                    body: &source[offsets[hunk_start + 1]..offsets[i]],
+                    source: &source[offsets[hunk_start]..offsets[i]],
+                });
+                shift += new_len as isize - old_len as isize;
+            }
+
+            files.push(FilePatch {
+                key,
+                header,
+                hunks,
+                source: &source[offsets[file_start]..offsets[i]],
+            });
+        }
+
+        (!files.is_empty()).then_some(Self { preamble, files })
+    }
+
+    /// The changes to the file with the given key, if any
+    fn file(&'a self, key: &str) -> Option<&'a FilePatch<'a>> {
+        self.files.iter().find(|file| file.key == key)
+    }
+}
+
+/// Parse a `start,len` range of a hunk header, where the length defaults to 1.
+/// Ranges whose end doesn't fit in a `usize` are rejected.
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let (start, len): (usize, usize) = match range.split_once(',') {
+        Some((start, len)) => (start.parse().ok()?, len.parse().ok()?),
+        None => (range.parse().ok()?, 1),
+    };
+    start.checked_add(len.max(1))?;
+    Some((start, len))
+}
+
+impl Hunk<'_> {
+    /// Whether two hunks make the same change, regardless of the shift
+    /// caused by the previous hunks
+    fn same_change(&self, other: &Self) -> bool {
+        self.old_start == other.old_start
+            && self.old_len == other.old_len
+            && self.new_len == other.new_len
+            && self.body == other.body
+    }
+
+    /// The source of this hunk, once the previous hunks of the same file
+    /// add `shift` lines in total
+    fn render(&self, shift: isize) -> Cow<'_, str> {
+        if shift == self.shift {
+            return Cow::Borrowed(self.source);
+        }
+        let new_start = self.new_start.saturating_add_signed(shift - self.shift);
+        let new_range = if self.explicit_new_len {
+            format!("{new_start},{}", self.new_len)
+        } else {
+            new_start.to_string()
+        };
+        Cow::Owned(format!(
+            "@@ -{} +{new_range} @@{}{}",
+            self.old_range, self.header_rest, self.body
+        ))
+    }
+}
+
+/// A part of the merged patch
+enum Piece<'a> {
+    Resolved(Cow<'a, str>),
+    Conflict {
+        left: Option<Cow<'a, str>>,
+        base: Option<Cow<'a, str>>,
+        right: Option<Cow<'a, str>>,
+    },
+}
+
+/// The revisions a merged element was taken from
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Origin {
+    /// Both sides agree on it
+    Both,
+    Left,
+    Right,
+}
+
+/// Three-way merge of an element, which may be absent from some revisions.
+/// Returns the merged element and where it comes from, or `None` in case of conflict.
+fn merge_element<T: Copy>(
+    base: Option<T>,
+    left: Option<T>,
+    right: Option<T>,
+    same: impl Fn(T, T) -> bool,
+) -> Option<(Option<T>, Origin)> {
+    let same = |a: Option<T>, b: Option<T>| match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => same(a, b),
+        _ => false,
+    };
+    if same(left, right) {
+        Some((left, Origin::Both))
+    } else if same(base, left) {
+        Some((right, Origin::Right))
+    } else if same(base, right) {
+        Some((left, Origin::Left))
+    } else {
+        None
+    }
+}
+
+/// Merge the changes made to a file in the three revisions
+fn merge_files<'a>(
+    base: Option<&'a FilePatch<'a>>,
+    left: Option<&'a FilePatch<'a>>,
+    right: Option<&'a FilePatch<'a>>,
+    pieces: &mut Vec<Piece<'a>>,
+) {
+    let (Some(left), Some(right)) = (left, right) else {
+        // added, deleted or kept on one side only
+        match merge_element(base, left, right, |a, b| a.source == b.source) {
+            Some((merged, _)) => {
+                pieces.extend(merged.map(|file| Piece::Resolved(Cow::Borrowed(file.source))));
+            }
+            None => pieces.push(Piece::Conflict {
+                left: left.map(|file| Cow::Borrowed(file.source)),
+                base: base.map(|file| Cow::Borrowed(file.source)),
+                right: right.map(|file| Cow::Borrowed(file.source)),
+            }),
+        }
+        return;
+    };
+
+    let base_header = base.map(|file| file.header);
+    match merge_element(
+        base_header,
+        Some(left.header),
+        Some(right.header),
+        |a, b| a == b,
+    ) {
+        Some((header, _)) => pieces.extend(header.map(|h| Piece::Resolved(Cow::Borrowed(h)))),
+        // the blob ids of the `index` lines can't be right for the merged file anyway
+        None if without_index_line(left.header) == without_index_line(right.header) => {
+            pieces.push(Piece::Resolved(Cow::Owned(without_index_line(left.header))));
+        }
+        None => pieces.push(Piece::Conflict {
+            left: Some(Cow::Borrowed(left.header)),
+            base: base_header.map(Cow::Borrowed),
+            right: Some(Cow::Borrowed(right.header)),
+        }),
+    }
+
+    let base_hunks = base.map_or(&[][..], |file| &file.hunks);
+    let mut hunk_keys: Vec<(usize, usize)> = (base_hunks.iter())
+        .chain(&left.hunks)
+        .chain(&right.hunks)
+        .map(|hunk| (hunk.old_start, hunk.old_len))
+        .collect();
+    hunk_keys.sort_unstable();
+    hunk_keys.dedup();
+    let find = |hunks: &'a [Hunk<'a>], (old_start, old_len)| {
+        hunks
+            .iter()
+            .find(|hunk| hunk.old_start == old_start && hunk.old_len == old_len)
+    };
+
+    // group the hunks whose ranges overlap, as they can only be merged if they come from the same side
+    let mut groups: Vec<Vec<(usize, usize)>> = Vec::new();
+    let mut group_end = 0;
+    for key in hunk_keys {
+        let (start, end) = (key.0, key.0 + key.1.max(1));
+        match groups.last_mut() {
+            Some(group) if start < group_end => {
+                group.push(key);
+                group_end = group_end.max(end);
+            }
+            _ => {
+                groups.push(vec![key]);
+                group_end = end;
+            }
+        }
+    }
+
+    let mut shift = 0;
+    for group in groups {
+        let merged: Option<Vec<(Option<&Hunk>, Origin)>> = (group.iter())
+            .map(|key| {
+                merge_element(
+                    find(base_hunks, *key),
+                    find(&left.hunks, *key),
+                    find(&right.hunks, *key),
+                    Hunk::same_change,
+                )
+            })
+            .collect();
+        let merged = merged.filter(|merged| {
+            let from = |origin| merged.iter().any(|(_, o)| *o == origin);
+            !(from(Origin::Left) && from(Origin::Right))
+        });
+        if let Some(merged) = merged {
+            for hunk in merged.into_iter().filter_map(|(hunk, _)| hunk) {
+                pieces.push(Piece::Resolved(hunk.render(shift)));
+                shift += hunk.new_len as isize - hunk.old_len as isize;
+            }
+        } else {
+            let side = |hunks: &'a [Hunk<'a>]| {
+                let source: String = (group.iter())
+                    .filter_map(|key| find(hunks, *key))
+                    .map(|hunk| hunk.source)
+                    .collect();
+                (!source.is_empty()).then_some(Cow::Owned(source))
+            };
+            shift += (group.iter())
+                .filter_map(|key| find(&left.hunks, *key))
+                .map(|hunk| hunk.new_len as isize - hunk.old_len as isize)
+                .sum::<isize>();
+            pieces.push(Piece::Conflict {
+                left: side(&left.hunks),
+                base: side(base_hunks),
+                right: side(&right.hunks),
+            });
+        }
+    }
+}
+
+/// The header of a file patch, without its `index` line
+fn without_index_line(header: &str) -> String {
+    (header.split_inclusive('\n'))
+        .filter(|line| !line.starts_with("index "))
+        .collect()
+}
+
+/// The union of sequences of keys, preserving their order as much as possible
+fn merged_order<'a>(sequences: &[&[&'a str]]) -> Vec<&'a str> {
+    let mut result: Vec<&str> = Vec::new();
+    for sequence in sequences {
+        let mut position = 0;
+        for key in *sequence {
+            if let Some(existing) = result.iter().position(|k| k == key) {
+                position = existing + 1;
+            } else {
+                // keys added by the previous sequences at the same place come first
+                while result
+                    .get(position)
+                    .is_some_and(|other| !sequence.contains(other))
+                {
+                    position += 1;
+                }
+                result.insert(position, key);
+                position += 1;
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge(base: &str, left: &str, right: &str) -> MergeResult {
+        merge_patches(base, left, right, &DisplaySettings::default()).expect("invalid patch")
+    }
+
+    const FOO: &str = "\
+diff --git a/foo.txt b/foo.txt
+index 1111111..2222222 100644
+--- a/foo.txt
++++ b/foo.txt
+@@ -1,3 +1,3 @@
+ a
+-b
++B
+ c
+";
+
+    const BAR: &str = "\
+diff --git a/bar.txt b/bar.txt
+index 3333333..4444444 100644
+--- a/bar.txt
++++ b/bar.txt
+@@ -10,2 +10,3 @@ fn bar() {
+ x
++y
+ z
+";
+
+    const BAZ: &str = "\
+diff --git a/baz.txt b/baz.txt
+index 5555555..6666666 100644
+--- a/baz.txt
++++ b/baz.txt
+@@ -1 +1 @@
+-old
++new
+";
+
+    #[test]
+    fn hunks_to_different_files() {
+        let base = format!("Subject: [PATCH] Update\n\n{FOO}");
+        let left = format!("Subject: [PATCH] Update\n\n{FOO}{BAR}");
+        let right = format!("Subject: [PATCH] Update\n\n{FOO}{BAZ}");
+
+        let result = merge(&base, &left, &right);
+        assert_eq!(result.conflict_count, 0);
+        assert_eq!(result.method, PATCH_METHOD);
+        assert_eq!(
+            result.contents,
+            format!("Subject: [PATCH] Update\n\n{FOO}{BAR}{BAZ}")
+        );
+    }
+
+    #[test]
+    fn hunks_to_different_ranges_of_the_same_file() {
+        let base = "\
+--- a/foo.txt
++++ b/foo.txt
+@@ -10,2 +10,2 @@
+ x
+-y
++Y
+";
+        let left = "\
+--- a/foo.txt
++++ b/foo.txt
+@@ -1,2 +1,3 @@
+ a
++b
+ c
+@@ -10,2 +11,2 @@
+ x
+-y
++Y
+";
+        let right = "\
+--- a/foo.txt
++++ b/foo.txt
+@@ -10,2 +10,2 @@
+ x
+-y
++Y
+@@ -20,2 +20,1 @@
+ p
+-q
+";
+        // the new line numbers of the last hunk take the hunk added on the left into account
+        let expected = "\
+--- a/foo.txt
++++ b/foo.txt
+@@ -1,2 +1,3 @@
+ a
++b
+ c
+@@ -10,2 +11,2 @@
+ x
+-y
++Y
+@@ -20,2 +21,1 @@
+ p
+-q
+";
+        let result = merge(base, left, right);
+        assert_eq!(result.conflict_count, 0);
+        assert_eq!(result.contents, expected);
+    }
+
+    #[test]
+    fn overlapping_hunks_conflict() {
+        let base = "\
+--- a/foo.txt
++++ b/foo.txt
+";
+        let left = "\
+--- a/foo.txt
++++ b/foo.txt
+@@ -1,3 +1,3 @@
+ a
+-b
++left
+ c
+";
+        let right = "\
+--- a/foo.txt
++++ b/foo.txt
+@@ -2,2 +2,2 @@
+ b
+-c
++right
+";
+        let result = merge(base, left, right);
+        assert_eq!(result.conflict_count, 1);
+        assert_eq!(
+            result.contents,
+            "\
+--- a/foo.txt
++++ b/foo.txt
+<<<<<<< LEFT
+@@ -1,3 +1,3 @@
+ a
+-b
++left
+ c
+||||||| BASE
+=======
+@@ -2,2 +2,2 @@
+ b
+-c
++right
+>>>>>>> RIGHT
+"
+        );
+    }
+
+    #[test]
+    fn index_lines_are_dropped_when_both_sides_change_them() {
+        let base = "\
+diff --git a/foo.txt b/foo.txt
+index 1111111..2222222 100644
+--- a/foo.txt
++++ b/foo.txt
+@@ -1 +1 @@
+-a
++b
+";
+        let left = format!(
+            "{}@@ -10 +10 @@\n-x\n+y\n",
+            base.replace("2222222", "3333333")
+        );
+        let right = format!(
+            "{}@@ -20 +20 @@\n-p\n+q\n",
+            base.replace("2222222", "4444444")
+        );
+
+        let result = merge(base, &left, &right);
+        assert_eq!(result.conflict_count, 0);
+        assert_eq!(
+            result.contents,
+            "\
+diff --git a/foo.txt b/foo.txt
+--- a/foo.txt
++++ b/foo.txt
+@@ -1 +1 @@
+-a
++b
+@@ -10 +10 @@
+-x
++y
+@@ -20 +20 @@
+-p
++q
+"
+        );
+    }
+
+    #[test]
+    fn not_a_patch() {
+        assert!(Patch::parse("hello\nworld\n").is_none());
+        assert!(Patch::parse("--- a/foo\n+++ b/foo\n@@ -1,2 +1,2 @@\n a\n").is_none());
+        // the end of the range overflows
+        assert!(
+            Patch::parse("--- a/foo\n+++ b/foo\n@@ -18446744073709551615,1 +1,1 @@\n-a\n+b\n")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn patch_file_extensions() {
+        assert!(is_patch_file(Path::new("fix.patch")));
+        assert!(is_patch_file(Path::new("FIX.DIFF")));
+        assert!(!is_patch_file(Path::new("patch")));
+    }
+}
@@ -1,6 +1,7 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, str::FromStr};
 
 use regex::Regex;
+use rustc_hash::FxHashSet;
 
 use crate::parsed_merge::{MergedChunk, ParsedMerge};
 
@@ -50,11 +51,23 @@ pub struct ConflictRegexes {
     pub diff3_no_newline: Regex,
 }
 
+impl ConflictRegexes {
+    /// Whether the supplied contents contain any conflict, in any of the recognized formats
+    pub fn is_match(&self, contents: &str) -> bool {
+        self.diff3.is_match(contents)
+            || self.diff2.is_match(contents)
+            || self.diff3_no_newline.is_match(contents)
+    }
+}
+
 #[derive(Clone, Debug, derive_more::PartialEq, derive_more::Eq)]
 /// Parameters controlling how the merged tree should be output.
 pub struct DisplaySettings<'a> {
     /// Whether to show the base revision in the conflicts (true by default)
     pub diff3: bool,
+    /// Whether to always show the base revision in the conflicts, overriding `diff3`.
+    /// Unlike `diff3`, this is also honored when falling back on `git merge-file`.
+    pub force_diff3: bool,
     /// Whether to show compact conflicts or to expand them to fill an entire line
     pub compact: Option<bool>,
     /// The number of characters for conflict markers (7 by default)
@@ -87,6 +100,12 @@ pub struct DisplaySettings<'a> {
     /// - be present but empty (`>>>>>>>(space)(newline`) -- a very unlikely case which we ignore.
     /// - be present and non-empty (`>>>>>>>(space)(revision name)(newline)`)
     pub right_revision_name: Option<Cow<'a, str>>,
+    /// If set, the delimiters of a comment to add before each conflict, so that tools can
+    /// find them, e.g. `/* mergiraf:conflict id=1 */`
+    pub conflict_comment: Option<(&'static str, &'static str)>,
+    /// If set, the maximum size (in bytes) of the sides of a structured conflict.
+    /// Conflicts exceeding it are replaced by a line-based merge of their parent node.
+    pub max_conflict_size: Option<usize>,
 }
 
 impl<'a> DisplaySettings<'a> {
@@ -106,6 +125,9 @@ impl<'a> DisplaySettings<'a> {
             left_revision_name,
             right_revision_name,
             diff3: true,
+            force_diff3: false,
+            conflict_comment: None,
+            max_conflict_size: None,
         }
     }
 
@@ -114,6 +136,11 @@ impl<'a> DisplaySettings<'a> {
         Self::new(None, Some(size), None, None, None)
     }
 
+    /// Whether the base revision should be shown in the conflicts
+    pub fn show_base(&self) -> bool {
+        self.diff3 || self.force_diff3
+    }
+
     /// The value of `compact` if set, the default value otherwise
     pub fn compact_or_default(&self) -> bool {
         self.compact.unwrap_or(false)
@@ -132,6 +159,33 @@ impl<'a> DisplaySettings<'a> {
         self.conflict_marker_size = Some(new_size);
     }
 
+    /// Increase the conflict marker size if needed, so that the conflict markers we add
+    /// can't be confused with lines of the given contents which look like conflict markers
+    pub fn avoid_conflict_markers_in(&mut self, contents: &[&str]) {
+        let marker_like_sizes: FxHashSet<usize> = (contents.iter())
+            .flat_map(|contents| contents.lines())
+            .filter_map(|line| {
+                let first = line.chars().next()?;
+                if !matches!(first, '<' | '|' | '=' | '>') {
+                    return None;
+                }
+                let size = line.chars().take_while(|c| *c == first).count();
+                matches!(line[size..].chars().next(), None | Some(' ' | '\r')).then_some(size)
+            })
+            .collect();
+        let mut size = self.conflict_marker_size_or_default();
+        while marker_like_sizes.contains(&size) {
+            size += 1;
+        }
+        self.set_conflict_marker_size(size);
+    }
+
+    /// Whether a structured conflict with sides of the given total size (in bytes)
+    /// should rather be rendered with a line-based merge
+    pub fn exceeds_max_conflict_size(&self, size: usize) -> bool {
+        self.max_conflict_size.is_some_and(|max| size > max)
+    }
+
     pub fn conflict_regexes(&self) -> &ConflictRegexes {
         // `debug_assert_eq!` will unfortunately not work here, as it uses merely
         // `if cfg!(debug_assertions)`, which doesn't stop the compilation error
@@ -201,6 +255,18 @@ impl<'a> DisplaySettings<'a> {
         "=".repeat(self.conflict_marker_size_or_default())
     }
 
+    /// The comment to add on the line before the `id`-th conflict (starting at 1), if enabled.
+    /// It does not contain any newline character.
+    pub fn conflict_comment(&self, id: usize) -> Option<String> {
+        self.conflict_comment.map(|(start, end)| {
+            if end.is_empty() {
+                format!("{start} mergiraf:conflict id={id}")
+            } else {
+                format!("{start} mergiraf:conflict id={id} {end}")
+            }
+        })
+    }
+
     pub fn default_compact() -> Self {
         Self {
             compact: Some(true),
@@ -228,6 +294,33 @@ impl<'a> DisplaySettings<'a> {
     }
 }
 
+/// How to merge an element which was deleted on one side and modified on the other.
+///
+/// In both cases, the parent of the element is merged line by line. The strategy determines
+/// what happens to the conflicts of this line-based merge.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeleteModifyStrategy {
+    /// Leave the conflicts for the user to resolve
+    #[default]
+    Conflict,
+    /// Resolve the conflicts by keeping the side which modified the element
+    KeepModified,
+}
+
+impl FromStr for DeleteModifyStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "conflict" => Ok(Self::Conflict),
+            "keep-modified" => Ok(Self::KeepModified),
+            _ => Err(format!(
+                "invalid strategy `{s}`, expected `conflict` or `keep-modified`"
+            )),
+        }
+    }
+}
+
 impl Default for DisplaySettings<'_> {
     fn default() -> Self {
         Self::new(Some(false), None, None, None, None)
@@ -104,6 +104,53 @@ impl<'b> AstNodeEquiv<'_, 'b> {
         }
     }
 
+    /// Unified interface to fetch the source of the first child on either an original tree or a merged one
+    fn first_child_source(&self, class_mapping: &ClassMapping<'b>) -> Option<&'b str> {
+        match self {
+            Self::Original(ast_node) => ast_node.children.first().map(|child| child.source),
+            Self::Merged(tree) => match tree {
+                MergedTree::ExactTree {
+                    node, revisions, ..
+                } => {
+                    let rev = revisions.any();
+                    let representative = class_mapping
+                        .node_at_rev(node, rev)
+                        .expect("Inconsistent class_mapping and ExactTree revisions");
+                    Self::Original(representative).first_child_source(class_mapping)
+                }
+                MergedTree::MixedTree { children, .. } => match children.first() {
+                    Some(MergedTree::ExactTree {
+                        node, revisions, ..
+                    }) => class_mapping
+                        .node_at_rev(node, revisions.any())
+                        .map(|child| child.source),
+                    _ => None,
+                },
+                MergedTree::Conflict { .. }
+                | MergedTree::LineBasedMerge { .. }
+                | MergedTree::CommutativeChildSeparator { .. } => None,
+            },
+        }
+    }
+
+    /// Unified interface to fetch the source of the node on either an original tree or a merged one
+    fn source(&self, class_mapping: &ClassMapping<'b>) -> Option<&'b str> {
+        match self {
+            Self::Original(ast_node) => Some(ast_node.source),
+            Self::Merged(tree) => match tree {
+                MergedTree::ExactTree {
+                    node, revisions, ..
+                } => class_mapping
+                    .node_at_rev(node, revisions.any())
+                    .map(|node| node.source),
+                MergedTree::MixedTree { .. }
+                | MergedTree::Conflict { .. }
+                | MergedTree::LineBasedMerge { .. }
+                | MergedTree::CommutativeChildSeparator { .. } => None,
+            },
+        }
+    }
+
     /// Checks for isomorphism between two [AstNodeEquiv]s
     fn isomorphic(&self, other: &Self, class_mapping: Option<&ClassMapping<'b>>) -> bool {
         match (self, other) {
@@ -260,6 +307,11 @@ pub struct SignatureDefinition {
     pub node_type: &'static str,
     // The list of paths to take into account when extracting the signature
     pub paths: Vec<AstPath>,
+    // The indices of the paths which must lead to at least one descendant for the node to be
+    // identified by the signature
+    pub required_paths: Vec<usize>,
+    // Whether nodes for which none of the paths lead to any descendant are left unidentified
+    pub optional: bool,
 }
 
 /// Helper to ease declaring signatures in `supported_langs.rs`
@@ -267,6 +319,8 @@ pub fn signature(node_type: &'static str, paths: Vec<Vec<PathStep>>) -> Signatur
     SignatureDefinition {
         node_type,
         paths: paths.into_iter().map(|steps| AstPath { steps }).collect(),
+        required_paths: Vec::new(),
+        optional: false,
     }
 }
 
@@ -275,20 +329,41 @@ impl SignatureDefinition {
         signature(node_type, paths)
     }
 
-    /// Extracts a signature for the supplied original node
+    /// Adds a path to the signature, which must be possible to follow for the node to be
+    /// identified by the signature at all. This is useful when only some of the nodes
+    /// of the given type carry a key, such as TOML array-of-tables entries with a `name`.
+    #[must_use]
+    pub fn requiring(mut self, steps: Vec<PathStep>) -> Self {
+        self.required_paths.push(self.paths.len());
+        self.paths.push(AstPath { steps });
+        self
+    }
+
+    /// Leaves the nodes for which none of the paths of the signature can be followed
+    /// unidentified, instead of giving them all the same empty signature. Such nodes
+    /// can't be told apart, so they are not reordered by commutative merging.
+    /// This is useful when the key of the nodes is optional, such as the name of
+    /// GitHub Actions workflow steps.
+    #[must_use]
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    /// Extracts a signature for the supplied original node, if it is identified by this signature
     pub(crate) fn extract_signature_from_original_node<'a, 'b: 'a>(
         &self,
         node: &'b AstNode<'b>,
-    ) -> Signature<'a, 'b> {
+    ) -> Option<Signature<'a, 'b>> {
         self.extract_internal(AstNodeEquiv::Original(node), &ClassMapping::new())
     }
 
-    /// Extracts a signature for the supplied merged node
+    /// Extracts a signature for the supplied merged node, if it is identified by this signature
     pub(crate) fn extract_signature_from_merged_node<'a, 'b: 'a>(
         &self,
         node: &'a MergedTree<'b>,
         class_mapping: &ClassMapping<'b>,
-    ) -> Signature<'a, 'b> {
+    ) -> Option<Signature<'a, 'b>> {
         self.extract_internal(AstNodeEquiv::Merged(node), class_mapping)
     }
 
@@ -297,13 +372,15 @@ impl SignatureDefinition {
         &self,
         node: AstNodeEquiv<'a, 'b>,
         class_mapping: &ClassMapping<'b>,
-    ) -> Signature<'a, 'b> {
-        Signature(
-            self.paths
-                .iter()
-                .map(|path| path.extract(node, class_mapping))
-                .collect(),
-        )
+    ) -> Option<Signature<'a, 'b>> {
+        let extracted: Vec<_> = self
+            .paths
+            .iter()
+            .map(|path| path.extract(node, class_mapping))
+            .collect();
+        let unidentified = (self.required_paths.iter()).any(|&index| extracted[index].is_empty())
+            || (self.optional && extracted.iter().all(Vec::is_empty));
+        (!unidentified).then_some(Signature(extracted))
     }
 
     /// Checks that all names found in this signarute are valid
@@ -347,6 +424,12 @@ pub enum PathStep {
     Field(&'static str),
     /// Fetch all children of a given kind
     ChildKind(&'static str),
+    /// Fetch all children of a given kind whose first child has the given source,
+    /// such as the `pair` whose key is `name` in a TOML table
+    KeyedChild(&'static str, &'static str),
+    /// Fetch all children of a given kind whose source is one of the given ones,
+    /// such as the `identifier` of a `@property` decorator in Python
+    ChildWithSource(&'static str, &'static [&'static str]),
 }
 
 impl AstPath {
@@ -383,6 +466,20 @@ impl AstPath {
                         node.children_by_field_name(field_name, class_mapping)
                     }
                     PathStep::ChildKind(kind) => node.children_by_kind(kind, class_mapping),
+                    PathStep::KeyedChild(kind, key) => node
+                        .children_by_kind(kind, class_mapping)
+                        .into_iter()
+                        .filter(|child| child.first_child_source(class_mapping) == Some(key))
+                        .collect(),
+                    PathStep::ChildWithSource(kind, sources) => node
+                        .children_by_kind(kind, class_mapping)
+                        .into_iter()
+                        .filter(|child| {
+                            child
+                                .source(class_mapping)
+                                .is_some_and(|source| sources.contains(&source))
+                        })
+                        .collect(),
                 };
 
                 for child in children {
@@ -410,7 +507,9 @@ impl AstPath {
                         return Err(format!("invalid field name: {field_name:?}"));
                     }
                 }
-                PathStep::ChildKind(node_name) => {
+                PathStep::ChildKind(node_name)
+                | PathStep::KeyedChild(node_name, _)
+                | PathStep::ChildWithSource(node_name, _) => {
                     if !name_is_valid(node_name) {
                         return Err(format!("invalid child type: {node_name:?}"));
                     }
@@ -432,6 +531,12 @@ impl Display for PathStep {
         match self {
             Self::Field(field_name) => write!(f, "field({field_name})"),
             Self::ChildKind(child_type) => write!(f, "child_type({child_type})"),
+            Self::KeyedChild(child_type, key) => write!(f, "keyed_child({child_type}, {key})"),
+            Self::ChildWithSource(child_type, sources) => write!(
+                f,
+                "child_with_source({child_type}, {})",
+                sources.iter().format(" | ")
+            ),
         }
     }
 }
@@ -463,7 +568,7 @@ mod tests {
             signature("pair", paths)
         };
 
-        let expected_sig = Signature(vec![vec![AstNodeEquiv::Original(key)]]);
+        let expected_sig = Some(Signature(vec![vec![AstNodeEquiv::Original(key)]]));
         assert_eq!(
             signature_def.extract_signature_from_original_node(pair),
             expected_sig
@@ -474,6 +579,97 @@ mod tests {
         );
     }
 
+    #[test]
+    fn keyed_child_signatures() {
+        let ctx = ctx();
+
+        let table = ctx.parse(
+            "a.toml",
+            "[[package]]\nname = \"log\"\nversion = \"0.4.28\"\nchecksum = \"abc\"\n",
+        )[0];
+        let name = table[3];
+        let version = table[4];
+
+        let signature_def = signature(
+            "table_array_element",
+            vec![
+                vec![PathStep::KeyedChild("pair", "name")],
+                vec![PathStep::KeyedChild("pair", "version")],
+            ],
+        );
+
+        assert_eq!(
+            signature_def.extract_signature_from_original_node(table),
+            Some(Signature(vec![
+                vec![AstNodeEquiv::Original(name)],
+                vec![AstNodeEquiv::Original(version)]
+            ]))
+        );
+    }
+
+    #[test]
+    fn child_with_source_signatures() {
+        let ctx = ctx();
+
+        let block = ctx.parse(
+            "a.py",
+            "class A:\n    @x.setter\n    def x(self, v):\n        pass\n\n    @cache\n    def y(self):\n        pass\n",
+        )[0][3];
+        let setter = block[0];
+        let cached = block[1];
+
+        let signature_def = signature(
+            "decorated_definition",
+            vec![vec![
+                PathStep::ChildKind("decorator"),
+                PathStep::ChildKind("attribute"),
+                PathStep::ChildWithSource("identifier", &["getter", "setter", "deleter"]),
+            ]],
+        );
+
+        assert_eq!(
+            signature_def.extract_signature_from_original_node(setter),
+            Some(Signature(vec![vec![AstNodeEquiv::Original(
+                setter[0][1][2]
+            )]]))
+        );
+        assert_eq!(
+            signature_def.extract_signature_from_original_node(cached),
+            Some(Signature(vec![vec![]]))
+        );
+    }
+
+    #[test]
+    fn required_paths() {
+        let ctx = ctx();
+
+        let document = ctx.parse(
+            "a.toml",
+            "[[bin]]\nname = \"a\"\npath = \"a.rs\"\n\n[[bin]]\npath = \"b.rs\"\n",
+        );
+        let named = document[0];
+        let unnamed = document[1];
+
+        let signature_def = signature(
+            "table_array_element",
+            vec![vec![PathStep::ChildKind("bare_key")]],
+        )
+        .requiring(vec![PathStep::KeyedChild("pair", "name")]);
+
+        assert_eq!(
+            signature_def.extract_signature_from_original_node(named),
+            Some(Signature(vec![
+                vec![AstNodeEquiv::Original(named[1])],
+                vec![AstNodeEquiv::Original(named[3])]
+            ]))
+        );
+        assert!(
+            signature_def
+                .extract_signature_from_original_node(unnamed)
+                .is_none()
+        );
+    }
+
     #[test]
     fn node_equality_and_hashing() {
         let ctx = ctx();
@@ -11,8 +11,11 @@ use crate::{
         GitTempFile, GitTempFiles, attr::GitAttrsForSolve, extract_all_revisions_from_git,
         read_content_from_commits,
     },
-    newline::{imitate_newline_style, infer_newline_style, normalize_to_lf},
-    resolve_merge, structured_merge,
+    newline::{LineEndings, normalize_to_lf},
+    resolve_merge,
+    settings::DeleteModifyStrategy,
+    structured::StructuredMergeOptions,
+    structured_merge,
 };
 
 const FROM_PARSED_ORIGINAL: &str = "from_parsed_original";
@@ -28,6 +31,21 @@ pub struct CliOpts<'a> {
     pub conflict_marker_size: Option<usize>,
     pub language: Option<&'a str>,
     pub allow_parse_errors: Option<bool>,
+    pub commute_order_sensitive: Option<bool>,
+    pub commutative_only: Option<Vec<String>>,
+    pub conflict_comment: bool,
+    /// Match nodes across renames, based on the similarity of their contents
+    pub detect_renames: bool,
+    /// Only resolve the conflicts which can be merged commutatively
+    pub commutative_only_resolution: bool,
+    /// How to merge the elements deleted on one side and modified on the other
+    pub delete_modify: DeleteModifyStrategy,
+    /// Run the whole merge on the current thread
+    pub single_threaded: bool,
+    /// Replace the structured conflicts larger than this size (in bytes) with line-based merges
+    pub max_conflict_size: Option<usize>,
+    /// Merge the revisions staged in the Git index, instead of solving the conflicts in the file
+    pub from_index: bool,
 }
 
 /// Cascading merge resolution starting from a user-supplied file with merge conflicts
@@ -38,19 +56,48 @@ pub fn solve(
     working_dir: &Path,
     debug_dir: Option<&Path>,
 ) -> Result<MergeResult, String> {
-    let original_newline_style = infer_newline_style(original_conflict_contents);
+    let original_line_endings = LineEndings::infer(original_conflict_contents);
     let conflict_contents = normalize_to_lf(original_conflict_contents);
 
-    let (settings, lang_profile) = create_settings(conflict_path, cli_opts, working_dir)?;
-    let mut merged = do_solve(
-        &conflict_contents,
-        conflict_path,
-        settings,
-        &lang_profile,
-        working_dir,
-        debug_dir,
-    )?;
-    merged.contents = imitate_newline_style(&merged.contents, original_newline_style);
+    let from_index = cli_opts.from_index;
+    let options = StructuredMergeOptions {
+        commutative_only: cli_opts.commutative_only.clone(),
+        single_threaded: cli_opts.single_threaded,
+        delete_modify: cli_opts.delete_modify,
+        commutative_only_resolution: cli_opts.commutative_only_resolution,
+        detect_renames: cli_opts.detect_renames,
+    };
+    let (settings, lang_profile) =
+        create_settings(conflict_path, &conflict_contents, cli_opts, working_dir)?;
+    let mut merged = if from_index {
+        // the conflict markers can be ambiguous, so we don't look at them at all
+        structured_merge_from_git_revisions(
+            conflict_path,
+            &settings,
+            &options,
+            debug_dir,
+            working_dir,
+            &lang_profile,
+        )
+        .map_err(|err| match err {
+            FallbackMergeError::GitError(err) => {
+                format!("Could not retrieve the conflicted revisions from the Git index: {err}")
+            }
+            FallbackMergeError::MergeError(err) => format!("Structured merge failed: {err}"),
+        })
+        .inspect(|merge| info!("{} conflict(s) remaining.", merge.conflict_count))?
+    } else {
+        do_solve(
+            &conflict_contents,
+            conflict_path,
+            settings,
+            &options,
+            &lang_profile,
+            working_dir,
+            debug_dir,
+        )?
+    };
+    merged.contents = original_line_endings.imitate(&merged.contents);
     Ok(merged)
 }
 
@@ -58,25 +105,33 @@ pub fn solve(
 /// to create [DisplaySettings] and [LangProfile] to be used during the solve
 fn create_settings(
     conflict_path: &Path,
+    conflict_contents: &str,
     cli_opts: CliOpts,
     working_dir: &Path,
 ) -> Result<(DisplaySettings<'static>, Cow<'static, LangProfile>), String> {
-    let (conflict_marker_size_git, allow_parse_errors_git, language_git) =
-        if let Some(git_attrs) = GitAttrsForSolve::new(working_dir, conflict_path) {
-            (
-                git_attrs.conflict_marker_size,
-                git_attrs.allow_parse_errors,
-                git_attrs.language,
-            )
-        } else {
-            (None, None, None)
-        };
+    let (
+        conflict_marker_size_git,
+        allow_parse_errors_git,
+        commute_order_sensitive_git,
+        language_git,
+    ) = if let Some(git_attrs) = GitAttrsForSolve::new(working_dir, conflict_path) {
+        (
+            git_attrs.conflict_marker_size,
+            git_attrs.allow_parse_errors,
+            git_attrs.commute_order_sensitive,
+            git_attrs.language,
+        )
+    } else {
+        (None, None, None, None)
+    };
 
     #[rustfmt::skip]
     let conflict_marker_size = cli_opts.conflict_marker_size.or(conflict_marker_size_git);
     let allow_parse_errors = cli_opts.allow_parse_errors.or(allow_parse_errors_git);
+    let commute_order_sensitive =
+        (cli_opts.commute_order_sensitive).or(commute_order_sensitive_git);
 
-    let settings = DisplaySettings::new(
+    let mut settings = DisplaySettings::new(
         cli_opts.compact,
         conflict_marker_size,
         // NOTE: the names will be recognized in `do_solve` (if possible)
@@ -84,15 +139,23 @@ fn create_settings(
         None,
         None,
     );
+    settings.max_conflict_size = cli_opts.max_conflict_size;
 
     let mut lang_profile = Cow::Borrowed(LangProfile::find(
         conflict_path,
+        conflict_contents,
         cli_opts.language,
         language_git.as_deref(),
     )?);
     if let Some(allow_parse_errors) = allow_parse_errors {
         lang_profile.to_mut().allow_parse_errors = allow_parse_errors;
     }
+    if let Some(commute_order_sensitive) = commute_order_sensitive {
+        lang_profile.to_mut().commute_order_sensitive = commute_order_sensitive;
+    }
+    if cli_opts.conflict_comment {
+        settings.conflict_comment = lang_profile.comment_delimiters;
+    }
 
     Ok((settings, lang_profile))
 }
@@ -102,6 +165,7 @@ fn do_solve<'a>(
     merge_contents: &'a str,
     fname_base: &Path,
     mut settings: DisplaySettings<'a>,
+    options: &StructuredMergeOptions,
     lang_profile: &LangProfile,
     working_dir: &Path,
     debug_dir: Option<&Path>,
@@ -126,7 +190,7 @@ fn do_solve<'a>(
         Ok(parsed_merge) => {
             settings.add_revision_names(&parsed_merge);
 
-            match resolve_merge(&parsed_merge, &settings, lang_profile, debug_dir) {
+            match resolve_merge(&parsed_merge, &settings, options, lang_profile, debug_dir) {
                 Ok(solve) if solve.conflict_count == 0 => {
                     info!("Solved all conflicts.");
                     debug!("Structured merge from reconstructed revisions.");
@@ -150,6 +214,7 @@ fn do_solve<'a>(
     match structured_merge_from_git_revisions(
         fname_base,
         &settings,
+        options,
         debug_dir,
         working_dir,
         lang_profile,
@@ -175,6 +240,7 @@ fn do_solve<'a>(
     match structured_merge_from_oid(
         fname_base,
         &settings,
+        options,
         debug_dir,
         working_dir,
         lang_profile,
@@ -206,6 +272,7 @@ enum FallbackMergeError {
 fn structured_merge_from_git_revisions(
     fname_base: &Path,
     settings: &DisplaySettings,
+    options: &StructuredMergeOptions,
     debug_dir: Option<&Path>,
     working_dir: &Path,
     lang_profile: &LangProfile,
@@ -231,6 +298,7 @@ fn structured_merge_from_git_revisions(
         &contents_right,
         None,
         settings,
+        options,
         lang_profile,
         debug_dir,
     )
@@ -247,6 +315,7 @@ fn structured_merge_from_git_revisions(
 fn structured_merge_from_oid(
     fname_base: &Path,
     settings: &DisplaySettings,
+    options: &StructuredMergeOptions,
     debug_dir: Option<&Path>,
     working_dir: &Path,
     lang_profile: &LangProfile,
@@ -260,6 +329,7 @@ fn structured_merge_from_oid(
         &contents.2,
         None,
         settings,
+        options,
         lang_profile,
         debug_dir,
     );
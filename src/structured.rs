@@ -1,11 +1,24 @@
-use std::{path::Path, time::Instant};
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
 
 use log::debug;
+use serde_json::json;
 use typed_arena::Arena;
 
 use crate::{
-    MergeResult, Revision, ast::AstNode, lang_profile::LangProfile, matching::ApproxExactMatching,
-    merge_3dm::three_way_merge, parsed_merge::ParsedMerge, settings::DisplaySettings,
+    MergeResult, Revision,
+    ast::AstNode,
+    class_mapping::ClassMapping,
+    lang_profile::LangProfile,
+    matching::ApproxExactMatching,
+    merge_3dm::three_way_merge,
+    merged_text::MergedText,
+    merged_tree::MergedTree,
+    parsed_merge::ParsedMerge,
+    settings::{DeleteModifyStrategy, DisplaySettings},
     tree_matcher::TreeMatcher,
 };
 
@@ -15,20 +28,80 @@ pub const FULLY_STRUCTURED_METHOD: &str = "fully_structured";
 pub(crate) const ZDIFF3_DETECTED: &str =
     "Mergiraf cannot solve conflicts displayed in the zdiff style";
 
+/// The time spent in each phase of a structured merge
+#[derive(Debug, Default)]
+pub(crate) struct PhaseTimings {
+    /// Parsing all three revisions
+    pub parse: Duration,
+    /// Matching the trees and building the class mapping
+    pub matching: Duration,
+    /// Building the merged tree out of the PCS triples
+    pub build: Duration,
+    /// Rendering the merged tree to text
+    pub render: Duration,
+    /// Checking that the rendered text is faithful to the merged tree
+    pub check: Duration,
+}
+
+impl PhaseTimings {
+    /// Writes the durations to a JSON file, in milliseconds
+    pub(crate) fn save(&self, fname: impl AsRef<Path>) {
+        let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        let timings = json!({
+            "parse": millis(self.parse),
+            "match": millis(self.matching),
+            "build": millis(self.build),
+            "render": millis(self.render),
+            "check": millis(self.check),
+        });
+        let json =
+            serde_json::to_string_pretty(&timings).expect("JSON values can always be serialized");
+        fs::write(fname, json + "\n").expect("Unable to write timings file");
+    }
+}
+
+/// Options controlling how the revisions are merged in a structured way,
+/// as opposed to how the merge is output (see [`DisplaySettings`])
+#[derive(Debug, Clone, Default)]
+pub struct StructuredMergeOptions {
+    /// If set, only the commutative parents of these grammar node types are merged
+    /// commutatively, the others being merged like any other node
+    pub commutative_only: Option<Vec<String>>,
+    /// Whether to run all the steps of the merge on the current thread, without any timeout,
+    /// so that the timings of successive runs can be compared
+    pub single_threaded: bool,
+    /// How to merge the elements deleted on one side and modified on the other
+    pub delete_modify: DeleteModifyStrategy,
+    /// Whether to only combine the changes of both sides in commutative parents, leaving
+    /// the other nodes changed on both sides to a line-based merge
+    pub commutative_only_resolution: bool,
+    /// Whether to match nodes across renames, based on the similarity of their contents
+    pub detect_renames: bool,
+}
+
+impl StructuredMergeOptions {
+    /// Whether the commutative parents of the given grammar node type can be merged commutatively
+    pub fn allows_commutative_parent(&self, kind: &str) -> bool {
+        (self.commutative_only.as_ref()).is_none_or(|kinds| kinds.iter().any(|k| k == kind))
+    }
+}
+
 /// Performs a fully structured merge, parsing the contents of all three revisions,
 /// creating tree matchings between all pairs, and merging them.
 ///
 /// If a debug dir is provided, various intermediate stages of the matching will be
-/// written as files in that directory.
+/// written as files in that directory, as well as the time spent in each phase of the merge.
 ///
 /// After the merge, checks that the resulting tree is isomorphic to the input trees,
 /// and rejects the merge if it isn't.
+#[allow(clippy::too_many_arguments)]
 pub fn structured_merge(
     contents_base: &str,
     contents_left: &str,
     contents_right: &str,
     parsed_merge: Option<&ParsedMerge>,
     settings: &DisplaySettings,
+    options: &StructuredMergeOptions,
     lang_profile: &LangProfile,
     debug_dir: Option<&Path>,
 ) -> Result<MergeResult, String> {
@@ -40,19 +113,24 @@ pub fn structured_merge(
         sim_threshold: 0.4,
         max_recovery_size: 100,
         use_rted: true,
+        detect_renames: options.detect_renames,
     };
     let auxiliary_matcher = TreeMatcher {
         min_height: 2,
         sim_threshold: 0.6,
         max_recovery_size: 100,
         use_rted: false,
+        detect_renames: options.detect_renames,
     };
 
+    let mut timings = PhaseTimings::default();
+
     let start = Instant::now();
     let tree_base = AstNode::parse(contents_base, lang_profile, &arena, &ref_arena);
     let tree_left = AstNode::parse(contents_left, lang_profile, &arena, &ref_arena);
     let tree_right = AstNode::parse(contents_right, lang_profile, &arena, &ref_arena);
-    debug!("parsing all three files took {:?}", start.elapsed());
+    timings.parse = start.elapsed();
+    debug!("parsing all three files took {:?}", timings.parse);
 
     // detect a merge in zdiff3 style
     let (tree_base, tree_left, tree_right) = match (tree_base, tree_left, tree_right) {
@@ -94,13 +172,42 @@ pub fn structured_merge(
         &primary_matcher,
         &auxiliary_matcher,
         settings,
+        options,
         debug_dir,
+        &mut timings,
     );
     debug!("{result_tree}");
 
+    let start = Instant::now();
     let merged_text = result_tree.to_merged_text(&class_mapping);
+    timings.render = start.elapsed();
 
     // Check that the rendered merge is faithful to the tree
+    let start = Instant::now();
+    let check_result =
+        check_rendered_merge(&merged_text, &result_tree, &class_mapping, lang_profile);
+    timings.check = start.elapsed();
+    if let Some(debug_dir) = debug_dir {
+        timings.save(debug_dir.join("timings.json"));
+    }
+    check_result?;
+
+    let method = if parsed_merge.is_none() {
+        FULLY_STRUCTURED_METHOD
+    } else {
+        STRUCTURED_RESOLUTION_METHOD
+    };
+    Ok(merged_text.into_merge_result(settings, method))
+}
+
+/// Checks that the rendered merge is faithful to the merged tree, by parsing
+/// the revisions reconstructed from it
+fn check_rendered_merge<'a>(
+    merged_text: &MergedText<'a>,
+    result_tree: &'a MergedTree<'a>,
+    class_mapping: &ClassMapping<'a>,
+    lang_profile: &LangProfile,
+) -> Result<(), String> {
     let revisions_to_check = if merged_text.count_conflicts() == 0 {
         [Revision::Base].as_slice()
     } else {
@@ -110,29 +217,19 @@ pub fn structured_merge(
         let merged_revision = merged_text.reconstruct_revision(*revision);
         let arena = Arena::new();
         let ref_arena = Arena::new();
-        let tree = AstNode::parse(
-            &merged_revision,
-            lang_profile,
-            &arena,
-            &ref_arena,
-        )
-        .map_err(|err| {
-            format!(
-                "merge discarded because rendered revision {revision} has a parsing error: {err}"
-            )
-        })?;
-        if !result_tree.isomorphic_to_source(tree, *revision, &class_mapping) {
+        let tree = AstNode::parse(&merged_revision, lang_profile, &arena, &ref_arena).map_err(
+            |err| {
+                format!(
+                    "merge discarded because rendered revision {revision} has a parsing error: {err}"
+                )
+            },
+        )?;
+        if !result_tree.isomorphic_to_source(tree, *revision, class_mapping) {
             debug!(
                 "discarding merge because rendered revision {revision} isn't isomorphic to the merged tree"
             );
             return Err("merge discarded after isomorphism check".to_owned());
         }
     }
-
-    let method = if parsed_merge.is_none() {
-        FULLY_STRUCTURED_METHOD
-    } else {
-        STRUCTURED_RESOLUTION_METHOD
-    };
-    Ok(merged_text.into_merge_result(settings, method))
+    Ok(())
 }
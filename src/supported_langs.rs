@@ -3,7 +3,7 @@ use std::sync::LazyLock;
 use crate::{
     lang_profile::{ChildrenGroup, CommutativeParent, LangProfile},
     signature::{
-        PathStep::{ChildKind, Field},
+        PathStep::{ChildKind, ChildWithSource, Field, KeyedChild},
         signature,
     },
 };
@@ -27,7 +27,11 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
         ]),
         CommutativeParent::new("interface_body", " {\n", ";\n", "\n}\n"),
         CommutativeParent::new("object_type", " {\n", ";\n", "\n}\n"),
-        CommutativeParent::new("enum_body", " {\n", ",\n", "\n}\n"),
+        // the values of enum members without an initializer depend on their position,
+        // so only do it on request if there are any
+        CommutativeParent::from_query("(enum_body) @commutative", " {\n", ",\n", "\n}\n"),
+        CommutativeParent::from_query("(enum_body name: (_)) @commutative", " {\n", ",\n", "\n}\n")
+            .order_sensitive(),
         CommutativeParent::new("object_pattern", "{", ", ", "}"),
         CommutativeParent::without_delimiters("union_type", " | "),
         CommutativeParent::without_delimiters("intersection_type", " & "),
@@ -42,6 +46,7 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
         signature("property_identifier", vec![vec![]]),
         signature("shorthand_property_identifier", vec![vec![]]),
         signature("pair_pattern", vec![vec![Field("key")]]),
+        signature("enum_assignment", vec![vec![Field("name")]]),
         signature("literal_type", vec![vec![]]), // for union and intersection types
     ];
     let typescript_flattened_nodes = &["union_type", "intersection_type"];
@@ -71,14 +76,46 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
         vec![vec![ChildKind("field_path"), ChildKind("field_name")]],
     )];
 
+    let d_declarations = [
+        "function_declaration",
+        "struct_declaration",
+        "class_declaration",
+        "interface_declaration",
+        "union_declaration",
+        "enum_declaration",
+    ];
+    let d_top_level_groups = vec![
+        ChildrenGroup::with_separator(&["import_declaration"], "\n"),
+        ChildrenGroup::new(&d_declarations),
+    ];
+
+    let swift_parameter_types = [
+        "user_type",
+        "optional_type",
+        "array_type",
+        "dictionary_type",
+    ]
+    .map(|kind| vec![ChildKind("parameter"), ChildKind(kind)])
+    .to_vec();
+    let swift_function_signature = [
+        vec![vec![ChildKind("simple_identifier")]],
+        swift_parameter_types.clone(),
+    ]
+    .concat();
+
     vec![
         LangProfile {
             name: "Java",
             alternate_names: &[],
             extensions: &["java"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_java_orchard::LANGUAGE.into(),
             atomic_nodes: &["import_declaration"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 // top-level node, for imports and class declarations
                 CommutativeParent::without_delimiters("program", "\n\n").restricted_to(vec![
@@ -131,6 +168,13 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                 CommutativeParent::without_delimiters("catch_type", " | "),
                 CommutativeParent::without_delimiters("type_list", ", "), // for "implements" or "sealed"
                 CommutativeParent::new("annotation_argument_list", "{", ", ", "}"),
+                // `ordinal()` and `values()` depend on the order of the constants, so only do it on request
+                CommutativeParent::new("enum_body", " {\n", ",\n", "\n}\n")
+                    .restricted_to_groups(&[&["enum_constant"]])
+                    .order_sensitive(),
+                // `switch_block` is deliberately not commutative, not even on request:
+                // a case without `break` falls through into the next one, so reordering
+                // cases or inserting one in between changes the behaviour of the code.
             ],
             signatures: vec![
                 // program
@@ -190,37 +234,56 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                 signature("identifier", vec![vec![]]),
                 // annotation_argument_list
                 signature("element_value_pair", vec![vec![Field("key")]]),
+                // enum_body
+                signature("enum_constant", vec![vec![Field("name")]]),
             ],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "Java properties",
             alternate_names: &[],
             extensions: &["properties"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_properties::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![CommutativeParent::without_delimiters("file", "\n")],
             signatures: vec![signature("property", vec![vec![ChildKind("key")]])],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
         LangProfile {
             name: "Kotlin",
             alternate_names: &[],
             extensions: &["kt"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_kotlin_ng::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 // top-level node, for imports and class declarations
                 CommutativeParent::without_delimiters("source_file", "\n\n")
                     .restricted_to_groups(&[&["import"], &["function_declaration"]]),
+                // unlike the properties declared in class bodies, those declared in primary
+                // constructors (`class_parameters`) are positional, just like the parameters of
+                // functions: they must not commute
                 CommutativeParent::new("class_body", " {\n", "\n\n", "\n}\n")
                     .restricted_to_groups(&[&["property_declaration"], &["function_declaration"]]),
                 CommutativeParent::without_delimiters("modifiers", "\n").restricted_to(vec![
@@ -236,6 +299,10 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                 ]),
                 CommutativeParent::without_delimiters("class_declaration", ", ")
                     .restricted_to_groups(&[&["delegation_specifier"]]),
+                // reordering enum entries changes their ordinals, so only do it on request
+                CommutativeParent::new("enum_class_body", " {\n", ",\n", "\n}\n")
+                    .restricted_to_groups(&[&["enum_entry"]])
+                    .order_sensitive(),
             ],
             signatures: vec![
                 signature("import", vec![vec![]]),
@@ -260,6 +327,8 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                 ),
                 // class_declaration
                 signature("delegation_specifier", vec![vec![]]),
+                // enum_class_body
+                signature("enum_entry", vec![vec![ChildKind("identifier")]]),
                 // modifiers
                 signature("annotation", vec![vec![]]), // annotations can be repeatable, so we can't use the name as key
                 signature("public", vec![vec![]]),
@@ -275,14 +344,89 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
+        },
+        LangProfile {
+            name: "Swift",
+            alternate_names: &[],
+            extensions: &["swift"],
+            file_names: &[],
+            path_patterns: &[],
+            language: tree_sitter_swift::LANGUAGE.into(),
+            atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
+            commutative_parents: vec![
+                // top-level statements are executed in order, so only declarations commute
+                CommutativeParent::without_delimiters("source_file", "\n\n").restricted_to(vec![
+                    ChildrenGroup::with_separator(&["import_declaration"], "\n"),
+                    ChildrenGroup::new(&[
+                        "class_declaration",
+                        "protocol_declaration",
+                        "function_declaration",
+                    ]),
+                ]),
+                CommutativeParent::new("class_body", " {\n", "\n\n", "\n}\n").restricted_to(vec![
+                    ChildrenGroup::with_separator(&["property_declaration"], "\n"),
+                    ChildrenGroup::new(&[
+                        "init_declaration",
+                        "function_declaration",
+                        "class_declaration",
+                    ]),
+                ]),
+                CommutativeParent::new("protocol_body", " {\n", "\n", "\n}\n")
+                    .restricted_to_groups(&[&[
+                        "protocol_property_declaration",
+                        "protocol_function_declaration",
+                    ]]),
+            ],
+            signatures: vec![
+                signature("import_declaration", vec![vec![ChildKind("identifier")]]),
+                // a type can be extended several times in the same file,
+                // typically to add conformances to different protocols
+                signature(
+                    "class_declaration",
+                    vec![
+                        vec![Field("declaration_kind")],
+                        vec![Field("name")],
+                        vec![ChildKind("inheritance_specifier")],
+                        vec![ChildKind("type_constraints")],
+                    ],
+                ),
+                signature("protocol_declaration", vec![vec![Field("name")]]),
+                // the grammar puts parameter and return types in the `name` field too,
+                // so the name and parameter types are found by their kinds instead
+                signature("function_declaration", swift_function_signature.clone()),
+                signature("protocol_function_declaration", swift_function_signature),
+                signature("init_declaration", swift_parameter_types),
+                signature("property_declaration", vec![vec![Field("name")]]),
+                signature(
+                    "protocol_property_declaration",
+                    vec![vec![Field("name"), Field("bound_identifier")]],
+                ),
+            ],
+            injections: None,
+            flattened_nodes: &[],
+            extra_comment_nodes: &[],
+            allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "Rust",
             alternate_names: &[],
             extensions: &["rs"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_rust_orchard::LANGUAGE.into(),
             atomic_nodes: &["block_comment", "line_comment"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("source_file", "\n").restricted_to_groups(&[
                     &["use_declaration"], // to keep use declarations together (even if it's not actually required)
@@ -408,14 +552,21 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "Go",
             alternate_names: &[],
             extensions: &["go"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_go::LANGUAGE.into(),
             atomic_nodes: &["interpreted_string_literal"], // for https://github.com/tree-sitter/tree-sitter-go/issues/150
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("source_file", "\n").restricted_to(vec![
                     ChildrenGroup::new(&["import_declaration"]),
@@ -450,14 +601,21 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "go.mod",
             alternate_names: &["Go module", "go mod"],
             extensions: &[],
             file_names: &["go.mod"],
+            path_patterns: &[],
             language: tree_sitter_gomod_orchard::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("source_file", "\n\n").restricted_to_groups(
                     &[
@@ -524,14 +682,21 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("//", "")),
         },
         LangProfile {
             name: "go.sum",
             alternate_names: &["Go checksums"],
             extensions: &[],
             file_names: &["go.sum", "go.work.sum"],
+            path_patterns: &[],
             language: tree_sitter_gosum_orchard::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("checksum_database", "\n")
                     .restricted_to_groups(&[&["checksum"]]),
@@ -552,14 +717,21 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: None,
         },
         LangProfile {
             name: "INI",
             alternate_names: &[],
             extensions: &["ini"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_ini::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("section", "\n")
                     .restricted_to_groups(&[&["setting"]]),
@@ -569,14 +741,21 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
         LangProfile {
             name: "Javascript",
             alternate_names: &[],
             extensions: &["js", "jsx", "mjs", "cjs"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_javascript::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::new("object", "{", ", ", "}"),
                 CommutativeParent::new("class_body", " {\n", "\n\n", "\n}\n"),
@@ -596,14 +775,21 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "JSON",
             alternate_names: &[],
             extensions: &["json"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_json::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 // the order of keys is deemed irrelevant
                 CommutativeParent::new("object", "{", ", ", "}"),
@@ -613,20 +799,83 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: None,
+        },
+        // This language profile is before the YAML one, so that workflows are not detected as plain YAML.
+        LangProfile {
+            name: "GitHub Actions workflow",
+            alternate_names: &[],
+            extensions: &[],
+            file_names: &[],
+            path_patterns: &[".github/workflows/*.yml", ".github/workflows/*.yaml"],
+            language: tree_sitter_yaml::LANGUAGE.into(),
+            atomic_nodes: &["single_quote_scalar", "double_quote_scalar"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
+            commutative_parents: vec![
+                CommutativeParent::without_delimiters("block_mapping", "\n"),
+                // Steps are run in order, but steps added on both sides can generally be
+                // put one after the other. Only the ones which have a `name` or an `id` are
+                // reordered, as the others can't be told apart reliably.
+                CommutativeParent::from_query_without_delimiters(
+                    r#"(block_mapping_pair
+  key: (flow_node) @key (#eq? @key "steps")
+  value: (block_node (block_sequence) @commutative)
+)"#,
+                    "\n",
+                ),
+            ],
+            signatures: vec![
+                signature("block_mapping_pair", vec![vec![Field("key")]]),
+                signature(
+                    "block_sequence_item",
+                    vec![
+                        vec![
+                            ChildKind("block_node"),
+                            ChildKind("block_mapping"),
+                            KeyedChild("block_mapping_pair", "name"),
+                            Field("value"),
+                        ],
+                        vec![
+                            ChildKind("block_node"),
+                            ChildKind("block_mapping"),
+                            KeyedChild("block_mapping_pair", "id"),
+                            Field("value"),
+                        ],
+                    ],
+                )
+                .optional(),
+            ],
+            injections: None,
+            flattened_nodes: &[],
+            extra_comment_nodes: &[],
+            allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
         LangProfile {
             name: "YAML",
             alternate_names: &[],
             extensions: &["yml", "yaml"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_yaml::LANGUAGE.into(),
             atomic_nodes: &["single_quote_scalar", "double_quote_scalar"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![CommutativeParent::without_delimiters("block_mapping", "\n")],
             signatures: vec![signature("block_mapping_pair", vec![vec![Field("key")]])],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
         // This language profile is before the TOML one, so that the more specific pyproject.toml one is encountered first.
         LangProfile {
@@ -634,8 +883,13 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             alternate_names: &[],
             extensions: &[],
             file_names: &["pyproject.toml"],
+            path_patterns: &[],
             language: tree_sitter_toml_ng::LANGUAGE.into(),
             atomic_nodes: &["string"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("document", "\n"),
                 CommutativeParent::without_delimiters("table", "\n"),
@@ -687,45 +941,184 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             ],
             signatures: vec![
                 signature("pair", vec![vec![ChildKind("bare_key")]]),
+                signature(
+                    "table",
+                    vec![
+                        vec![ChildKind("bare_key")],
+                        vec![ChildKind("quoted_key")],
+                        vec![ChildKind("dotted_key")],
+                    ],
+                ),
+                signature(
+                    "table_array_element",
+                    vec![vec![ChildKind("bare_key")], vec![ChildKind("dotted_key")]],
+                )
+                .requiring(vec![KeyedChild("pair", "name")]),
                 signature("string", vec![vec![]]),
             ],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
+        },
+        LangProfile {
+            name: "Cargo.lock",
+            alternate_names: &["Cargo lockfile"],
+            extensions: &[],
+            file_names: &["Cargo.lock"],
+            path_patterns: &[],
+            language: tree_sitter_toml_ng::LANGUAGE.into(),
+            atomic_nodes: &["string", "quoted_key"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
+            commutative_parents: vec![
+                CommutativeParent::without_delimiters("document", "\n\n")
+                    .restricted_to_groups(&[&["table_array_element"]]),
+                // lists of dependencies are sorted sets
+                CommutativeParent::new("array", "[", ",\n", "]"),
+            ],
+            signatures: vec![
+                // the same package can appear multiple times with different versions
+                signature(
+                    "table_array_element",
+                    vec![
+                        vec![KeyedChild("pair", "name")],
+                        vec![KeyedChild("pair", "version")],
+                    ],
+                ),
+                signature("string", vec![vec![]]),
+            ],
+            injections: None,
+            flattened_nodes: &[],
+            extra_comment_nodes: &[],
+            allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
         LangProfile {
             name: "TOML",
             alternate_names: &[],
             extensions: &["toml"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_toml_ng::LANGUAGE.into(),
             atomic_nodes: &["string", "quoted_key"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("document", "\n"),
                 CommutativeParent::without_delimiters("table", "\n"),
                 CommutativeParent::new("inline_table", "{", ", ", "}"),
             ],
+            signatures: vec![
+                signature(
+                    "pair",
+                    vec![
+                        vec![ChildKind("bare_key")],
+                        vec![ChildKind("quoted_key")],
+                        vec![ChildKind("dotted_key")],
+                    ],
+                ),
+                // tables are identified by their entire header, such as `[a.b.c]`
+                signature(
+                    "table",
+                    vec![
+                        vec![ChildKind("bare_key")],
+                        vec![ChildKind("quoted_key")],
+                        vec![ChildKind("dotted_key")],
+                    ],
+                ),
+                // array-of-tables entries such as `[[bin]]` targets in Cargo.toml are identified by
+                // their name, if they have one
+                signature(
+                    "table_array_element",
+                    vec![vec![ChildKind("bare_key")], vec![ChildKind("dotted_key")]],
+                )
+                .requiring(vec![KeyedChild("pair", "name")]),
+            ],
+            injections: None,
+            flattened_nodes: &[],
+            extra_comment_nodes: &[],
+            allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
+        },
+        LangProfile {
+            name: "CSS",
+            alternate_names: &[],
+            extensions: &["css"],
+            file_names: &[],
+            path_patterns: &[],
+            language: tree_sitter_css::LANGUAGE.into(),
+            atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
+            // rules of the same specificity override each other in order of appearance,
+            // so only the declarations within a block commute
+            commutative_parents: vec![
+                CommutativeParent::new("block", " {\n", "\n", "\n}\n")
+                    .restricted_to_groups(&[&["declaration"]]),
+            ],
             signatures: vec![signature(
-                "pair",
-                vec![
-                    vec![ChildKind("bare_key")],
-                    vec![ChildKind("quoted_key")],
-                    vec![ChildKind("dotted_key")],
-                ],
+                "declaration",
+                vec![vec![ChildKind("property_name")]],
             )],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "HTML",
             alternate_names: &[],
             extensions: &["html", "htm"],
             file_names: &[],
+            path_patterns: &[],
+            language: tree_sitter_html::LANGUAGE.into(),
+            atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
+            commutative_parents: vec![
+                CommutativeParent::new("self_closing_tag", "<", " ", "/>"),
+                CommutativeParent::new("start_tag", "<", " ", ">"),
+            ],
+            signatures: vec![signature(
+                "attribute",
+                vec![vec![ChildKind("attribute_name")]],
+            )],
+            injections: Some(tree_sitter_html::INJECTIONS_QUERY),
+            flattened_nodes: &[],
+            extra_comment_nodes: &[],
+            allow_parse_errors: true,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("<!--", "-->")),
+        },
+        LangProfile {
+            name: "Svelte",
+            alternate_names: &[],
+            extensions: &["svelte"],
+            file_names: &[],
+            path_patterns: &[],
+            // The HTML grammar parses the `<script>` and `<style>` sections of components,
+            // while the `{#if}`/`{#each}` blocks of the markup are kept as text.
             language: tree_sitter_html::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::new("self_closing_tag", "<", " ", "/>"),
                 CommutativeParent::new("start_tag", "<", " ", ">"),
@@ -734,18 +1127,130 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                 "attribute",
                 vec![vec![ChildKind("attribute_name")]],
             )],
+            injections: Some(
+                r#"((script_element
+                   (raw_text) @injection.content)
+                  (#set! injection.language "javascript"))
+
+                 ((script_element
+                   (start_tag
+                     (attribute
+                       (attribute_name) @_name
+                       (quoted_attribute_value (attribute_value) @_lang)))
+                   (raw_text) @injection.content)
+                  (#eq? @_name "lang")
+                  (#any-of? @_lang "ts" "typescript")
+                  (#set! injection.language "typescript"))
+
+                 ((style_element
+                   (raw_text) @injection.content)
+                  (#set! injection.language "css"))"#,
+            ),
+            flattened_nodes: &[],
+            extra_comment_nodes: &[],
+            allow_parse_errors: true,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("<!--", "-->")),
+        },
+        LangProfile {
+            name: "Handlebars",
+            alternate_names: &[],
+            extensions: &["hbs", "handlebars"],
+            file_names: &[],
+            path_patterns: &[],
+            // The `{{…}}` mustaches are kept as text by the HTML grammar, which lets
+            // the surrounding markup be merged in a structured way.
+            language: tree_sitter_html::LANGUAGE.into(),
+            atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
+            commutative_parents: vec![
+                CommutativeParent::from_query("(self_closing_tag) @commutative", "<", " ", "/>"),
+                CommutativeParent::from_query("(start_tag) @commutative", "<", " ", ">"),
+                // Element modifiers such as `{{on "click" this.select}}` are not valid attributes
+                // and get split into arbitrary pieces, which must not be reordered
+                CommutativeParent::from_query(
+                    r#"([(start_tag (ERROR)) (self_closing_tag (ERROR))] @commutative)
+                       ([(start_tag (attribute (attribute_name) @_name))
+                         (self_closing_tag (attribute (attribute_name) @_name))] @commutative
+                        (#match? @_name "^\\{\\{"))"#,
+                    "<",
+                    " ",
+                    ">",
+                )
+                .order_sensitive(),
+            ],
+            signatures: vec![signature(
+                "attribute",
+                vec![vec![ChildKind("attribute_name")]],
+            )],
             injections: Some(tree_sitter_html::INJECTIONS_QUERY),
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: true,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("<!--", "-->")),
+        },
+        // This language profile is before the XML one, so that the more specific pom.xml one is encountered first.
+        LangProfile {
+            name: "pom.xml",
+            alternate_names: &["Maven POM"],
+            extensions: &[],
+            file_names: &["pom.xml"],
+            path_patterns: &[],
+            language: tree_sitter_xml::LANGUAGE_XML.into(),
+            atomic_nodes: &["AttValue"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &["CharData"],
+            word_list_nodes: &[],
+            commutative_parents: vec![
+                CommutativeParent::new("EmptyElemTag", "<", " ", "/>"),
+                CommutativeParent::new("STag", "<", " ", ">"),
+                // The order of elements generally matters in XML, but not for Maven dependencies,
+                // plugins or modules.
+                CommutativeParent::from_query_without_delimiters(
+                    r#"(element
+  (STag (Name) @name (#any-of? @name "dependencies" "plugins" "modules"))
+  (content) @commutative
+)"#,
+                    "\n",
+                )
+                .restricted_to_groups(&[&["element"]]),
+            ],
+            signatures: vec![
+                signature("Attribute", vec![vec![ChildKind("Name")]]),
+                signature(
+                    "element",
+                    vec![
+                        vec![ChildKind("content"), KeyedChild("element", "<groupId>")],
+                        vec![ChildKind("content"), KeyedChild("element", "<artifactId>")],
+                        // the path of a module
+                        vec![ChildKind("content"), ChildKind("CharData")],
+                    ],
+                ),
+            ],
+            injections: None,
+            flattened_nodes: &[],
+            extra_comment_nodes: &[],
+            allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("<!--", "-->")),
         },
         LangProfile {
             name: "XML",
             alternate_names: &[],
             extensions: &["xhtml", "xml"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_xml::LANGUAGE_XML.into(),
             atomic_nodes: &["AttValue"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &["CharData"],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::new("EmptyElemTag", "<", " ", "/>"),
                 CommutativeParent::new("STag", "<", " ", ">"),
@@ -755,14 +1260,21 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("<!--", "-->")),
         },
         LangProfile {
             name: "C",
             alternate_names: &["C"],
             extensions: &["c"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_c::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::new("initializer_list", "{", ",", "}")
                     .restricted_to_groups(&[&["initializer_pair"]]),
@@ -792,6 +1304,8 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: true,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "C++",
@@ -801,8 +1315,13 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                 "tcc",
             ],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_cpp::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::new("initializer_list", "{", ",", "}")
                     .restricted_to_groups(&[&["initializer_pair"]]),
@@ -832,14 +1351,21 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: true,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "C#",
             alternate_names: &["CSharp", "cake", "cakescript"],
             extensions: &["cs"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_c_sharp::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("compilation_unit", "\n"),
                 CommutativeParent::new("declaration_list", "{", "\n", "}").restricted_to_groups(&[
@@ -866,7 +1392,43 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                         "conversion_operator_declaration",
                     ],
                 ]),
-                CommutativeParent::new("enum_member_declaration_list", "{", ",\n", "}"),
+                // the values of enum members without an explicit one depend on their position,
+                // so only do it on request if there are any
+                CommutativeParent::from_query(
+                    "(enum_member_declaration_list) @commutative",
+                    "{",
+                    ",\n",
+                    "}",
+                ),
+                CommutativeParent::from_query(
+                    "(enum_member_declaration_list (enum_member_declaration !value)) @commutative",
+                    "{",
+                    ",\n",
+                    "}",
+                )
+                .order_sensitive(),
+                CommutativeParent::new("attribute_list", "[", ", ", "]")
+                    .restricted_to_groups(&[&["attribute"]]),
+                CommutativeParent::from_query_without_delimiters(
+                    r#"[
+                        (class_declaration)
+                        (struct_declaration)
+                        (enum_declaration)
+                        (interface_declaration)
+                        (record_declaration)
+                        (delegate_declaration)
+                        (field_declaration)
+                        (property_declaration)
+                        (event_declaration)
+                        (event_field_declaration)
+                        (method_declaration)
+                        (constructor_declaration)
+                        (operator_declaration)
+                        (enum_member_declaration)
+                    ] @commutative"#,
+                    "\n",
+                )
+                .restricted_to_groups(&[&["attribute_list"]]),
             ],
             signatures: vec![
                 signature("using_directive", vec![vec![]]),
@@ -903,19 +1465,124 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                 signature("event_declaration", vec![vec![Field("name")]]),
                 // enum_declaration_list
                 signature("enum_member_declaration", vec![vec![Field("name")]]),
+                // attribute_list
+                signature("attribute", vec![vec![Field("name")]]),
+                signature(
+                    "attribute_list",
+                    vec![vec![ChildKind("attribute"), Field("name")]],
+                ),
+            ],
+            injections: None,
+            flattened_nodes: &[],
+            extra_comment_nodes: &[],
+            allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
+        },
+        LangProfile {
+            name: "F#",
+            alternate_names: &["fsharp"],
+            extensions: &["fs"],
+            file_names: &[],
+            path_patterns: &[],
+            language: tree_sitter_fsharp::LANGUAGE_FSHARP.into(),
+            atomic_nodes: &[],
+            // definitions are scoped from top to bottom, so only `open` declarations commute
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
+            commutative_parents: vec![
+                CommutativeParent::without_delimiters("file", "\n")
+                    .restricted_to_groups(&[&["import_decl"]]),
+                CommutativeParent::without_delimiters("named_module", "\n")
+                    .restricted_to_groups(&[&["import_decl"]]),
+                CommutativeParent::without_delimiters("namespace", "\n")
+                    .restricted_to_groups(&[&["import_decl"]]),
+            ],
+            signatures: vec![signature(
+                "import_decl",
+                vec![vec![ChildKind("long_identifier")]],
+            )],
+            injections: None,
+            flattened_nodes: &[],
+            extra_comment_nodes: &[],
+            allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("(*", "*)")),
+        },
+        LangProfile {
+            name: "D",
+            alternate_names: &["dlang"],
+            extensions: &["d", "di"],
+            file_names: &[],
+            path_patterns: &[],
+            language: tree_sitter_d::LANGUAGE.into(),
+            atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
+            commutative_parents: vec![
+                // top-level nodes, depending on whether the module is declared or not
+                CommutativeParent::without_delimiters("source_file", "\n\n")
+                    .restricted_to(d_top_level_groups.clone()),
+                CommutativeParent::without_delimiters("module_def", "\n\n")
+                    .restricted_to(d_top_level_groups),
+                // struct, class, interface and union members
+                CommutativeParent::new("aggregate_body", " {\n", "\n\n", "\n}\n").restricted_to(
+                    vec![
+                        ChildrenGroup::with_separator(&["variable_declaration"], "\n"),
+                        ChildrenGroup::new(&d_declarations),
+                    ],
+                ),
+            ],
+            signatures: vec![
+                // the grammar doesn't define any fields, so we rely on child kinds instead
+                signature(
+                    "import_declaration",
+                    vec![vec![ChildKind("imported"), ChildKind("module_fqn")]],
+                ),
+                signature(
+                    "function_declaration",
+                    vec![
+                        vec![ChildKind("identifier")],
+                        vec![
+                            ChildKind("parameters"),
+                            ChildKind("parameter"),
+                            ChildKind("type"),
+                        ],
+                    ],
+                ),
+                signature("struct_declaration", vec![vec![ChildKind("identifier")]]),
+                signature("class_declaration", vec![vec![ChildKind("identifier")]]),
+                signature("interface_declaration", vec![vec![ChildKind("identifier")]]),
+                signature("union_declaration", vec![vec![ChildKind("identifier")]]),
+                signature("enum_declaration", vec![vec![ChildKind("identifier")]]),
+                signature(
+                    "variable_declaration",
+                    vec![vec![ChildKind("declarator"), ChildKind("identifier")]],
+                ),
             ],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "Dart",
             alternate_names: &[],
             extensions: &["dart"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_dart_orchard::LANGUAGE.into(),
             atomic_nodes: &["import_or_export"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("program", "\n"),
                 CommutativeParent::new("enum_body", "{", ",\n", "}"),
@@ -930,45 +1597,84 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "Devicetree Source",
             alternate_names: &[],
             extensions: &["dts"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_devicetree::LANGUAGE.into(),
             atomic_nodes: &["string_literal"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![CommutativeParent::new("node", "{", "\n", "}")],
             signatures: vec![
                 signature("property", vec![vec![Field("name")]]),
-                signature("node", vec![vec![Field("name")]]),
+                signature("node", vec![vec![Field("name")], vec![Field("address")]]),
             ],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "Scala",
             alternate_names: &[],
             extensions: &["scala", "sbt"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_scala::LANGUAGE.into(),
             atomic_nodes: &[],
-            commutative_parents: vec![],
-            signatures: vec![],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
+            commutative_parents: vec![
+                CommutativeParent::without_delimiters("compilation_unit", "\n\n").restricted_to(
+                    vec![
+                        ChildrenGroup::with_separator(&["import_declaration"], "\n"),
+                        ChildrenGroup::with_separator(&["given_definition"], "\n"),
+                    ],
+                ),
+                // import a.{b => c, d}
+                CommutativeParent::new("namespace_selectors", "{", ", ", "}"),
+            ],
+            signatures: vec![
+                signature("import_declaration", vec![vec![]]),
+                signature(
+                    "given_definition",
+                    vec![vec![Field("name")], vec![Field("return_type")]],
+                ),
+                // namespace_selectors
+                signature("identifier", vec![vec![]]),
+                signature("arrow_renamed_identifier", vec![vec![Field("name")]]),
+            ],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "Scheme",
             alternate_names: &[],
             extensions: &["scm"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_scheme::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![CommutativeParent::from_query_without_delimiters(
                 r#"(program
                          (list (symbol) @keyword
@@ -980,42 +1686,87 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some((";", "")),
+        },
+        LangProfile {
+            name: "TLA+",
+            alternate_names: &["tlaplus"],
+            extensions: &["tla"],
+            file_names: &[],
+            path_patterns: &[],
+            language: tree_sitter_tlaplus::LANGUAGE.into(),
+            atomic_nodes: &[],
+            // operators must be defined before they are used, so module units can't commute
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
+            commutative_parents: vec![CommutativeParent::with_left_delimiter(
+                "extends", "EXTENDS ", ", ",
+            )],
+            signatures: vec![signature("identifier_ref", vec![vec![]])],
+            injections: None,
+            flattened_nodes: &[],
+            extra_comment_nodes: &[],
+            allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("(*", "*)")),
         },
         LangProfile {
             name: "Typescript",
             alternate_names: &[],
             extensions: &["ts", "mts", "cts"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: typescript_commutative_parents,
             signatures: typescript_signatures,
             injections: None,
             flattened_nodes: typescript_flattened_nodes,
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "Typescript (TSX)",
             alternate_names: &[],
             extensions: &["tsx"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_typescript::LANGUAGE_TSX.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: tsx_commutative_parents,
             signatures: tsx_signatures,
             injections: None,
             flattened_nodes: tsx_flattened_nodes,
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "Python",
             alternate_names: &["Python3"],
             extensions: &["py"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_python_orchard::LANGUAGE.into(),
             atomic_nodes: &["string", "dotted_name"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("module", "\n").restricted_to_groups(&[
                     &["import_statement", "import_from_statement"],
@@ -1031,6 +1782,27 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                     "class_definition",
                     "assignment", // class attribute declaration
                 ]]),
+                // The order of the fields of dataclasses determines the order of the parameters
+                // of their constructors, so only their methods and nested classes commute.
+                CommutativeParent::from_query_without_delimiters(
+                    r#"(decorated_definition
+  (decorator [
+    (identifier) @decorator
+    (attribute attribute: (identifier) @decorator)
+    (call function: [
+      (identifier) @decorator
+      (attribute attribute: (identifier) @decorator)
+    ])
+  ])
+  definition: (class_definition (block) @commutative)
+  (#eq? @decorator "dataclass"))"#,
+                    "\n\n",
+                )
+                .restricted_to_groups(&[&[
+                    "function_definition",
+                    "decorated_definition",
+                    "class_definition",
+                ]]),
                 CommutativeParent::without_delimiters("import_from_statement", ", ")
                     .restricted_to_groups(&[&["dotted_name"]]),
                 CommutativeParent::new("argument_list", "(", ", ", ")")
@@ -1049,9 +1821,23 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                 signature("import_from_statement", vec![vec![]]),
                 signature("class_definition", vec![vec![Field("name")]]),
                 signature("function_definition", vec![vec![Field("name")]]),
+                // only the accessor decorators are part of the signature, to tell apart the
+                // getter, setter and deleter of a property, so that definitions of the same
+                // name with other decorators are still reported as duplicates
                 signature(
                     "decorated_definition",
-                    vec![vec![Field("definition"), Field("name")]],
+                    vec![
+                        vec![Field("definition"), Field("name")],
+                        vec![
+                            ChildKind("decorator"),
+                            ChildWithSource("identifier", &["property"]),
+                        ],
+                        vec![
+                            ChildKind("decorator"),
+                            ChildKind("attribute"),
+                            ChildWithSource("identifier", &["getter", "setter", "deleter"]),
+                        ],
+                    ],
                 ),
                 signature("dotted_name", vec![vec![]]),
                 signature("keyword_argument", vec![vec![Field("name")]]),
@@ -1062,15 +1848,58 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &["docstring"],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
+        },
+        LangProfile {
+            name: "Prolog",
+            alternate_names: &[],
+            extensions: &["pl", "pro"],
+            file_names: &[],
+            path_patterns: &[],
+            language: tree_sitter_prolog::LANGUAGE.into(),
+            atomic_nodes: &[],
+            // clauses of a predicate are tried in order, so only the predicates
+            // exported or imported by module directives can commute
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
+            commutative_parents: vec![CommutativeParent::from_query(
+                r#"(clause term: (unary_operation
+   operand: (compound_term
+     functor: (atom) @directive (#any-of? @directive "module" "use_module")
+     argument: (list) @commutative)))"#,
+                "[",
+                ", ",
+                "]",
+            )],
+            signatures: vec![
+                // elements of export and import lists, such as `foo/2` or `op(700, xfx, ===)`
+                signature("binary_operation", vec![vec![]]),
+                signature("compound_term", vec![vec![]]),
+                signature("atom", vec![vec![]]),
+            ],
+            injections: None,
+            flattened_nodes: &[],
+            extra_comment_nodes: &[],
+            allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "PHP",
             alternate_names: &[],
             extensions: &["php", "phtml", "php3", "php4", "php5", "phps", "phpt"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_php::LANGUAGE_PHP.into(),
             // optional settings, explained below
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &["heredoc", "nowdoc"],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 // TODO: allow commutation between "use" and "require" statements, which is
                 // currently not possible as "require" statements appear as "expression_statement",
@@ -1112,14 +1941,21 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "Solidity",
             alternate_names: &[],
             extensions: &["sol"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_solidity::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("source_file", "\n"),
                 CommutativeParent::without_delimiters("contract_body", "\n"),
@@ -1129,56 +1965,84 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "Lua",
             alternate_names: &[],
             extensions: &["lua"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_lua::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![],
             signatures: vec![],
             injections: Some(tree_sitter_lua::INJECTIONS_QUERY),
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("--", "")),
         },
         LangProfile {
             name: "Ruby",
             alternate_names: &[],
             extensions: &["rb"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_ruby::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &["heredoc_body"],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![],
             signatures: vec![],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
         LangProfile {
             name: "Elixir",
             alternate_names: &[],
             extensions: &["ex", "exs"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_elixir::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![],
             signatures: vec![],
             injections: Some(tree_sitter_elixir::INJECTIONS_QUERY),
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
         LangProfile {
             name: "Nix",
             alternate_names: &[],
             extensions: &["nix"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_nix::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::new("binding_set", "{", "\n", "}"),
                 CommutativeParent::new("formals", "{", ",\n", "}"),
@@ -1191,26 +2055,36 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "SystemVerilog",
             alternate_names: &[],
             extensions: &["sv", "svh"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_systemverilog::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![],
             signatures: vec![],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "Markdown",
             alternate_names: &[],
             extensions: &["md"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_md::LANGUAGE.into(),
             atomic_nodes: &[
                 "inline",
@@ -1222,6 +2096,10 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                 "pipe_table_delimiter_cell",
                 "pipe_table_cell",
             ],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("section", "\n\n").restricted_to(vec![
                     ChildrenGroup::with_separator(&["link_reference_definition"], "\n"),
@@ -1232,56 +2110,84 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("<!--", "-->")),
         },
         LangProfile {
             name: "HCL",
             alternate_names: &[],
             extensions: &["hcl", "tf", "tfvars"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_hcl::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![],
             signatures: vec![],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("/*", "*/")),
         },
         LangProfile {
             name: "OCaml",
             alternate_names: &[],
             extensions: &["ml"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_ocaml::LANGUAGE_OCAML.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: ocaml_commutative_parents.clone(),
             signatures: ocaml_signatures.clone(),
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("(*", "*)")),
         },
         LangProfile {
             name: "OCaml interfaces",
             alternate_names: &[],
             extensions: &["mli"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_ocaml::LANGUAGE_OCAML_TYPE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: ocaml_commutative_parents,
             signatures: ocaml_signatures,
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("(*", "*)")),
         },
         LangProfile {
             name: "Haskell",
             alternate_names: &[],
             extensions: &["hs"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_haskell::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("imports", "\n"),
                 CommutativeParent::new("import_list", "(", ", ", ")"),
@@ -1290,6 +2196,9 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                 CommutativeParent::new("children", "(", ", ", ")"), // children of types and typeclasses in imports and exports.
                 CommutativeParent::new("record", "{", ",\n", "}")
                     .restricted_to_groups(&[&["field_update"]]),
+                // the fields of record constructors, unlike positional ones, can be reordered
+                CommutativeParent::new("fields", "{", ",\n", "}")
+                    .restricted_to_groups(&[&["field"]]),
                 CommutativeParent::from_query("(deriving (tuple) @commutative)", "(", ", ", ")"),
                 CommutativeParent::without_delimiters("declarations", "\n").restricted_to_groups(
                     // notably leaving out: functions and TemplateHaskell splices
@@ -1324,14 +2233,21 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("{-", "-}")),
         },
         LangProfile {
             name: "GNU Make",
             alternate_names: &[],
             extensions: &["mk"],
             file_names: &["Makefile", "GNUmakefile"],
+            path_patterns: &[],
             language: tree_sitter_make::LANGUAGE.into(),
             atomic_nodes: &["recipe_line", "shell_command", "raw_text"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("prerequisites", " "),
                 CommutativeParent::without_delimiters("list", " "),
@@ -1345,14 +2261,58 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
+        },
+        LangProfile {
+            name: "Automake",
+            alternate_names: &["automake"],
+            extensions: &[],
+            file_names: &["Makefile.am"],
+            path_patterns: &[],
+            language: tree_sitter_make::LANGUAGE.into(),
+            atomic_nodes: &["recipe_line", "shell_command", "raw_text"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &["text"],
+            commutative_parents: vec![
+                CommutativeParent::without_delimiters("prerequisites", " "),
+                CommutativeParent::without_delimiters("list", " "),
+                CommutativeParent::without_delimiters("pattern_list", " "),
+                // The files listed in those variables can be given in any order.
+                // Values spanning multiple lines or referring to other variables are left alone.
+                CommutativeParent::from_query_without_delimiters(
+                    r#"(variable_assignment
+                         name: (word) @name (#match? @name "(_SOURCES|_HEADERS|_DATA|^EXTRA_DIST|^CLEANFILES)$")
+                         value: (text) @commutative (#not-match? @commutative "[$\n]"))"#,
+                    " ",
+                ),
+            ],
+            signatures: vec![
+                signature("variable_assignment", vec![vec![Field("name")]]),
+                signature("rule", vec![vec![Field("target")]]),
+                signature("@virtual_word@", vec![vec![]]),
+            ],
+            injections: None,
+            flattened_nodes: &[],
+            extra_comment_nodes: &[],
+            allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
         LangProfile {
             name: "Starlark",
             alternate_names: &["bazel", "bzl"],
             extensions: &["bzl", "bxl", "bazel", "star", "sky"],
             file_names: &["BUILD", "WORKSPACE", "BUCK", "PACKAGE"],
+            path_patterns: &[],
             language: tree_sitter_starlark::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 // The order of statements at module level doesn't matter.
                 CommutativeParent::without_delimiters("module", "\n")
@@ -1394,28 +2354,42 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
         LangProfile {
             name: "CMake",
             alternate_names: &["cmake"],
             extensions: &["cmake"],
             file_names: &["CMakeLists.txt"],
+            path_patterns: &[],
             language: tree_sitter_cmake::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![],
             signatures: vec![],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
         LangProfile {
             name: "Fortran",
             alternate_names: &[],
-            extensions: &["f", "for", "f90"],
+            extensions: &["f", "for", "f90", "f95", "f03", "f08"],
             file_names: &[],
+            path_patterns: &[],
             language: tree_sitter_fortran::LANGUAGE.into(),
             atomic_nodes: &[],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![
                 CommutativeParent::without_delimiters("program", "\n")
                     .restricted_to_groups(&[&["use_statement"]]),
@@ -1425,29 +2399,58 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                     .restricted_to_groups(&[&["identifier"]]),
                 CommutativeParent::without_delimiters("included_items", ",")
                     .restricted_to_groups(&[&["identifier"]]),
+                CommutativeParent::without_delimiters("translation_unit", "\n\n")
+                    .restricted_to_groups(&[&["program", "module", "subroutine", "function"]]),
+                CommutativeParent::without_delimiters("internal_procedures", "\n\n")
+                    .restricted_to_groups(&[&["subroutine", "function"]]),
             ],
             signatures: vec![
-                signature("use_statement", vec![vec![]]),
+                signature("use_statement", vec![vec![ChildKind("module_name")]]),
                 signature("identifier", vec![vec![]]),
+                signature(
+                    "program",
+                    vec![vec![ChildKind("program_statement"), ChildKind("name")]],
+                ),
+                signature(
+                    "module",
+                    vec![vec![ChildKind("module_statement"), ChildKind("name")]],
+                ),
+                signature(
+                    "subroutine",
+                    vec![vec![ChildKind("subroutine_statement"), Field("name")]],
+                ),
+                signature(
+                    "function",
+                    vec![vec![ChildKind("function_statement"), Field("name")]],
+                ),
             ],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("!", "")),
         },
         LangProfile {
             name: "R",
             alternate_names: &[],
             extensions: &["R", "r"],
             file_names: &[".Rprofile"],
+            path_patterns: &[],
             language: tree_sitter_r::LANGUAGE.into(),
             atomic_nodes: &["string"],
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![],
             signatures: vec![],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
         LangProfile {
             name: "pip-requirements",
@@ -1459,7 +2462,12 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
                 "requirements-docs.txt",
                 "requirements-test.txt",
             ],
+            path_patterns: &[],
             language: tree_sitter_requirements::LANGUAGE.into(),
+            unsplit_nodes: &[],
+            verbatim_nodes: &[],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
             commutative_parents: vec![CommutativeParent::without_delimiters("file", "\n")],
             signatures: vec![signature("requirement", vec![vec![ChildKind("package")]])],
             atomic_nodes: &[],
@@ -1467,21 +2475,40 @@ pub static SUPPORTED_LANGUAGES: LazyLock<Vec<LangProfile>> = LazyLock::new(|| {
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
         LangProfile {
             name: "bash",
             alternate_names: &[],
             extensions: &["sh", "bash"],
             file_names: &["PKGBUILD"],
+            path_patterns: &[],
             language: tree_sitter_bash::LANGUAGE.into(),
             atomic_nodes: &[],
             // variable declarations in front of commands might be commutative, but i am not sure enough to make that call
-            commutative_parents: vec![],
-            signatures: vec![],
+            unsplit_nodes: &[],
+            verbatim_nodes: &["heredoc_body", "heredoc_end"],
+            whitespace_nodes: &[],
+            word_list_nodes: &[],
+            commutative_parents: vec![
+                // functions and variables defined next to each other at the top level
+                // can be added in any order
+                CommutativeParent::without_delimiters("program", "\n\n").restricted_to(vec![
+                    ChildrenGroup::with_separator(&["variable_assignment"], "\n"),
+                    ChildrenGroup::new(&["function_definition"]),
+                ]),
+            ],
+            signatures: vec![
+                signature("variable_assignment", vec![vec![Field("name")]]),
+                signature("function_definition", vec![vec![Field("name")]]),
+            ],
             injections: None,
             flattened_nodes: &[],
             extra_comment_nodes: &[],
             allow_parse_errors: false,
+            commute_order_sensitive: false,
+            comment_delimiters: Some(("#", "")),
         },
     ]
 });
@@ -32,12 +32,14 @@ pub(crate) fn json_matchers() -> (TreeMatcher, TreeMatcher) {
         sim_threshold: 0.5,
         max_recovery_size: 100,
         use_rted: true,
+        detect_renames: false,
     };
     let auxiliary_matcher = TreeMatcher {
         min_height: 1,
         sim_threshold: 0.5,
         max_recovery_size: 100,
         use_rted: false,
+        detect_renames: false,
     };
     (primary_matcher, auxiliary_matcher)
 }
@@ -1,5 +1,7 @@
 use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
+use std::iter::zip;
+use std::ptr;
 use thiserror::Error;
 
 use either::Either;
@@ -13,11 +15,17 @@ use crate::{
     ast::AstNode,
     changeset::ChangeSet,
     class_mapping::{ClassMapping, Leader, RevNode, RevisionNESet, RevisionSet},
+    commutative_report::{commutative_report_enabled, report_commutation},
     lang_profile::CommutativeParent,
     merged_tree::MergedTree,
     multimap::MultiMap,
+    node_trace::{
+        conflict_contains_traced, contains_traced, is_traced_class, is_traced_pcs_node, trace_node,
+        traced_node,
+    },
     pcs::{PCSNode, Revision},
-    settings::DisplaySettings,
+    settings::{DeleteModifyStrategy, DisplaySettings},
+    structured::StructuredMergeOptions,
 };
 
 /// An internal structure to map a parent and a predecessor to a possible successor in each revision
@@ -54,6 +62,7 @@ pub struct TreeBuilder<'a, 'b> {
     base_successors: SuccessorMap<'a>,
     class_mapping: &'b ClassMapping<'a>,
     settings: &'b DisplaySettings<'a>,
+    options: &'b StructuredMergeOptions,
 }
 
 /// Variable state, keeping track of visited nodes to avoid looping
@@ -99,12 +108,14 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
         base_changeset: &ChangeSet<'a>,
         class_mapping: &'b ClassMapping<'a>,
         settings: &'b DisplaySettings<'a>,
+        options: &'b StructuredMergeOptions,
     ) -> Self {
         TreeBuilder {
             merged_successors: SuccessorMap::new(merged_changeset),
             base_successors: SuccessorMap::new(base_changeset),
             class_mapping,
             settings,
+            options,
         }
     }
 
@@ -134,29 +145,75 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
             .collect();
         debug!("really deleted children: {}", deleted.iter().format(", "));
 
-        let parents_to_recompute: HashSet<Leader<'a>> = deleted
+        let deleted_with_parents: Vec<(Leader<'a>, Leader<'a>)> = deleted
             .into_iter()
             .map(|deleted| {
                 let RevNode { rev, node } = deleted.as_representative();
-                self.class_mapping.map_to_leader(RevNode::new(
+                let parent = self.class_mapping.map_to_leader(RevNode::new(
                     rev,
                     node.parent().expect(
                         "the root node is marked as deleted and modified, \
                         but all roots should be mapped together",
                     ),
-                ))
+                ));
+                (deleted, parent)
             })
             .collect();
+        let parents_to_recompute: HashSet<Leader<'a>> = deleted_with_parents
+            .iter()
+            .map(|(_, parent)| *parent)
+            .collect();
         debug!(
             "parents to recompute: {}",
             parents_to_recompute.iter().format(", ")
         );
+        for parent in &parents_to_recompute {
+            if contains_traced(parent, self.class_mapping) {
+                trace_node!(
+                    "{parent} falls back on a line-based merge, because one of its children was deleted and modified"
+                );
+            }
+        }
 
-        Ok(merged_tree.force_line_based_fallback_on_specific_nodes(
+        let merged_tree = merged_tree.force_line_based_fallback_on_specific_nodes(
             &parents_to_recompute,
             self.class_mapping,
             self.settings,
-        ))
+        );
+        Ok(match self.options.delete_modify {
+            DeleteModifyStrategy::Conflict => merged_tree,
+            DeleteModifyStrategy::KeepModified => merged_tree
+                .keep_side_in_line_based_merges(&self.modifying_sides(&deleted_with_parents)),
+        })
+    }
+
+    /// For each parent of nodes deleted on one side and modified on the other, the side
+    /// which modified them, if it is the same for all of them
+    fn modifying_sides(
+        &self,
+        deleted_with_parents: &[(Leader<'a>, Leader<'a>)],
+    ) -> HashMap<Leader<'a>, Revision> {
+        let mut sides: HashMap<Leader<'a>, Option<Revision>> = HashMap::new();
+        for (deleted, parent) in deleted_with_parents {
+            let in_left = self.class_mapping.node_at_rev(deleted, Revision::Left);
+            let in_right = self.class_mapping.node_at_rev(deleted, Revision::Right);
+            let side = match (in_left, in_right) {
+                (Some(_), None) => Some(Revision::Left),
+                (None, Some(_)) => Some(Revision::Right),
+                _ => None,
+            };
+            sides
+                .entry(*parent)
+                .and_modify(|previous| {
+                    if *previous != side {
+                        *previous = None;
+                    }
+                })
+                .or_insert(side);
+        }
+        (sides.into_iter())
+            .filter_map(|(parent, side)| Some((parent, side?)))
+            .collect()
     }
 
     /// Recursive function to build the merged subtree rooted in a node,
@@ -206,6 +263,9 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
                 revisions
             };
 
+            if is_traced_class(&leader, self.class_mapping) {
+                trace_node!("{leader} is isomorphic in all revisions, kept as is");
+            }
             return Ok(MergedTree::new_exact(
                 leader,
                 final_revisions,
@@ -224,6 +284,9 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
 
         let pad = visiting_state.indentation();
         trace!("{pad}{node} build_subtree_from_changeset");
+        if is_traced_pcs_node(&node, self.class_mapping) {
+            trace_node!("building the merged children of {node} from the PCS triples");
+        }
 
         loop {
             match cursor.len() {
@@ -280,7 +343,9 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
 
                     if let PCSNode::Node { node: leader, .. } = node
                         && let Some(commutative_parent) = leader.commutative_parent_definition()
+                        && self.options.allows_commutative_parent(leader.kind())
                         && let Ok(solved_conflict) = self.commutatively_merge_lists(
+                            leader,
                             &base,
                             &left,
                             &right,
@@ -290,6 +355,23 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
                     {
                         children.extend(solved_conflict);
                     } else {
+                        if let PCSNode::Node { node: leader, .. } = node {
+                            let size: usize = (base.iter().chain(&left).chain(&right))
+                                .map(|side| side.source.len())
+                                .sum();
+                            if self.settings.exceeds_max_conflict_size(size) {
+                                if is_traced_class(&leader, self.class_mapping) {
+                                    trace_node!(
+                                        "{leader} has a conflict of {size} bytes among its children, falling back on a line-based merge"
+                                    );
+                                }
+                                return Ok(MergedTree::line_based_local_fallback_for_revnode(
+                                    leader,
+                                    self.class_mapping,
+                                    self.settings,
+                                ));
+                            }
+                        }
                         children.extend(MergedTree::new_conflict(
                             base,
                             left,
@@ -424,6 +506,22 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
                         common_revisions,
                         self.class_mapping,
                     ))
+                } else if self.options.commutative_only_resolution
+                    && !self.is_merged_commutatively(&revnode)
+                    && let sides = self.sides_changed(&children)
+                    && sides.contains(Revision::Left)
+                    && sides.contains(Revision::Right)
+                {
+                    if is_traced_class(&revnode, self.class_mapping) {
+                        trace_node!(
+                            "{revnode} combines changes from both sides outside of a commutative parent, falling back on a line-based merge"
+                        );
+                    }
+                    Ok(MergedTree::line_based_local_fallback_for_revnode(
+                        revnode,
+                        self.class_mapping,
+                        self.settings,
+                    ))
                 } else {
                     Ok(MergedTree::new_mixed(revnode, children))
                 }
@@ -483,14 +581,32 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
                 fmt_set(end_right)
             ))
         } else {
-            Ok((
-                end_base,
-                Conflict {
-                    base: list_base,
-                    left: list_left,
-                    right: list_right,
-                },
-            ))
+            let conflict = Conflict {
+                base: list_base,
+                left: list_left,
+                right: list_right,
+            };
+            if conflict_contains_traced(&conflict) {
+                trace_node!(
+                    "conflict after {predecessor}: base: [{}], left: [{}], right: [{}]",
+                    conflict
+                        .base
+                        .iter()
+                        .map(|n| RevNode::new(Revision::Base, n))
+                        .format(", "),
+                    conflict
+                        .left
+                        .iter()
+                        .map(|n| RevNode::new(Revision::Left, n))
+                        .format(", "),
+                    conflict
+                        .right
+                        .iter()
+                        .map(|n| RevNode::new(Revision::Right, n))
+                        .format(", "),
+                );
+            }
+            Ok((end_base, conflict))
         }
     }
 
@@ -551,6 +667,45 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
         }
     }
 
+    /// Whether the children of the node can be merged commutatively
+    fn is_merged_commutatively(&self, node: &Leader<'a>) -> bool {
+        node.commutative_parent_definition().is_some()
+            && self.options.allows_commutative_parent(node.kind())
+    }
+
+    /// The sides (among left and right) whose changes the merged trees contain, not counting
+    /// the commutative parents and conflicts among them, which combine changes on purpose.
+    fn sides_changed(&self, trees: &[MergedTree<'a>]) -> RevisionSet {
+        let mut sides = RevisionSet::new();
+        for tree in trees {
+            match tree {
+                MergedTree::ExactTree { revisions, .. } => {
+                    // a tree which is only found on one side was changed by that side
+                    for (side, other_side) in [
+                        (Revision::Left, Revision::Right),
+                        (Revision::Right, Revision::Left),
+                    ] {
+                        if revisions.contains(side) && !revisions.contains(other_side) {
+                            sides.add(side);
+                        }
+                    }
+                }
+                MergedTree::MixedTree { node, children, .. }
+                    if !self.is_merged_commutatively(node) =>
+                {
+                    for side in self.sides_changed(children).iter() {
+                        sides.add(side);
+                    }
+                }
+                MergedTree::MixedTree { .. }
+                | MergedTree::Conflict { .. }
+                | MergedTree::LineBasedMerge { .. }
+                | MergedTree::CommutativeChildSeparator { .. } => {}
+            }
+        }
+        sides
+    }
+
     /// Attempt to merge the children of the given node commutatively, if the node
     /// is indeed a commutative parent. If that fails, fall back on line-based merging.
     fn commutative_or_line_based_local_fallback(
@@ -563,13 +718,25 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
         let PCSNode::Node { node, .. } = node else {
             return Err(TreeBuildingError::LineBasedFallbackOnVirtualNode).debug_panic();
         };
+        let traced = contains_traced(&node, self.class_mapping);
         // If the root happens to be commutative, we can merge all children accordingly.
         if let Some(commutative_parent) = node.commutative_parent_definition()
+            && self.options.allows_commutative_parent(node.kind())
             && let Ok(commutative_merge) =
                 self.commutatively_merge_children(&node, commutative_parent, visiting_state)
         {
+            if traced {
+                trace_node!(
+                    "{node} children could not be merged from the PCS triples, merged them commutatively"
+                );
+            }
             Ok(MergedTree::new_mixed(node, commutative_merge))
         } else {
+            if traced {
+                trace_node!(
+                    "{node} children could not be merged from the PCS triples, falling back on a line-based merge"
+                );
+            }
             Ok(MergedTree::line_based_local_fallback_for_revnode(
                 node,
                 self.class_mapping,
@@ -600,32 +767,83 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
     }
 
     /// Collects examples of separators with the surrounding whitespace
-    /// among a list of children of a commutative parent.
+    /// among a list of children of a commutative parent, only considering
+    /// the separators between two elements accepted by `spaced_alike`.
     fn find_separators_with_whitespace<'s>(
-        slice: &'s [&'a AstNode<'a>],
+        children: &'s [&'a AstNode<'a>],
         trimmed_sep: &'s str,
+        trimmed_left_delim: &'s str,
+        trimmed_right_delim: &'s str,
+        spaced_alike: impl Fn(&AstNode) -> bool + 's,
     ) -> impl Iterator<Item = &'a str> {
         if trimmed_sep.is_empty() {
+            // the whitespace between the delimiters and the elements isn't a separator
             Either::Left(
-                slice
+                children
                     .iter()
-                    .skip(1)
-                    .filter_map(|node| node.preceding_whitespace())
+                    .filter(move |node| {
+                        let trimmed = node.source.trim();
+                        trimmed != trimmed_left_delim && trimmed != trimmed_right_delim
+                    })
+                    .tuple_windows()
+                    .filter(move |(previous, next)| spaced_alike(previous) && spaced_alike(next))
+                    .filter_map(|(_, next)| next.preceding_whitespace())
                     .filter(|s| !s.is_empty()),
             )
         } else {
             Either::Right(
-                slice
+                children
                     .iter()
-                    .filter(move |n| n.source.trim() == trimmed_sep)
-                    .map(|n| n.source_with_surrounding_whitespace()),
+                    .tuple_windows()
+                    .filter(move |(previous, separator, next)| {
+                        separator.source.trim() == trimmed_sep
+                            && spaced_alike(previous)
+                            && spaced_alike(next)
+                    })
+                    .map(|(_, separator, _)| separator.source_with_surrounding_whitespace()),
             )
         }
     }
 
+    /// Picks the separator used most often between the children of a commutative parent,
+    /// so that the elements inserted by the merge are spaced like the existing ones.
+    /// The indentation at the end of the separator is removed: it is added back when
+    /// pretty-printing, possibly at a different level. Leading separators, which come
+    /// after this indentation, are kept as they are.
+    fn dominant_separator(
+        children: &[&'a AstNode<'a>],
+        trimmed_sep: &str,
+        trimmed_left_delim: &str,
+        trimmed_right_delim: &str,
+        spaced_alike: impl Fn(&AstNode) -> bool,
+    ) -> Option<&'a str> {
+        let separators = Self::find_separators_with_whitespace(
+            children,
+            trimmed_sep,
+            trimmed_left_delim,
+            trimmed_right_delim,
+            spaced_alike,
+        )
+        .map(|separator| match separator.rfind('\n') {
+            // with leading separators (such as in `\n  , `), the separator itself comes after
+            // the indentation, so it is kept as is
+            Some(index) if separator[(index + 1)..].trim().is_empty() => &separator[..(index + 1)],
+            _ => separator,
+        })
+        .collect_vec();
+        let counts = separators.iter().counts();
+        // in case of a tie, pick the separator which appears first
+        separators
+            .iter()
+            .rev()
+            .max_by_key(|separator| counts[separator])
+            .copied()
+    }
+
     /// Merge three lists of nodes, knowing that their order does not matter
     fn commutatively_merge_lists(
         &self,
+        parent: Leader<'a>,
         base: &[&'a AstNode<'a>],
         left: &[&'a AstNode<'a>],
         right: &[&'a AstNode<'a>],
@@ -684,12 +902,62 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
             .collect();
         trace!("{pad}right_added: {}", right_added.iter().format(", "));
 
+        // elements left unidentified by their signature definition (see `SignatureDefinition::optional`)
+        // can't be told apart from one another, so we don't take the liberty of reordering them
+        if (left_added.iter().copied())
+            .chain(right_added.iter().copied())
+            .any(|leader| {
+                let node = leader.as_representative().node;
+                node.signature_definition().is_some() && node.signature().is_none()
+            })
+        {
+            return Err(TreeBuildingError::UncommutableChildren);
+        }
+
+        // describe the additions now, as they are only reported if the merge succeeds
+        let report =
+            (commutative_report_enabled() && !left_added.is_empty() && !right_added.is_empty())
+                .then(|| {
+                    let describe = |leaders: &mut dyn Iterator<Item = &Leader<'a>>| {
+                        leaders
+                            .map(|leader| {
+                                let node = leader.as_representative().node;
+                                node.signature().map_or_else(
+                                    || node.source.lines().next().unwrap_or_default().to_string(),
+                                    |signature| signature.to_string(),
+                                )
+                            })
+                            .join(", ")
+                    };
+                    format!(
+                        "{parent}: combined {} added by left with {} added by right",
+                        describe(&mut left_leaders.iter().filter(|x| left_added.contains(x))),
+                        describe(&mut right_added.iter().copied()),
+                    )
+                });
+
         // then, compute the symmetric difference between the base and right lists
+        // (with a set for the right elements, to keep this linear for long lists)
+        let right_leaders_set: HashSet<_> = right_leaders.iter().collect();
         let right_removed: HashSet<Leader<'_>> = base_leaders
             .into_iter()
-            .filter(|x| !right_leaders.contains(x))
+            .filter(|x| !right_leaders_set.contains(x))
             .collect();
         trace!("{pad}right_removed: {}", right_removed.iter().format(", "));
+        if traced_node().is_some() {
+            for (leaders, action) in [
+                (left_added.iter().copied().collect_vec(), "added by left"),
+                (right_added.iter().copied().collect_vec(), "added by right"),
+                (right_removed.iter().collect_vec(), "removed by right"),
+            ] {
+                if let Some(leader) = leaders
+                    .into_iter()
+                    .find(|leader| is_traced_class(leader, self.class_mapping))
+                {
+                    trace_node!("{leader} {action} in a commutative merge");
+                }
+            }
+        }
         // check which right removed elements have been modified on the left-hand side,
         // in which case they should be kept
         let mut removed_visiting_state = visiting_state.clone();
@@ -778,20 +1046,59 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
                 == Some(trimmed_sep)
         });
 
+        // if the lists being merged don't contain any separator, look at the other children of
+        // the parent, but only at those of the same children group (or of the same kinds as the
+        // merged elements, if there are no groups), as other elements can be spaced differently
+        let merged_kinds: HashSet<_> = (base.iter().chain(left).chain(right))
+            .filter(|node| {
+                let trimmed = node.source.trim();
+                trimmed != trimmed_sep
+                    && trimmed != trimmed_left_delim
+                    && trimmed != trimmed_right_delim
+            })
+            .map(|node| node.kind)
+            .collect();
+        let spaced_alike_kinds = (commutative_parent.children_groups.iter())
+            .find(|group| merged_kinds.is_subset(&group.node_types))
+            .map_or(&merged_kinds, |group| &group.node_types);
+        let spaced_alike = |node: &AstNode| spaced_alike_kinds.contains(node.kind);
+        let revisions = [
+            (left, Revision::Left),
+            (right, Revision::Right),
+            (base, Revision::Base),
+        ];
+        let all_children = revisions.map(|(_, revision)| {
+            (self.class_mapping.node_at_rev(&parent, revision))
+                .map_or(&[][..], |parent| &parent.children)
+        });
+        // the lists being merged, extended with their neighbouring element (and separator) on
+        // each side, so that the spacing right next to them takes precedence over the one used
+        // elsewhere in the parent. In case of a tie, the spacing before them wins.
+        let margin = if trimmed_sep.is_empty() { 1 } else { 2 };
+        let surroundings = zip(revisions, all_children).map(|((slice, _), children)| {
+            let start = slice
+                .first()
+                .and_then(|first| (children.iter()).position(|child| ptr::eq(*child, *first)));
+            start.map_or(&[][..], |start| {
+                let end = (start + slice.len() + margin).min(children.len());
+                &children[start.saturating_sub(margin)..end]
+            })
+        });
         let separator = MergedTree::CommutativeChildSeparator {
-            separator: Self::find_separators_with_whitespace(left, trimmed_sep)
-                .chain(Self::find_separators_with_whitespace(right, trimmed_sep))
-                .chain(Self::find_separators_with_whitespace(base, trimmed_sep))
-                // remove the indentation at the end of separators
-                // (it will be added back when pretty-printing, possibly at a different level)
-                .next()
-                .map_or(raw_separator, |separator| {
-                    let newline = separator.rfind('\n');
-                    match newline {
-                        None => separator,
-                        Some(index) => &separator[..(index + 1)],
-                    }
-                }),
+            separator: [left, right, base]
+                .into_iter()
+                .chain(surroundings)
+                .chain(all_children)
+                .find_map(|children| {
+                    Self::dominant_separator(
+                        children,
+                        trimmed_sep,
+                        trimmed_left_delim,
+                        trimmed_right_delim,
+                        spaced_alike,
+                    )
+                })
+                .unwrap_or(raw_separator),
         };
 
         // add delimiters and separators in the merged list
@@ -823,6 +1130,9 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
             ));
         }
 
+        if let Some(report) = report {
+            report_commutation!("{report}");
+        }
         Ok(with_separators)
     }
 
@@ -896,6 +1206,7 @@ impl<'a, 'b> TreeBuilder<'a, 'b> {
             .collect_vec();
 
         let mut merge_result = self.commutatively_merge_lists(
+            *leader,
             &base,
             &left,
             &right,
@@ -1059,14 +1370,20 @@ mod tests {
         changeset.add_tree(tree, Revision::Base, &class_mapping);
 
         let settings = DisplaySettings::default();
+        let options = StructuredMergeOptions::default();
 
         let result_tree = {
             let merged_changeset = &changeset;
             let base_changeset = &changeset;
             let class_mapping = &class_mapping;
             // build the necessary context for the tree-gathering algorithm
-            let tree_gatherer =
-                TreeBuilder::new(merged_changeset, base_changeset, class_mapping, &settings);
+            let tree_gatherer = TreeBuilder::new(
+                merged_changeset,
+                base_changeset,
+                class_mapping,
+                &settings,
+                &options,
+            );
             tree_gatherer.build_tree()
         };
 
@@ -1091,14 +1408,20 @@ mod tests {
         changeset.add_tree(tree, Revision::Base, &class_mapping);
 
         let settings = DisplaySettings::default();
+        let options = StructuredMergeOptions::default();
 
         let result_tree = {
             let merged_changeset = &changeset;
             let base_changeset = &changeset;
             let class_mapping = &class_mapping;
             // build the necessary context for the tree-gathering algorithm
-            let tree_gatherer =
-                TreeBuilder::new(merged_changeset, base_changeset, class_mapping, &settings);
+            let tree_gatherer = TreeBuilder::new(
+                merged_changeset,
+                base_changeset,
+                class_mapping,
+                &settings,
+                &options,
+            );
             tree_gatherer.build_tree()
         }
         .expect("a successful merge was expected");
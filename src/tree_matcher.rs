@@ -29,6 +29,9 @@ pub struct TreeMatcher {
     pub use_rted: bool,
     /// The maximum size of trees to match with tree edit distance
     pub max_recovery_size: i32,
+    /// Whether to match the remaining unmatched nodes with signatures (such as methods)
+    /// to similar siblings, in case they were renamed
+    pub detect_renames: bool,
 }
 
 /// A matching which keeps track of how each link was inferred, for visualization purposes
@@ -73,10 +76,15 @@ impl TreeMatcher {
             self.bottom_up_pass(truncated_left, truncated_right, &mut truncated_matching);
         debug!("matching took {:?}", start.elapsed());
         let mut full = matching;
-        let container = container_matching.translate(left, right);
-        let recovery = recovery_matches.translate(left, right);
+        let mut container = container_matching.translate(left, right);
+        let mut recovery = recovery_matches.translate(left, right);
         full.add_matching(&container);
         full.add_matching(&recovery);
+
+        // Third, optional pass matching renamed nodes
+        if self.detect_renames {
+            self.rename_pass(left, &mut full, &mut recovery, &mut container);
+        }
         DetailedMatching {
             full,
             exact: exact_matching,
@@ -217,6 +225,79 @@ impl TreeMatcher {
         (container_matching, recovery_matches)
     }
 
+    /// Matches nodes with signatures which were left unmatched, but whose parent is matched,
+    /// to an unmatched sibling of the same type if their leaves are similar enough.
+    /// This recovers the nodes which were renamed and otherwise modified.
+    fn rename_pass<'a>(
+        &self,
+        left: &'a AstNode<'a>,
+        matching: &mut Matching<'a>,
+        recovery_matching: &mut Matching<'a>,
+        container_matching: &mut Matching<'a>,
+    ) {
+        let unmatched_with_signature = |node: &'a AstNode<'a>, matching: &Matching<'a>, left| {
+            node.signature_definition().is_some()
+                && if left {
+                    matching.get_from_left(node).is_none()
+                } else {
+                    matching.get_from_right(node).is_none()
+                }
+        };
+        let renamed_candidates = left
+            .dfs()
+            .filter(|node| unmatched_with_signature(node, matching, true))
+            .collect_vec();
+        for left_node in renamed_candidates {
+            if matching.get_from_left(left_node).is_some() {
+                // matched by the recovery of an ancestor
+                continue;
+            }
+            let Some(right_parent) = (left_node.parent()).and_then(|p| matching.get_from_left(p))
+            else {
+                continue;
+            };
+            let best_candidate = (right_parent.children.iter().copied())
+                .filter(|candidate| {
+                    candidate.kind == left_node.kind
+                        && unmatched_with_signature(candidate, matching, false)
+                })
+                .map(|candidate| (candidate, Self::leaf_similarity(left_node, candidate)))
+                .filter(|(_, sim)| *sim > self.sim_threshold)
+                .max_by(|(_, sim_1), (_, sim_2)| sim_1.total_cmp(sim_2));
+            if let Some((right_node, sim)) = best_candidate {
+                debug!(
+                    "matching {} to {} as a rename, with similarity {sim}",
+                    left_node.kind, right_node.kind
+                );
+                self.last_chance_match(
+                    left_node,
+                    right_node,
+                    matching,
+                    recovery_matching,
+                    container_matching,
+                );
+            }
+        }
+    }
+
+    /// The dice similarity of the multisets of leaves of both nodes
+    fn leaf_similarity<'a>(left: &'a AstNode<'a>, right: &'a AstNode<'a>) -> f32 {
+        let leaves = |node: &'a AstNode<'a>| {
+            node.dfs()
+                .filter(|n| n.is_leaf())
+                .map(|n| n.source)
+                .counts()
+        };
+        let left_leaves = leaves(left);
+        let right_leaves = leaves(right);
+        let common: usize = (left_leaves.iter())
+            .map(|(leaf, count)| min(*count, right_leaves.get(leaf).copied().unwrap_or(0)))
+            .sum();
+        let total: usize =
+            left_leaves.values().sum::<usize>() + right_leaves.values().sum::<usize>();
+        2.0_f32 * (common as f32) / (total as f32)
+    }
+
     /// In the bottom up phase, finds candidates for matching a node,
     /// based on the pre-existing matches of its descendants
     fn find_candidates<'src>(
@@ -514,6 +595,7 @@ mod tests {
             sim_threshold: 0.5,
             max_recovery_size: 100,
             use_rted: true,
+            detect_renames: false,
         };
 
         let detailed_matching = matcher.match_trees(t1, t2, None);
@@ -539,6 +621,7 @@ mod tests {
             sim_threshold: 0.5,
             max_recovery_size: 100,
             use_rted: true,
+            detect_renames: false,
         };
 
         let matching = matcher.match_trees(t1, t2, None);
@@ -564,6 +647,7 @@ mod tests {
             sim_threshold: 0.5,
             max_recovery_size: 100,
             use_rted: false,
+            detect_renames: false,
         };
 
         let matching = matcher.match_trees(t1, t2, None);
@@ -586,6 +670,7 @@ mod tests {
             sim_threshold: 0.5,
             max_recovery_size: 100,
             use_rted: true,
+            detect_renames: false,
         };
 
         let matching = matcher.match_trees(left, right, None);
@@ -608,6 +693,7 @@ mod tests {
             sim_threshold: 0.5,
             max_recovery_size: 100,
             use_rted: true,
+            detect_renames: false,
         };
         let matching = matcher.match_trees(left, right, None);
 
@@ -616,4 +702,58 @@ mod tests {
         assert_eq!(matching.recovery.len(), 14);
         assert_eq!(matching.full.len(), 14);
     }
+
+    #[test]
+    fn matching_renamed_methods() {
+        let ctx = ctx();
+        let base = ctx.parse(
+            "a.java",
+            "\
+class A {
+  int first() { return 1; }
+  void compute() {
+    a = b;
+    c = d;
+    e = f;
+  }
+  int last() { return 3; }
+}",
+        );
+        let renamed = ctx.parse(
+            "a.java",
+            "\
+class A {
+  int first() { return 1; }
+  void computeAll() {
+    this.a = b.get();
+    this.c = d.get();
+    this.e = f.get();
+  }
+  int last() { return 3; }
+}",
+        );
+        let method = base[0][2][2];
+        let renamed_method = renamed[0][2][2];
+        assert_eq!(method.kind, "method_declaration");
+
+        // a low recovery size, as for large files, makes the recovery match children by signature
+        let mut matcher = TreeMatcher {
+            min_height: 1,
+            sim_threshold: 0.5,
+            max_recovery_size: 5,
+            use_rted: true,
+            detect_renames: false,
+        };
+        let matching = matcher.match_trees(base, renamed, None);
+        assert_eq!(matching.full.get_from_left(method), None);
+
+        matcher.detect_renames = true;
+        let matching = matcher.match_trees(base, renamed, None);
+        assert_eq!(matching.full.get_from_left(method), Some(renamed_method));
+        // the contents of the method are matched too
+        assert_eq!(
+            matching.full.get_from_left(method[2]),
+            Some(renamed_method[2])
+        );
+    }
 }
@@ -6,6 +6,8 @@ use std::path::Path;
 use itertools::Itertools;
 use log::error;
 
+#[cfg(feature = "dev")]
+use crate::tree_matcher::TreeMatcher;
 use crate::{ast::AstNode, tree_matcher::DetailedMatching};
 
 const COLOR_EXACTLY_MATCHED_NODE: &str = "#ff2222";
@@ -30,6 +32,25 @@ pub fn write_matching_to_dotty_file<'a>(
     }
 }
 
+/// Matches two trees in the same way as revisions are matched to the base one during a merge,
+/// and renders the matching as a dotty graph
+#[cfg(feature = "dev")]
+pub fn write_two_way_matching_to_dotty_file<'a>(
+    path: impl AsRef<Path>,
+    left: &'a AstNode<'a>,
+    right: &'a AstNode<'a>,
+) -> io::Result<()> {
+    let matcher = TreeMatcher {
+        min_height: 1,
+        sim_threshold: 0.4,
+        max_recovery_size: 100,
+        use_rted: true,
+        detect_renames: false,
+    };
+    let matching = matcher.match_trees(left, right, None);
+    matching_to_graph(path.as_ref(), left, right, &matching)
+}
+
 fn matching_to_graph<'a>(
     path: &Path,
     left: &'a AstNode<'a>,
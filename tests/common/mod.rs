@@ -62,6 +62,15 @@ pub(crate) fn language_override_for_test(test_dir: &Path) -> Option<&'static str
     Some(lang_profile.name)
 }
 
+/// Whether a test case enables the commutative merging of order-sensitive elements
+/// (see `--commute-order-sensitive`), which it does by containing a `commute_order_sensitive` file.
+pub(crate) fn commute_order_sensitive_for_test(test_dir: &Path) -> Option<bool> {
+    test_dir
+        .join("commute_order_sensitive")
+        .exists()
+        .then_some(true)
+}
+
 #[track_caller]
 pub fn merge() -> Command {
     let mut cmd = Command::cargo_bin(pkg_name!()).unwrap();
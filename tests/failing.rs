@@ -13,7 +13,7 @@ use rstest::rstest;
 mod common;
 use common::detect_test_suffix;
 
-use crate::common::language_override_for_test;
+use crate::common::{commute_order_sensitive_for_test, language_override_for_test};
 
 #[derive(Clone, Copy)]
 enum FailingTestResult {
@@ -76,7 +76,9 @@ fn integration_failing(
         None,
         CliOpts {
             allow_parse_errors: Some(true),
+            commute_order_sensitive: commute_order_sensitive_for_test(&test_dir),
             language: language_override_for_test(&test_dir),
+            ..Default::default()
         },
         None,
         None,
@@ -146,6 +148,7 @@ please examine the new output and update ExpectedCurrently{suffix} if it looks o
         None,
         CliOpts {
             allow_parse_errors: Some(true),
+            commute_order_sensitive: commute_order_sensitive_for_test(&test_dir),
             ..Default::default()
         },
         None,
@@ -0,0 +1,99 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use assert_cmd::{pkg_name, prelude::*};
+
+mod common;
+use common::run_git;
+
+fn commit(repo_dir: &Path, message: &str) {
+    run_git(&["add", "."], repo_dir);
+    run_git(
+        &[
+            "-c",
+            "user.email=author@example.com",
+            "-c",
+            "user.name=Author",
+            "commit",
+            "--no-gpg-sign",
+            "-m",
+            message,
+        ],
+        repo_dir,
+    );
+}
+
+fn merge_without_conflicts(repo_dir: &Path, args: &[&str]) {
+    let mut full_args = vec![
+        "-c",
+        "user.email=author@example.com",
+        "-c",
+        "user.name=Author",
+        "merge",
+        "--no-gpg-sign",
+        "--no-edit",
+    ];
+    full_args.extend(args);
+    run_git(&full_args, repo_dir);
+}
+
+#[test]
+fn merge_history_writes_conflict_counts() {
+    let test_dir = Path::new("examples/java/working/demo");
+    let revision = |name| {
+        fs::read_to_string(test_dir.join(format!("{name}.java"))).expect("could not read revision")
+    };
+
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_dir = repo_dir.path();
+    run_git(&["init", "."], repo_dir);
+    run_git(&["checkout", "-b", "main"], repo_dir);
+    fs::write(repo_dir.join("Demo.java"), revision("Base")).unwrap();
+    commit(repo_dir, "base");
+
+    run_git(&["checkout", "-b", "right"], repo_dir);
+    fs::write(repo_dir.join("Demo.java"), revision("Right")).unwrap();
+    commit(repo_dir, "right");
+
+    // a merge whose line-based version has conflicts, which Mergiraf solves.
+    // How it was actually resolved doesn't matter, so we just keep our side.
+    run_git(&["checkout", "main"], repo_dir);
+    fs::write(repo_dir.join("Demo.java"), revision("Left")).unwrap();
+    commit(repo_dir, "left");
+    merge_without_conflicts(repo_dir, &["-s", "ours", "right"]);
+
+    // a merge of unrelated changes
+    run_git(&["checkout", "-b", "unrelated"], repo_dir);
+    fs::write(repo_dir.join("other.json"), "{}\n").unwrap();
+    commit(repo_dir, "unrelated");
+    run_git(&["checkout", "main"], repo_dir);
+    fs::write(repo_dir.join("README.md"), "# Demo\n").unwrap();
+    commit(repo_dir, "readme");
+    merge_without_conflicts(repo_dir, &["unrelated"]);
+
+    let merge_history = |args: &[&str]| {
+        let assert = Command::cargo_bin(pkg_name!())
+            .unwrap()
+            .current_dir(repo_dir)
+            .arg("merge-history")
+            .args(args)
+            .assert()
+            .success();
+        String::from_utf8(assert.get_output().stdout.clone()).unwrap()
+    };
+
+    let csv = merge_history(&[]);
+    let rows: Vec<_> = csv.lines().collect();
+    assert_eq!(rows.len(), 3, "unexpected CSV: {csv}");
+    assert_eq!(
+        rows[0],
+        "commit,files,line_based_conflicts,mergiraf_conflicts"
+    );
+    assert!(rows[1].ends_with(",0,0,0"), "unexpected row: {}", rows[1]);
+    assert!(rows[2].ends_with(",1,2,0"), "unexpected row: {}", rows[2]);
+
+    // only the most recent merge is replayed
+    let csv = merge_history(&["--max-commits=1", "main"]);
+    assert_eq!(csv.lines().count(), 2, "unexpected CSV: {csv}");
+}
@@ -1,10 +1,16 @@
 use assert_cmd::prelude::*;
-use mergiraf::{EXIT_SOLVE_HAS_CONFLICTS, git, utils::write_string_to_file};
+use mergiraf::{
+    ENABLING_ENV_VAR, EXIT_MERGE_HAS_CONFLICTS, EXIT_MERGE_TOO_MANY_CONFLICTS,
+    EXIT_MERGE_UNEXPECTED_OUTPUT, EXIT_SOLVE_HAS_CONFLICTS, EXIT_SUCCESS, MergedChunk, git,
+    merge::DEFAULT_TIMEOUT, merge_files, settings::DisplaySettings, utils::write_string_to_file,
+};
 use std::fs;
 use std::path::{Path, PathBuf};
 
 mod common;
-use common::{DEFAULT_FILE_FOR_SOLVE, create_file_for_solve, create_files_for_merge, merge, solve};
+use common::{
+    DEFAULT_FILE_FOR_SOLVE, create_file_for_solve, create_files_for_merge, merge, run_git, solve,
+};
 
 #[test]
 fn keep_backup_keeps_backup() {
@@ -92,6 +98,28 @@ fn manual_language_selection_for_merge() {
         .stdout("[0, 1, 2, 3, 4]\n");
 }
 
+#[test]
+fn language_detection_from_windows_path_name() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) = create_files_for_merge(
+        repo_path,
+        "use a;\n",
+        "use a;\nuse b;\n",
+        "use c;\nuse a;\n",
+    );
+
+    merge()
+        .arg("--path-name=src\\build.d\\main.rs")
+        .arg(base_file_abs_path)
+        .arg(left_file_abs_path)
+        .arg(right_file_abs_path)
+        .assert()
+        .success()
+        .stdout("use c;\nuse a;\nuse b;\n");
+}
+
 #[test]
 fn debug_dir_is_created_for_solve() {
     let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
@@ -140,7 +168,16 @@ fn debug_dir_is_created_for_merge() {
         .assert()
         .success();
 
-    assert!(fs::exists(debug_dir_path).unwrap());
+    assert!(fs::exists(&debug_dir_path).unwrap());
+
+    let timings = fs::read_to_string(debug_dir_path.join("timings.json"))
+        .expect("the timings of the merge phases should be written");
+    for phase in ["parse", "match", "build", "render", "check"] {
+        assert!(
+            timings.contains(&format!("\"{phase}\": ")),
+            "missing {phase} in {timings}"
+        );
+    }
 }
 
 #[test]
@@ -190,6 +227,29 @@ fn line_ending_preservation_for_merge() {
         .stdout("[0, 1, 2, 3, 4]\r\n");
 }
 
+#[test]
+fn mixed_line_endings_preservation_for_merge() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) = create_files_for_merge(
+        repo_path,
+        "x = 1\r\ny = 2\nz = 3\r\nw = 4\r\n",
+        "x = 10\r\ny = 2\nz = 3\r\nw = 4\r\n",
+        "x = 1\r\ny = 2\nz = 3\r\nw = 40\n",
+    );
+
+    // each line keeps its own line ending, the new ones getting the most common one
+    merge()
+        .arg("--language=python")
+        .arg(base_file_abs_path)
+        .arg(left_file_abs_path)
+        .arg(right_file_abs_path)
+        .assert()
+        .success()
+        .stdout("x = 10\r\ny = 2\nz = 3\r\nw = 40\r\n");
+}
+
 fn create_iso8859_input_files(repo_path: &Path) -> (PathBuf, PathBuf, PathBuf, PathBuf) {
     create_files_for_merge(
         repo_path,
@@ -326,7 +386,54 @@ class OtherClass {
         .assert()
         .success()
         .stdout(contents_expected)
-        .stderr("WARN left side contains conflict markers, falling back to Git\n");
+        .stderr("WARN left side contains conflict markers, falling back to line-based merging\n");
+}
+
+#[test]
+fn merging_files_with_marker_like_lines_without_parsing_markers() {
+    let fixture = "\
+const FIXTURE: &str = \"
+<<<<<<< LEFT
+a
+=======
+b
+>>>>>>> RIGHT
+\";
+";
+    let contents_base = format!("{fixture}\nfn foo() {{\n    let x = 1;\n}}\n");
+    let contents_left = format!("{fixture}\nfn foo() {{\n    let x = 2;\n}}\n");
+    let contents_right = format!("{fixture}\nfn foo() {{\n    let x = 3;\n}}\n");
+    let contents_expected = format!(
+        "\
+{fixture}
+fn foo() {{
+<<<<<<<< LEFT
+    let x = 2;
+|||||||| BASE
+    let x = 1;
+========
+    let x = 3;
+>>>>>>>> RIGHT
+}}
+"
+    );
+
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) =
+        create_files_for_merge(repo_path, contents_base, contents_left, contents_right);
+
+    merge()
+        .arg("--language=rust")
+        .arg("--no-parse-markers")
+        .args(["-s", "BASE", "-x", "LEFT", "-y", "RIGHT"])
+        .arg(base_file_abs_path)
+        .arg(left_file_abs_path)
+        .arg(right_file_abs_path)
+        .assert()
+        .code(1)
+        .stdout(contents_expected);
 }
 
 #[test]
@@ -420,3 +527,1009 @@ fn solve_respects_conflict_marker_size_attr() {
         .code(0)
         .stdout(contents_after_solve);
 }
+
+#[test]
+fn solve_writes_remaining_conflicts_to_reject_file() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let contents = r#"{
+  "name": "demo",
+<<<<<<< LEFT
+  "version": "1.1",
+||||||| BASE
+  "version": "1.0",
+=======
+  "version": "2.0",
+>>>>>>> RIGHT
+  "deps": {
+<<<<<<< LEFT
+    "foo": "1",
+    "bar": "1"
+||||||| BASE
+    "foo": "1"
+=======
+    "foo": "1",
+    "baz": "1"
+>>>>>>> RIGHT
+  }
+}
+"#;
+    let conflict_path = create_file_for_solve(repo_path, contents);
+
+    solve()
+        .arg("--language=json")
+        .arg("--reject")
+        .arg(&conflict_path)
+        .assert()
+        .code(EXIT_SOLVE_HAS_CONFLICTS);
+
+    let solved = fs::read_to_string(&conflict_path).expect("couldn't read the solved file");
+    assert_eq!(
+        solved,
+        r#"{
+  "name": "demo",
+  "version": "1.1",
+  "deps": {
+    "foo": "1",
+    "bar": "1",
+    "baz": "1"
+  }
+}
+"#
+    );
+
+    let rejects = fs::read_to_string(conflict_path.with_extension("txt.rej"))
+        .expect("couldn't read the reject file");
+    assert_eq!(
+        rejects,
+        r#"@@ line 3 @@
+<<<<<<< LEFT
+  "version": "1.1",
+||||||| BASE
+  "version": "1.0",
+=======
+  "version": "2.0",
+>>>>>>> RIGHT
+"#
+    );
+}
+
+#[test]
+fn reject_file_keeps_line_endings_of_conflict_file() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let contents = "<<<<<<< LEFT\r\n{\"version\": \"1.1\"}\r\n||||||| BASE\r\n{\"version\": \"1.0\"}\r\n=======\r\n{\"version\": \"2.0\"}\r\n>>>>>>> RIGHT\r\n";
+    let conflict_path = create_file_for_solve(repo_path, contents);
+
+    solve()
+        .arg("--language=json")
+        .arg("--reject")
+        .arg(&conflict_path)
+        .assert()
+        .code(EXIT_SOLVE_HAS_CONFLICTS);
+
+    let rejects = fs::read_to_string(conflict_path.with_extension("txt.rej"))
+        .expect("couldn't read the reject file");
+    assert!(rejects.contains("<<<<<<< LEFT\r\n"));
+    assert!(!rejects.replace("\r\n", "").contains('\n'));
+}
+
+#[test]
+fn quiet_flag_suppresses_warnings() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) =
+        create_files_for_merge(repo_path, "{\"a\": 1}\n", "{\"a\": 2}\n", "{\"a\": 3}\n");
+
+    // `%S` is what old Git versions pass instead of the revision name,
+    // which makes Mergiraf warn about it on conflicts
+    let assert = merge()
+        .arg("--language=json")
+        .args(["-s", "%S", "-x", "%X", "-y", "%Y"])
+        .arg(&base_file_abs_path)
+        .arg(&left_file_abs_path)
+        .arg(&right_file_abs_path)
+        .assert()
+        .code(EXIT_MERGE_HAS_CONFLICTS);
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(
+        stderr.contains("Git v2.44.0"),
+        "unexpected stderr: {stderr}"
+    );
+
+    merge()
+        .arg("--quiet")
+        .arg("--language=json")
+        .args(["-s", "%S", "-x", "%X", "-y", "%Y"])
+        .arg(base_file_abs_path)
+        .arg(left_file_abs_path)
+        .arg(right_file_abs_path)
+        .assert()
+        .code(EXIT_MERGE_HAS_CONFLICTS)
+        .stderr("");
+}
+
+#[test]
+fn max_conflicts_aborts_or_falls_back_to_line_based_merge() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) = create_files_for_merge(
+        repo_path,
+        "{\n  \"a\": 1,\n  \"b\": 1,\n  \"c\": 1\n}\n",
+        "{\n  \"a\": 2,\n  \"b\": 2,\n  \"c\": 2\n}\n",
+        "{\n  \"a\": 3,\n  \"b\": 3,\n  \"c\": 3\n}\n",
+    );
+    let output_file_abs_path = repo_path.join("output.json");
+
+    let merge_with_max_conflicts = |max_conflicts: &str| {
+        let mut merge = merge();
+        merge
+            .arg("--language=json")
+            .arg(format!("--max-conflicts={max_conflicts}"))
+            .arg(&base_file_abs_path)
+            .arg(&left_file_abs_path)
+            .arg(&right_file_abs_path)
+            .arg("--output")
+            .arg(&output_file_abs_path);
+        merge
+    };
+
+    // below the threshold, the structured merge is output as usual
+    merge_with_max_conflicts("3")
+        .assert()
+        .code(EXIT_MERGE_HAS_CONFLICTS);
+    let output = fs::read_to_string(&output_file_abs_path).expect("no output file");
+    assert_eq!(output.matches("<<<<<<<").count(), 3);
+    fs::remove_file(&output_file_abs_path).unwrap();
+
+    // above it, the result is discarded
+    merge_with_max_conflicts("2")
+        .assert()
+        .code(EXIT_MERGE_TOO_MANY_CONFLICTS);
+    assert!(!output_file_abs_path.exists());
+
+    // unless we ask for a line-based merge instead
+    merge_with_max_conflicts("2")
+        .arg("--line-based-fallback")
+        .assert()
+        .code(EXIT_MERGE_TOO_MANY_CONFLICTS);
+    let output = fs::read_to_string(&output_file_abs_path).expect("no output file");
+    assert_eq!(output.matches("<<<<<<<").count(), 1);
+}
+
+#[test]
+fn prefer_resolves_remaining_conflicts_with_one_side() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) = create_files_for_merge(
+        repo_path,
+        "{\n  \"a\": 1,\n  \"b\": 1\n}\n",
+        "{\n  \"a\": 2,\n  \"b\": 1,\n  \"c\": 1\n}\n",
+        "{\n  \"a\": 3,\n  \"b\": 1,\n  \"d\": 1\n}\n",
+    );
+
+    let assert = merge()
+        .arg("--language=json")
+        .arg("--prefer=left")
+        .arg(&base_file_abs_path)
+        .arg(&left_file_abs_path)
+        .arg(&right_file_abs_path)
+        .assert()
+        .code(EXIT_SUCCESS);
+    let output = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert_eq!(
+        output,
+        "{\n  \"a\": 2,\n  \"b\": 1,\n  \"c\": 1,\n  \"d\": 1\n}\n"
+    );
+}
+
+#[test]
+fn solve_check_reports_without_writing() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let solvable_path = repo_path.join("solvable.json");
+    fs::write(&solvable_path, DEFAULT_FILE_FOR_SOLVE).expect("failed to write solvable file");
+    let unsolvable_contents = "\
+<<<<<<< LEFT
+{\"version\": \"1.1\"}
+||||||| BASE
+{\"version\": \"1.0\"}
+=======
+{\"version\": \"2.0\"}
+>>>>>>> RIGHT
+";
+    let unsolvable_path = repo_path.join("unsolvable.json");
+    fs::write(&unsolvable_path, unsolvable_contents).expect("failed to write unsolvable file");
+
+    solve()
+        .current_dir(repo_path)
+        .arg("--check")
+        .arg("solvable.json")
+        .assert()
+        .success()
+        .stdout("solvable.json: all conflicts can be solved\n");
+
+    solve()
+        .current_dir(repo_path)
+        .arg("--check")
+        .arg("unsolvable.json")
+        .assert()
+        .code(EXIT_SOLVE_HAS_CONFLICTS)
+        .stdout("unsolvable.json: 1 conflict(s) would remain\n");
+
+    // neither the files nor their backups were written
+    assert_eq!(
+        fs::read_to_string(&solvable_path).unwrap(),
+        DEFAULT_FILE_FOR_SOLVE
+    );
+    assert_eq!(
+        fs::read_to_string(&unsolvable_path).unwrap(),
+        unsolvable_contents
+    );
+    assert_eq!(fs::read_dir(repo_path).unwrap().count(), 2);
+
+    // `--check` doesn't write any file, so it can't log the remaining conflicts either
+    solve()
+        .current_dir(repo_path)
+        .arg("--check")
+        .arg("--conflict-log=conflicts.log")
+        .arg("unsolvable.json")
+        .assert()
+        .code(2);
+    assert!(!repo_path.join("conflicts.log").exists());
+}
+
+#[test]
+fn commutative_only_restricts_commutative_parents() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) = create_files_for_merge(
+        repo_path,
+        "use std::fmt;\n\nstruct S;\n\nimpl S {\n    fn a() {}\n}\n",
+        "use std::fmt;\nuse std::io;\n\nstruct S;\n\nimpl S {\n    fn a() {}\n\n    fn b() {}\n}\n",
+        "use std::fmt;\nuse std::fs;\n\nstruct S;\n\nimpl S {\n    fn a() {}\n\n    fn c() {}\n}\n",
+    );
+
+    // the imports can still be merged commutatively, but not the methods
+    let assert = merge()
+        .arg("--language=rust")
+        .arg("--commutative-only=source_file")
+        .arg(base_file_abs_path)
+        .arg(left_file_abs_path)
+        .arg(right_file_abs_path)
+        .assert()
+        .code(EXIT_MERGE_HAS_CONFLICTS);
+    let merged = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(
+        merged.starts_with("use std::fmt;\nuse std::io;\nuse std::fs;\n"),
+        "unexpected merge: {merged}"
+    );
+    assert_eq!(merged.matches("<<<<<<<").count(), 1);
+}
+
+#[test]
+fn max_conflict_size_falls_back_on_line_based_merge() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let contents_base = "fn foo() {\n    let a = 1;\n    let b = 2;\n    let c = 3;\n}\n";
+    let contents_left = contents_base.replace(
+        "2;",
+        "compute_the_left_value(a, Some(\"first\"), Some(\"second\"));",
+    );
+    let contents_right =
+        contents_base.replace("2;", "compute_the_right_value(a, None, Some(\"third\"));");
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) =
+        create_files_for_merge(repo_path, contents_base, &contents_left, &contents_right);
+
+    let merge_output = |max_conflict_size: Option<&str>| {
+        let mut merge = merge();
+        if let Some(size) = max_conflict_size {
+            merge.arg(format!("--max-conflict-size={size}"));
+        }
+        let assert = merge
+            .arg("--language=rust")
+            .arg("--compact")
+            .arg(&base_file_abs_path)
+            .arg(&left_file_abs_path)
+            .arg(&right_file_abs_path)
+            .assert()
+            .code(EXIT_MERGE_HAS_CONFLICTS);
+        String::from_utf8_lossy(&assert.get_output().stdout).into_owned()
+    };
+
+    // the structured conflict is limited to the initializers of `b`
+    let structured = merge_output(None);
+    assert!(structured.contains("    let b =\n<<<<<<<"), "{structured}");
+    assert_eq!(merge_output(Some("1000")), structured);
+    // with a cap on its size, the whole statement is merged line by line instead
+    let capped = merge_output(Some("20"));
+    assert!(
+        capped.contains("    let a = 1;\n<<<<<<<") && capped.contains("    let b = 2;\n======="),
+        "{capped}"
+    );
+
+    // `mergiraf solve` honors it too
+    let conflicts_file_abs_path = create_file_for_solve(repo_path, &capped);
+    let solve_output = |max_conflict_size: Option<&str>| {
+        let mut solve = solve();
+        if let Some(size) = max_conflict_size {
+            solve.arg(format!("--max-conflict-size={size}"));
+        }
+        let assert = solve
+            .arg("--language=rust")
+            .arg("--compact")
+            .arg("--stdout")
+            .arg(&conflicts_file_abs_path)
+            .assert()
+            .code(EXIT_SOLVE_HAS_CONFLICTS);
+        String::from_utf8_lossy(&assert.get_output().stdout).into_owned()
+    };
+    let solved = solve_output(None);
+    assert!(solved.contains("    let b =\n<<<<<<<"), "{solved}");
+    let solved = solve_output(Some("20"));
+    assert!(solved.contains("    let b = 2;\n======="), "{solved}");
+}
+
+#[test]
+fn diff3_flag_forces_base_section_in_git_fallback() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) =
+        create_files_for_merge(repo_path, "{\"a\": 1}\n", "{\"a\": 2}\n", "{\"a\": 3}\n");
+
+    let merge_output = |disabled: bool, diff3: bool| {
+        let mut merge = merge();
+        if disabled {
+            merge.env(ENABLING_ENV_VAR, "0");
+        }
+        if diff3 {
+            merge.arg("--diff3");
+        }
+        let assert = merge
+            .arg("--language=json")
+            .arg(&base_file_abs_path)
+            .arg(&left_file_abs_path)
+            .arg(&right_file_abs_path)
+            .assert()
+            .code(EXIT_MERGE_HAS_CONFLICTS);
+        String::from_utf8_lossy(&assert.get_output().stdout).into_owned()
+    };
+
+    // `git merge-file` doesn't show the base by default
+    assert!(!merge_output(true, false).contains("|||||||"));
+    assert!(merge_output(true, true).contains("|||||||"));
+    // Mergiraf's own conflicts show it in any case
+    assert!(merge_output(false, false).contains("|||||||"));
+    assert!(merge_output(false, true).contains("|||||||"));
+}
+
+#[test]
+fn conflict_comment_annotates_conflicts() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) = create_files_for_merge(
+        repo_path,
+        "class A {\n    int x = 1;\n}\n",
+        "class A {\n    int x = 2;\n}\n",
+        "class A {\n    int x = 3;\n}\n",
+    );
+
+    merge()
+        .arg("--language=java")
+        .arg("--conflict-comment")
+        .args(["-s", "BASE", "-x", "LEFT", "-y", "RIGHT"])
+        .arg(base_file_abs_path)
+        .arg(left_file_abs_path)
+        .arg(right_file_abs_path)
+        .assert()
+        .code(EXIT_MERGE_HAS_CONFLICTS)
+        .stdout(
+            "\
+class A {
+/* mergiraf:conflict id=1 */
+<<<<<<< LEFT
+    int x = 2;
+||||||| BASE
+    int x = 1;
+=======
+    int x = 3;
+>>>>>>> RIGHT
+}
+",
+        );
+
+    let test_file_abs_path = create_file_for_solve(
+        repo_path,
+        "\
+def f():
+<<<<<<< LEFT
+    return 2
+||||||| BASE
+    return 1
+=======
+    return 3
+>>>>>>> RIGHT
+",
+    );
+
+    solve()
+        .arg("--language=python")
+        .arg("--conflict-comment")
+        .arg("--stdout")
+        .arg(test_file_abs_path)
+        .assert()
+        .code(EXIT_SOLVE_HAS_CONFLICTS)
+        .stdout(
+            "\
+def f():
+# mergiraf:conflict id=1
+<<<<<<< LEFT
+    return 2
+||||||| BASE
+    return 1
+=======
+    return 3
+>>>>>>> RIGHT
+",
+        );
+}
+
+#[test]
+fn range_restricts_structured_merge_to_enclosing_nodes() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    // a file large enough for a full structured merge to be slow,
+    // with a single change on each side, to the same method
+    let big_class = |changed_signature: &str| {
+        let mut contents = "class Big {\n".to_string();
+        for i in 0..2000 {
+            if i == 1000 {
+                contents.push_str(changed_signature);
+            } else {
+                contents.push_str(&format!("    void m{i}(int a) {{\n"));
+            }
+            contents.push_str(&format!("        call({i});\n    }}\n\n"));
+        }
+        contents.push_str("}\n");
+        contents
+    };
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) = create_files_for_merge(
+        repo_path,
+        big_class("    void m1000(int a) {\n"),
+        big_class("    public void m1000(int a) {\n"),
+        big_class("    void m1000(int a) throws Exception {\n"),
+    );
+
+    // the lines of the line-based conflict
+    merge()
+        .arg("--language=java")
+        .arg("--range=4002:4008")
+        .arg(&base_file_abs_path)
+        .arg(&left_file_abs_path)
+        .arg(&right_file_abs_path)
+        .assert()
+        .success()
+        .stdout(big_class(
+            "    public void m1000(int a) throws Exception {\n",
+        ));
+
+    // lines elsewhere in the file leave the conflict alone
+    let assert = merge()
+        .arg("--language=java")
+        .arg("--range=42:42")
+        .arg(base_file_abs_path)
+        .arg(left_file_abs_path)
+        .arg(right_file_abs_path)
+        .assert()
+        .code(EXIT_MERGE_HAS_CONFLICTS);
+    let merged = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert_eq!(merged.matches("<<<<<<<").count(), 1);
+    assert!(merged.contains("    public void m1000(int a) {\n"));
+}
+
+#[test]
+fn range_can_extend_to_the_largest_line_number() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) =
+        create_files_for_merge(repo_path, "[1, 2]\n", "[0, 1, 2]\n", "[1, 2, 3]\n");
+
+    merge()
+        .arg("--language=json")
+        .arg(format!("--range=1:{}", usize::MAX))
+        .arg(base_file_abs_path)
+        .arg(left_file_abs_path)
+        .arg(right_file_abs_path)
+        .assert()
+        .success()
+        .stdout("[0, 1, 2, 3]\n");
+}
+
+#[test]
+fn trace_node_logs_decisions_about_conflicting_node() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) =
+        create_files_for_merge(repo_path, "[1, 2]\n", "[1, 3, 2]\n", "[1, 4, 2]\n");
+
+    // the node with id 4 in the left revision is the added `3`
+    let assert = merge()
+        .arg("--language=json")
+        .arg("--trace-node=left:4")
+        .arg(base_file_abs_path)
+        .arg(left_file_abs_path)
+        .arg(right_file_abs_path)
+        .assert()
+        .code(EXIT_MERGE_HAS_CONFLICTS);
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(
+        stderr.contains("[trace-node] number:4…5@Left not matched to any node in Base"),
+        "unexpected stderr: {stderr}"
+    );
+    assert!(
+        stderr.contains(
+            "[trace-node] conflict after number:1…2@Base: base: [], \
+            left: [,:2…3@Left, number:4…5@Left], right: [,:2…3@Right, number:4…5@Right]"
+        ),
+        "unexpected stderr: {stderr}"
+    );
+    // nodes of other revisions which happen to have the same id are not traced
+    assert!(
+        !stderr.contains("@Base matched"),
+        "unexpected stderr: {stderr}"
+    );
+}
+
+#[test]
+fn commutative_report_lists_imports_added_on_both_sides() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) = create_files_for_merge(
+        repo_path,
+        "import java.util.List;\nimport java.util.Map;\n\nclass A {}\n",
+        "import java.util.List;\nimport java.util.Map;\nimport java.util.Set;\n\nclass A {}\n",
+        "import java.util.List;\nimport java.util.Map;\nimport java.io.File;\n\nclass A {}\n",
+    );
+
+    let assert = merge()
+        .arg("--language=java")
+        .arg("--commutative-report")
+        .arg(base_file_abs_path)
+        .arg(left_file_abs_path)
+        .arg(right_file_abs_path)
+        .assert()
+        .success();
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr);
+    assert!(
+        stderr.contains(
+            "[commutative-report] program:0…57@Base: combined Signature [[import java.util.Set;]] \
+            added by left with Signature [[import java.io.File;]] added by right"
+        ),
+        "unexpected stderr: {stderr}"
+    );
+}
+
+#[test]
+fn changed_only_outputs_diff_from_base() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let contents_base: String = (1..=20).map(|i| format!("let x{i} = {i};\n")).collect();
+    let contents_left = contents_base.replace("let x2 = 2;", "let x2 = 200;");
+    let contents_right = contents_base.replace("let x19 = 19;", "let x19 = 1900;");
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) =
+        create_files_for_merge(repo_path, &contents_base, &contents_left, &contents_right);
+
+    let assert = merge()
+        .arg("--language=rust")
+        .arg("--changed-only")
+        .arg("--path-name=lib.rs")
+        .arg(&base_file_abs_path)
+        .arg(&left_file_abs_path)
+        .arg(&right_file_abs_path)
+        .assert()
+        .code(EXIT_SUCCESS);
+    let output = String::from_utf8_lossy(&assert.get_output().stdout);
+
+    assert!(output.starts_with("--- a/lib.rs\n+++ b/lib.rs\n"));
+    assert!(output.contains("-let x2 = 2;\n+let x2 = 200;\n"));
+    assert!(output.contains("-let x19 = 19;\n+let x19 = 1900;\n"));
+    // the unchanged lines far from both changes are left out
+    assert!(!output.contains("let x10 = 10;"));
+}
+
+#[test]
+fn changed_only_writes_conflict_log_from_merged_contents() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let contents_base = "class A {\n    int f() {\n        return 1;\n    }\n}\n";
+    let contents_left = contents_base.replace("return 1;", "return 2;");
+    let contents_right = contents_base.replace("return 1;", "return 3;");
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) =
+        create_files_for_merge(repo_path, contents_base, &contents_left, &contents_right);
+    let log_path = repo_path.join("conflicts.log");
+
+    let assert = merge()
+        .arg("--language=java")
+        .arg("--changed-only")
+        .arg("--conflict-log")
+        .arg(&log_path)
+        .arg(&base_file_abs_path)
+        .arg(&left_file_abs_path)
+        .arg(&right_file_abs_path)
+        .assert()
+        .code(EXIT_MERGE_HAS_CONFLICTS);
+    let output = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(output.contains("+<<<<<<<"), "unexpected output: {output}");
+
+    // the markers are found in the merged contents, not in the diff printed instead of them
+    let log = fs::read_to_string(&log_path).expect("the conflict log should have been written");
+    assert_eq!(
+        log,
+        format!(
+            "{}:3: method_declaration Signature [[f], [], []]\n",
+            base_file_abs_path.display()
+        )
+    );
+}
+
+#[test]
+fn detect_language_by_content() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let contents_base = "{\n  \"a\": 1\n}\n";
+    let contents_left = "{\n  \"a\": 1,\n  \"b\": 2\n}\n";
+    let contents_right = "{\n  \"a\": 1,\n  \"c\": 3\n}\n";
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) =
+        create_files_for_merge(repo_path, contents_base, contents_left, contents_right);
+
+    // without the flag, the file without extension is merged line-based
+    merge()
+        .arg("--path-name=data")
+        .arg(&base_file_abs_path)
+        .arg(&left_file_abs_path)
+        .arg(&right_file_abs_path)
+        .assert()
+        .code(EXIT_MERGE_HAS_CONFLICTS);
+
+    let assert = merge()
+        .arg("--path-name=data")
+        .arg("--detect-language-by-content")
+        .arg(&base_file_abs_path)
+        .arg(&left_file_abs_path)
+        .arg(&right_file_abs_path)
+        .assert()
+        .code(EXIT_SUCCESS);
+    let output = String::from_utf8_lossy(&assert.get_output().stdout);
+
+    assert_eq!(output, "{\n  \"a\": 1,\n  \"b\": 2,\n  \"c\": 3\n}\n");
+}
+
+#[test]
+fn dry_run_explains_remaining_conflicts() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let contents_base = "class A {\n    void first() {\n        a = 0;\n    }\n\n    void second() {\n        b = 0;\n    }\n}\n";
+    let contents_left = contents_base.replace("= 0", "= 1");
+    let contents_right = contents_base.replace("= 0", "= 2");
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) =
+        create_files_for_merge(repo_path, contents_base, &contents_left, &contents_right);
+    let output_path = repo_path.join("Merged.java");
+
+    let assert = merge()
+        .arg("--path-name=A.java")
+        .arg("--dry-run")
+        .arg("--explain")
+        .arg(&base_file_abs_path)
+        .arg(&left_file_abs_path)
+        .arg(&right_file_abs_path)
+        .assert()
+        .code(EXIT_MERGE_HAS_CONFLICTS);
+    let output = String::from_utf8_lossy(&assert.get_output().stdout);
+
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines[0], "A.java: 2 conflict(s) would remain");
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("A.java:3: modified on both sides, in method_declaration"));
+    assert!(lines[1].contains("[first]"));
+    assert!(lines[2].contains("[second]"));
+
+    // a dry run refuses to be combined with an output file
+    merge()
+        .arg("--dry-run")
+        .arg("--output")
+        .arg(&output_path)
+        .arg(&base_file_abs_path)
+        .arg(&left_file_abs_path)
+        .arg(&right_file_abs_path)
+        .assert()
+        .failure();
+    assert!(!output_path.exists());
+}
+
+#[test]
+fn stdout_on_conflict() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let contents_base = "[1, 2, 3]\n";
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, output_file_abs_path) =
+        create_files_for_merge(repo_path, contents_base, "[1, 4, 3]\n", "[1, 5, 3]\n");
+    fs::write(&output_file_abs_path, "untouched\n").unwrap();
+
+    // the conflicting merge is printed, not written
+    let assert = merge()
+        .arg("--language=json")
+        .arg("--stdout-on-conflict")
+        .arg("--output")
+        .arg(&output_file_abs_path)
+        .arg(&base_file_abs_path)
+        .arg(&left_file_abs_path)
+        .arg(&right_file_abs_path)
+        .assert()
+        .code(EXIT_MERGE_HAS_CONFLICTS);
+    let output = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(output.contains("<<<<<<<"));
+    assert_eq!(
+        fs::read_to_string(&output_file_abs_path).unwrap(),
+        "untouched\n"
+    );
+
+    // a clean merge is written to the output file as usual
+    fs::write(&right_file_abs_path, "[1, 2, 3, 4]\n").unwrap();
+    let assert = merge()
+        .arg("--language=json")
+        .arg("--stdout-on-conflict")
+        .arg("--output")
+        .arg(&output_file_abs_path)
+        .arg(&base_file_abs_path)
+        .arg(&left_file_abs_path)
+        .arg(&right_file_abs_path)
+        .assert()
+        .code(EXIT_SUCCESS);
+    assert!(assert.get_output().stdout.is_empty());
+    assert_eq!(
+        fs::read_to_string(&output_file_abs_path).unwrap(),
+        "[1, 4, 3, 4]\n"
+    );
+}
+
+#[test]
+fn merge_with_expected_output() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, expected_file_abs_path) =
+        create_files_for_merge(repo_path, "[1, 2, 3]\n", "[0, 1, 2, 3]\n", "[1, 2, 3, 4]\n");
+    let merge_expecting = |expected: &str| {
+        fs::write(&expected_file_abs_path, expected).unwrap();
+        merge()
+            .arg("--language=json")
+            .arg("--expect")
+            .arg(&expected_file_abs_path)
+            .arg(&base_file_abs_path)
+            .arg(&left_file_abs_path)
+            .arg(&right_file_abs_path)
+            .assert()
+    };
+
+    // a matching expected file: nothing is printed
+    let assert = merge_expecting("[0, 1, 2, 3, 4]\n").code(EXIT_SUCCESS);
+    assert!(assert.get_output().stdout.is_empty());
+
+    // a mismatching one: the differences are printed
+    let assert = merge_expecting("[0, 1, 2, 3, 5]\n").code(EXIT_MERGE_UNEXPECTED_OUTPUT);
+    let output = String::from_utf8_lossy(&assert.get_output().stdout);
+    assert!(output.contains("-[0, 1, 2, 3, 5]\n+[0, 1, 2, 3, 4]\n"));
+
+    // the expected file is left untouched
+    assert_eq!(
+        fs::read_to_string(&expected_file_abs_path).unwrap(),
+        "[0, 1, 2, 3, 5]\n"
+    );
+}
+
+#[test]
+fn merge_revisions_from_git() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+    run_git(&["init", "."], repo_path);
+
+    // the revisions are committed one after the other and tagged, the working tree is irrelevant
+    for (tag, contents) in [
+        ("base", "[1, 2, 3]\n"),
+        ("left", "[1, 2, 3, 4]\n"),
+        ("right", "[0, 1, 2, 3]\n"),
+    ] {
+        fs::write(repo_path.join("list.json"), contents).unwrap();
+        run_git(&["add", "list.json"], repo_path);
+        run_git(
+            &[
+                "-c",
+                "user.email=author@example.com",
+                "-c",
+                "user.name=Author",
+                "commit",
+                "--no-gpg-sign",
+                "-m",
+                tag,
+            ],
+            repo_path,
+        );
+        run_git(&["tag", tag], repo_path);
+    }
+    fs::write(repo_path.join("list.json"), "garbage").unwrap();
+
+    merge()
+        .arg("--from-git")
+        .arg("--path-name=list.json")
+        .args(["base", "left", "right"])
+        .current_dir(repo_path)
+        .assert()
+        .code(EXIT_SUCCESS)
+        .stdout("[0, 1, 2, 3, 4]\n");
+
+    merge()
+        .arg("--from-git")
+        .args(["base:list.json", "left:list.json", "right:list.json"])
+        .current_dir(repo_path)
+        .assert()
+        .code(EXIT_SUCCESS)
+        .stdout("[0, 1, 2, 3, 4]\n");
+}
+
+#[test]
+fn commit_subjects_in_conflict_markers() {
+    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_path = repo_dir.path();
+    run_git(&["init", "."], repo_path);
+
+    let mut oids = Vec::new();
+    for (subject, contents) in [
+        ("Initial list", "[1, 2, 3]\n"),
+        ("Replace 2 with 4", "[1, 4, 3]\n"),
+        ("Replace 2 with 5", "[1, 5, 3]\n"),
+    ] {
+        fs::write(repo_path.join("list.json"), contents).unwrap();
+        run_git(&["add", "list.json"], repo_path);
+        run_git(
+            &[
+                "-c",
+                "user.email=author@example.com",
+                "-c",
+                "user.name=Author",
+                "commit",
+                "--no-gpg-sign",
+                "-m",
+                subject,
+            ],
+            repo_path,
+        );
+        let output = std::process::Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(repo_path)
+            .output()
+            .unwrap();
+        oids.push(String::from_utf8(output.stdout).unwrap().trim().to_owned());
+    }
+    let (base_file_abs_path, left_file_abs_path, right_file_abs_path, _) =
+        create_files_for_merge(repo_path, "[1, 2, 3]\n", "[1, 4, 3]\n", "[1, 5, 3]\n");
+
+    let assert = merge()
+        .arg("--language=json")
+        .arg("--commit-subjects")
+        .args(["-s", &oids[0], "-x", &oids[1], "-y", "feature"])
+        .arg(&base_file_abs_path)
+        .arg(&left_file_abs_path)
+        .arg(&right_file_abs_path)
+        .current_dir(repo_path)
+        .assert()
+        .code(EXIT_MERGE_HAS_CONFLICTS);
+    let output = String::from_utf8_lossy(&assert.get_output().stdout);
+
+    assert!(output.contains("<<<<<<< Replace 2 with 4\n"));
+    assert!(output.contains("||||||| Initial list\n"));
+    // names which aren't commit ids are kept
+    assert!(output.contains(">>>>>>> feature\n"));
+}
+
+#[test]
+fn merge_files_held_in_memory() {
+    let settings = DisplaySettings::default();
+
+    let merged = merge_files(
+        "[1, 2]\n",
+        "[1, 2, 3]\n",
+        "[0, 1, 2]\n",
+        Path::new("list.json"),
+        None,
+        &settings,
+        DEFAULT_TIMEOUT,
+    );
+    assert_eq!(merged.contents, "[0, 1, 2, 3]\n");
+    assert_eq!(merged.conflict_count, 0);
+
+    let merged = merge_files(
+        "a\n",
+        "b\n",
+        "c\n",
+        Path::new("notes.txt"),
+        None,
+        &settings,
+        DEFAULT_TIMEOUT,
+    );
+    let parsed = merged.parse(&settings).expect("could not parse the merge");
+    assert_eq!(parsed.conflict_count(), 1);
+    assert!(matches!(
+        parsed.chunks[..],
+        [MergedChunk::Conflict {
+            left: Some("b\n"),
+            base: Some("a\n"),
+            right: Some("c\n"),
+            ..
+        }]
+    ));
+    assert_eq!(parsed.extract_conflict_oids(), None);
+}
+
+#[test]
+fn merge_confidence() {
+    let settings = DisplaySettings::default();
+    let base = "import os\n\ndef f():\n    return 1\n";
+
+    // the imports added on both sides are merged structurally
+    let structured = merge_files(
+        base,
+        "import os\nimport sys\n\ndef f():\n    return 1\n",
+        "import os\nimport re\n\ndef f():\n    return 1\n",
+        Path::new("script"),
+        Some("python"),
+        &settings,
+        DEFAULT_TIMEOUT,
+    );
+    assert_eq!(structured.conflict_count, 0);
+    assert_eq!(structured.confidence(), 1.0);
+
+    // the same changes, in a language Mergiraf can only merge line by line
+    let line_based = merge_files(
+        "a\nb\n",
+        "a\nc\nb\n",
+        "a\nd\nb\n",
+        Path::new("notes.txt"),
+        None,
+        &settings,
+        DEFAULT_TIMEOUT,
+    );
+    assert_eq!(line_based.conflict_count, 1);
+    assert_eq!(line_based.confidence(), 0.0);
+
+    // a conflict which can only be narrowed down
+    let narrowed = merge_files(
+        base,
+        "import os\n\ndef f():\n    return 2\n",
+        "import os\n\ndef f():\n    return 3\n",
+        Path::new("script"),
+        Some("python"),
+        &settings,
+        DEFAULT_TIMEOUT,
+    );
+    assert_eq!(narrowed.conflict_count, 1);
+    assert!(0.0 < narrowed.confidence() && narrowed.confidence() < 0.5);
+}
@@ -1,5 +1,5 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use mergiraf::newline::normalize_to_lf;
@@ -12,21 +12,20 @@ use common::{detect_test_suffix, run_git, write_file_from_rev};
 
 use crate::common::language_override_for_test;
 
-/// End-to-end test for the "mergiraf solve" command
-#[rstest]
-#[case("merge")]
-#[case("diff3")]
-fn solve_command(#[case] conflict_style: &str) {
-    let test_dir = Path::new("examples/java/working/demo");
-    let suffix = detect_test_suffix(test_dir);
-
+/// Creates a Git repository in a temporary directory, in which rebasing the `Right` revision
+/// of the test case on top of the `Left` one leaves the file conflicted
+fn rebase_with_conflicts(
+    test_dir: &Path,
+    suffix: &str,
+    conflict_style: &str,
+) -> (tempfile::TempDir, PathBuf) {
     // create temp directory
-    let repo_dir = tempfile::tempdir().expect("failed to create the temp dir");
-    let repo_dir = repo_dir.path();
+    let tmp_dir = tempfile::tempdir().expect("failed to create the temp dir");
+    let repo_dir = tmp_dir.path();
     // init git repository
     run_git(&["init", "."], repo_dir);
     run_git(&["checkout", "-b", "first_branch"], repo_dir);
-    let file_name = write_file_from_rev(repo_dir, test_dir, "Base", &suffix);
+    let file_name = write_file_from_rev(repo_dir, test_dir, "Base", suffix);
     run_git(&["add", &file_name.to_string_lossy()], repo_dir);
     run_git(
         &[
@@ -41,7 +40,7 @@ fn solve_command(#[case] conflict_style: &str) {
         ],
         repo_dir,
     );
-    write_file_from_rev(repo_dir, test_dir, "Left", &suffix);
+    write_file_from_rev(repo_dir, test_dir, "Left", suffix);
     run_git(
         &[
             "-c",
@@ -57,7 +56,7 @@ fn solve_command(#[case] conflict_style: &str) {
     );
     run_git(&["checkout", "HEAD~"], repo_dir);
     run_git(&["checkout", "-b", "second_branch"], repo_dir);
-    write_file_from_rev(repo_dir, test_dir, "Right", &suffix);
+    write_file_from_rev(repo_dir, test_dir, "Right", suffix);
     run_git(
         &[
             "-c",
@@ -87,6 +86,19 @@ fn solve_command(#[case] conflict_style: &str) {
     let output = command.output().expect("Failed to execute git command");
     assert!(!output.status.success(), "expected a rebase conflict");
 
+    (tmp_dir, file_name)
+}
+
+/// End-to-end test for the "mergiraf solve" command
+#[rstest]
+#[case("merge")]
+#[case("diff3")]
+fn solve_command(#[case] conflict_style: &str) {
+    let test_dir = Path::new("examples/java/working/demo");
+    let suffix = detect_test_suffix(test_dir);
+    let (repo_dir, file_name) = rebase_with_conflicts(test_dir, &suffix, conflict_style);
+    let repo_dir = repo_dir.path();
+
     // call mergiraf to the rescue
     let conflicts_contents =
         fs::read_to_string(repo_dir.join(&file_name)).expect("could not read the conflicts");
@@ -108,3 +120,45 @@ fn solve_command(#[case] conflict_style: &str) {
     let expected_result = normalize_to_lf(expected_result);
     assert_eq!(merge_result.contents, expected_result);
 }
+
+#[test]
+fn solve_from_index() {
+    let test_dir = Path::new("examples/java/working/demo");
+    let suffix = detect_test_suffix(test_dir);
+    let (repo_dir, file_name) = rebase_with_conflicts(test_dir, &suffix, "merge");
+    let repo_dir = repo_dir.path();
+
+    // the conflict markers are not even looked at
+    let merge_result = solve::solve(
+        &file_name,
+        "not a merge with conflicts\n",
+        CliOpts {
+            language: language_override_for_test(test_dir),
+            from_index: true,
+            ..Default::default()
+        },
+        repo_dir,
+        None,
+    )
+    .expect("solving the conflicts returned an error");
+
+    let expected_result = fs::read_to_string(test_dir.join(format!("Expected{suffix}")))
+        .expect("could not read the expected results");
+    assert_eq!(merge_result.contents, normalize_to_lf(expected_result));
+    assert_eq!(merge_result.conflict_count, 0);
+
+    // outside of a conflicted repository, the revisions can't be found
+    let not_a_repo = tempfile::tempdir().expect("failed to create the temp dir");
+    let error = solve::solve(
+        &file_name,
+        "",
+        CliOpts {
+            from_index: true,
+            ..Default::default()
+        },
+        not_a_repo.path(),
+        None,
+    )
+    .expect_err("there is no Git index to read the revisions from");
+    assert!(error.contains("Git index"), "{error}");
+}
@@ -13,7 +13,7 @@ use rstest::rstest;
 mod common;
 use common::detect_test_suffix;
 
-use crate::common::language_override_for_test;
+use crate::common::{commute_order_sensitive_for_test, language_override_for_test};
 
 fn compare_against_merge(
     test_dir: &Path,
@@ -36,7 +36,9 @@ fn compare_against_merge(
         None,
         CliOpts {
             allow_parse_errors: Some(true),
+            commute_order_sensitive: commute_order_sensitive_for_test(test_dir),
             language: language_override_for_test(test_dir),
+            ..Default::default()
         },
         None,
         None,